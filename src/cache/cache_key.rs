@@ -0,0 +1,224 @@
+use crate::geo::geo_utils::get_tile_range;
+use std::fmt::{self, Formatter};
+use std::path::PathBuf;
+
+const KEY_PREFIX: &str = "tile";
+
+/// Inclusive `(min_x, min_y, max_x, max_y)` XYZ tile range
+type TileRange = (u32, u32, u32, u32);
+
+/// Lowercases layer names and replaces anything but letters, digits, `_`, `-` and the `,`
+/// between combined layers, so the layer is safe in Redis keys and paths. Space around combined
+/// layers is dropped.
+fn normalize_layer(layer: &str) -> String {
+    layer
+        .split(',')
+        .map(|layer| {
+            layer
+                .trim()
+                .to_lowercase()
+                .chars()
+                .map(|char| match char {
+                    'a'..='z' | '0'..='9' | '_' | '-' => char,
+                    _ => '_',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Key of a cached tile: its layer, coordinates and a hash of everything else the tile depends
+/// on, such as the query and format
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Layer name, or comma separated layers for a combined tile
+    pub layer: String,
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    pub variant: String,
+}
+
+impl CacheKey {
+    pub fn new(layer: &str, x: u32, y: u32, z: u32, variant: String) -> Self {
+        Self {
+            layer: normalize_layer(layer),
+            x,
+            y,
+            z,
+            variant,
+        }
+    }
+
+    /// Parses a key written by `Display`
+    pub fn parse(key: &str) -> Option<Self> {
+        let key = key.strip_prefix(KEY_PREFIX)?.strip_prefix(':')?;
+        let mut parts = key.rsplitn(5, ':');
+        let variant = parts.next()?.to_string();
+        let y = parts.next()?.parse().ok()?;
+        let x = parts.next()?.parse().ok()?;
+        let z = parts.next()?.parse().ok()?;
+        let layer = parts.next()?.to_string();
+        Some(Self {
+            layer,
+            x,
+            y,
+            z,
+            variant,
+        })
+    }
+
+    /// `layer/z/x/y/variant` path relative to a cache directory
+    pub fn path(&self) -> PathBuf {
+        [
+            self.layer.clone(),
+            self.z.to_string(),
+            self.x.to_string(),
+            self.y.to_string(),
+            self.variant.clone(),
+        ]
+        .iter()
+        .collect()
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}:{}",
+            KEY_PREFIX, self.layer, self.z, self.x, self.y, self.variant
+        )
+    }
+}
+
+/// Selects cached tiles to purge: every tile, the tiles of a layer, optionally limited to tile
+/// ranges at some zooms. Combined tiles match when any of their layers does.
+#[derive(Clone, Debug, Default)]
+pub struct CacheKeyFilter {
+    layer: Option<String>,
+    /// Tile ranges by zoom, `None` for every tile
+    tiles: Option<Vec<(u32, TileRange)>>,
+}
+
+impl CacheKeyFilter {
+    pub fn layer(layer: Option<&str>) -> Self {
+        Self {
+            layer: layer.map(normalize_layer),
+            tiles: None,
+        }
+    }
+
+    pub fn tile(layer: Option<&str>, x: u32, y: u32, z: u32) -> Self {
+        Self {
+            layer: layer.map(normalize_layer),
+            tiles: Some(vec![(z, (x, y, x, y))]),
+        }
+    }
+
+    /// Tiles covering a longitude/latitude bounding box at every zoom of the range
+    pub fn bbox(layer: Option<&str>, bbox: &[f64; 4], min_zoom: u32, max_zoom: u32) -> Self {
+        Self {
+            layer: layer.map(normalize_layer),
+            tiles: Some(
+                (min_zoom..=max_zoom.min(31))
+                    .map(|z| (z, get_tile_range(bbox, z)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Whether a key's layer component, a single or combined layer, is selected
+    pub fn matches_layer(&self, layer: &str) -> bool {
+        match &self.layer {
+            Some(selected) => layer.split(',').any(|layer| layer == selected),
+            None => true,
+        }
+    }
+
+    /// Selected tile range at a zoom, `None` when no tile of the zoom is selected
+    pub fn tile_range(&self, z: u32) -> Option<TileRange> {
+        match &self.tiles {
+            Some(tiles) => tiles
+                .iter()
+                .find(|(zoom, _)| *zoom == z)
+                .map(|(_, range)| *range),
+            None => Some((0, 0, u32::MAX, u32::MAX)),
+        }
+    }
+
+    pub fn matches(&self, key: &CacheKey) -> bool {
+        let Some((min_x, min_y, max_x, max_y)) = self.tile_range(key.z) else {
+            return false;
+        };
+        self.matches_layer(&key.layer)
+            && (min_x..=max_x).contains(&key.x)
+            && (min_y..=max_y).contains(&key.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(layer: &str, x: u32, y: u32, z: u32) -> CacheKey {
+        CacheKey::new(layer, x, y, z, "variant".to_string())
+    }
+
+    #[test]
+    fn normalizes_layers_for_keys_and_paths() {
+        assert_eq!(key("Roads", 0, 0, 0).layer, "roads");
+        assert_eq!(key("a b/../c", 0, 0, 0).layer, "a_b____c");
+        assert_eq!(key("Roads, Water ,poi", 0, 0, 0).layer, "roads,water,poi");
+    }
+
+    #[test]
+    fn parses_displayed_keys() {
+        let key = key("roads,water", 3, 5, 4);
+
+        assert_eq!(key.to_string(), "tile:roads,water:4:3:5:variant");
+        assert_eq!(CacheKey::parse(&key.to_string()), Some(key));
+        assert_eq!(CacheKey::parse("tile:roads:4:3:variant"), None);
+        assert_eq!(CacheKey::parse("other:roads:4:3:5:variant"), None);
+    }
+
+    #[test]
+    fn combined_keys_match_filters_of_any_of_their_layers() {
+        let key = key("roads, water", 3, 5, 4);
+
+        assert!(CacheKeyFilter::layer(Some("water")).matches(&key));
+        assert!(CacheKeyFilter::layer(Some("Roads")).matches(&key));
+        assert!(!CacheKeyFilter::layer(Some("road")).matches(&key));
+        assert!(CacheKeyFilter::layer(None).matches(&key));
+    }
+
+    #[test]
+    fn tile_filters_match_one_tile() {
+        let filter = CacheKeyFilter::tile(Some("roads"), 3, 5, 4);
+
+        assert!(filter.matches(&key("roads", 3, 5, 4)));
+        assert!(!filter.matches(&key("roads", 3, 5, 5)));
+        assert!(!filter.matches(&key("roads", 4, 5, 4)));
+    }
+
+    #[test]
+    fn bbox_filters_expand_to_the_covering_tiles_of_each_zoom() {
+        let filter = CacheKeyFilter::bbox(None, &[-1.0, -1.0, 1.0, 1.0], 0, 2);
+
+        assert_eq!(filter.tile_range(0), Some((0, 0, 0, 0)));
+        assert_eq!(filter.tile_range(1), Some((0, 0, 1, 1)));
+        assert_eq!(filter.tile_range(2), Some((1, 1, 2, 2)));
+        assert_eq!(filter.tile_range(3), None);
+        assert!(filter.matches(&key("roads", 2, 1, 2)));
+        assert!(!filter.matches(&key("roads", 0, 1, 2)));
+    }
+
+    #[test]
+    fn bbox_filters_stop_at_the_highest_zoom_of_tile_coordinates() {
+        let filter = CacheKeyFilter::bbox(None, &[-180.0, -85.0, 180.0, 85.0], 30, 40);
+
+        assert!(filter.tile_range(31).is_some());
+        assert_eq!(filter.tile_range(32), None);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::cache::cached_tile::CachedTile;
+use crate::cache::content_encoding::ContentEncoding;
+use crate::config::{Config, LayerConfig};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// TTL of cached tiles when neither the layer nor the server configures one
+const DEFAULT_CACHE_TTL: u64 = 86_400;
+
+/// How long past its TTL a tile is served while it is rendered again in the background
+const DEFAULT_STALE_WHILE_REVALIDATE: u64 = 3_600;
+
+/// How long past its TTL a tile is served when rendering it again fails
+const DEFAULT_STALE_IF_ERROR: u64 = 86_400;
+
+const DEFAULT_CACHE_ENCODINGS: &str = "gzip br zstd";
+
+const DEFAULT_CACHE_CONTROL: &str = "private, max-age=300";
+
+/// Uses the zoom band with the highest minimum zoom at or below `zoom`
+fn zoom_band_ttl(bands: Option<&HashMap<String, u64>>, zoom: u32) -> Option<u64> {
+    bands?
+        .iter()
+        .filter_map(|(min_zoom, ttl)| Some((min_zoom.parse::<u32>().ok()?, *ttl)))
+        .filter(|(min_zoom, _)| *min_zoom <= zoom)
+        .max_by_key(|(min_zoom, _)| *min_zoom)
+        .map(|(_, ttl)| ttl)
+}
+
+/// How long a layer's tile at a zoom stays cached: the layer's zoom band, the layer's TTL, the
+/// server's zoom band, then the server's TTL
+pub fn cache_ttl(config: &Config, layer_config: &LayerConfig, zoom: u32) -> Duration {
+    let seconds = zoom_band_ttl(layer_config.cache_zoom_ttls.as_ref(), zoom)
+        .or(layer_config.cache_ttl)
+        .or_else(|| zoom_band_ttl(config.cache_zoom_ttls.as_ref(), zoom))
+        .or(config.cache_ttl)
+        .unwrap_or(DEFAULT_CACHE_TTL);
+    Duration::from_secs(seconds)
+}
+
+/// How long a rendered tile stays fresh: `CACHE_EMPTY_TTL` for empty tiles when configured, the
+/// tile's TTL otherwise
+pub fn tile_ttl(config: &Config, tile: &CachedTile, ttl: Duration) -> Duration {
+    match config.cache_empty_ttl {
+        Some(empty_ttl) if tile.is_empty() => Duration::from_secs(empty_ttl),
+        _ => ttl,
+    }
+}
+
+pub fn stale_while_revalidate(config: &Config) -> Duration {
+    Duration::from_secs(
+        config
+            .cache_stale_while_revalidate
+            .unwrap_or(DEFAULT_STALE_WHILE_REVALIDATE),
+    )
+}
+
+pub fn stale_if_error(config: &Config) -> Duration {
+    Duration::from_secs(
+        config
+            .cache_stale_if_error
+            .unwrap_or(DEFAULT_STALE_IF_ERROR),
+    )
+}
+
+/// How long a tile is kept in the cache: its TTL and the longest time it may be served stale
+pub fn retention(config: &Config, ttl: Duration) -> Duration {
+    ttl + stale_while_revalidate(config).max(stale_if_error(config))
+}
+
+/// Encodings tiles are compressed in when rendered, space or comma separated. `DISABLE_GZIP` sends
+/// tiles uncompressed, so none are cached.
+pub fn cache_encodings(config: &Config) -> Vec<ContentEncoding> {
+    if config.disable_gzip.unwrap_or(false) {
+        return vec![];
+    }
+    config
+        .cache_encodings
+        .as_deref()
+        .unwrap_or(DEFAULT_CACHE_ENCODINGS)
+        .split([' ', ','])
+        .filter_map(ContentEncoding::from_name)
+        .collect()
+}
+
+/// The configured `Cache-Control` header with `max-age` and `s-maxage` capped at the time the tile
+/// has left in the cache, so clients and proxies don't keep it longer than the server does
+pub fn cache_control(header: Option<&str>, remaining: Duration) -> String {
+    let remaining = remaining.as_secs();
+    header
+        .unwrap_or(DEFAULT_CACHE_CONTROL)
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| match directive.split_once('=') {
+            Some((name @ ("max-age" | "s-maxage"), value)) => match value.parse::<u64>() {
+                Ok(age) => format!("{}={}", name, age.min(remaining)),
+                Err(_) => directive.to_string(),
+            },
+            _ => directive.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -1,41 +1,186 @@
-use redis::{Client, ToRedisArgs};
-use std::option::Option;
+use crate::cache::cache_key::{CacheKey, CacheKeyFilter};
+use crate::cache::cached_tile::CachedTile;
+use crate::cache::filesystem_cache::FilesystemCache;
+use crate::cache::memory_cache::MemoryCache;
+use crate::cache::redis_cache::RedisCache;
+use crate::cache::redis_lock::{Lock, RedisLock, LOCK_TIMEOUT};
+use crate::cache::tile_cache::TileCache;
+use crate::config::Config;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+const DEFAULT_MEMORY_MAX_BYTES: usize = 256 * 1024 * 1024;
+
+/// How often the cache is checked for a tile another instance is rendering
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tiers used when `CACHE_TIERS` is not set: Redis when a `CACHE_URL` is configured
+fn default_tiers(config: &Config) -> &'static str {
+    if config.cache_url.is_some() {
+        "redis"
+    } else {
+        ""
+    }
+}
+
+fn open_tier(config: &Config, name: &str) -> Option<Arc<dyn TileCache>> {
+    match name {
+        "memory" => Some(Arc::new(MemoryCache::new(
+            config
+                .cache_memory_max_bytes
+                .unwrap_or(DEFAULT_MEMORY_MAX_BYTES),
+        ))),
+        "filesystem" => match &config.cache_directory {
+            Some(directory) => Some(Arc::new(FilesystemCache::new(PathBuf::from(directory)))),
+            None => {
+                tracing::error!("the filesystem cache needs CACHE_DIRECTORY, skipping it");
+                None
+            }
+        },
+        "redis" => match config.cache_url.as_deref().map(RedisCache::open) {
+            Some(Ok(redis)) => Some(Arc::new(redis)),
+            Some(Err(error)) => {
+                tracing::error!("invalid cache url, skipping the redis cache: {}", error);
+                None
+            }
+            None => {
+                tracing::error!("the redis cache needs CACHE_URL, skipping it");
+                None
+            }
+        },
+        _ => {
+            tracing::error!("unknown cache tier {}, skipping it", name);
+            None
+        }
+    }
+}
+
+fn open_lock(config: &Config) -> Option<Arc<RedisLock>> {
+    if !config.cache_lock.unwrap_or(false) {
+        return None;
+    }
+
+    match config.cache_url.as_deref().map(RedisLock::open) {
+        Some(Ok(lock)) => Some(Arc::new(lock)),
+        Some(Err(error)) => {
+            tracing::error!(
+                "invalid cache url, tiles are rendered without a lock: {}",
+                error
+            );
+            None
+        }
+        None => {
+            tracing::error!("CACHE_LOCK needs CACHE_URL, tiles are rendered without a lock");
+            None
+        }
+    }
+}
+
+/// Cache tiers checked in order, e.g. memory in front of Redis. A hit in a later tier is copied
+/// into the tiers in front of it, and new tiles are written to every tier.
 #[derive(Clone)]
 pub struct CacheProvider {
-    redis_client: Option<Client>,
+    tiers: Arc<Vec<Arc<dyn TileCache>>>,
+    lock: Option<Arc<RedisLock>>,
 }
 
 impl CacheProvider {
-    pub fn new(url: Option<String>) -> Self {
-        if let Some(url) = url {
-            let redis_client = Client::open(url).unwrap();
-            return CacheProvider {
-                redis_client: Some(redis_client),
+    pub fn new(tiers: Vec<Arc<dyn TileCache>>, lock: Option<Arc<RedisLock>>) -> Self {
+        Self {
+            tiers: Arc::new(tiers),
+            lock,
+        }
+    }
+
+    /// Opens the tiers listed in `CACHE_TIERS`, space or comma separated
+    pub fn from_config(config: &Config) -> Self {
+        let tiers = config
+            .cache_tiers
+            .as_deref()
+            .unwrap_or(default_tiers(config))
+            .split([' ', ','])
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| open_tier(config, &name.to_lowercase()))
+            .collect();
+        Self::new(tiers, open_lock(config))
+    }
+
+    /// Finds a tile in the first tier holding it, fresh or stale
+    pub async fn get(&self, key: &CacheKey) -> Option<CachedTile> {
+        for (index, tier) in self.tiers.iter().enumerate() {
+            let Some(entry) = tier.get(key).await else {
+                continue;
             };
+            if let Some(ttl) = entry.ttl {
+                for front in self.tiers[..index].iter() {
+                    front.set(key, &entry.data, ttl).await;
+                }
+            }
+            return CachedTile::decode(entry.data);
         }
+        None
+    }
 
-        CacheProvider { redis_client: None }
+    /// Stores a tile in every tier, where it is kept for `ttl` including the time it may be
+    /// served stale
+    pub async fn set(&self, key: &CacheKey, tile: &CachedTile, ttl: Duration) {
+        let value = tile.encode();
+        for tier in self.tiers.iter() {
+            tier.set(key, &value, ttl).await;
+        }
     }
 
-    pub fn set<T: ToRedisArgs>(&mut self, key: &str, value: &T) {
-        if let Some(mut conn) = self.redis_client.clone() {
-            redis::cmd("SET")
-                .arg(key)
-                .arg(value)
-                .exec(&mut conn)
-                .expect("Failed to set");
+    /// Removes the selected tiles from every tier, returning how many entries were removed
+    pub async fn purge(&self, filter: &CacheKeyFilter) -> usize {
+        let mut purged = 0;
+        for tier in self.tiers.iter() {
+            purged += tier.purge(filter).await;
         }
+        purged
     }
 
-    pub fn get_bytes(&mut self, key: &str) -> Option<Vec<u8>> {
-        if let Some(mut conn) = self.redis_client.clone() {
-            if let Ok(exists) = redis::cmd("EXISTS").arg(key).query::<u8>(&mut conn) {
-                if exists == 1 {
-                    return redis::cmd("GET").arg(key).query::<Vec<u8>>(&mut conn).ok();
-                }
+    /// Locks a tile across instances before rendering it, when `CACHE_LOCK` is enabled
+    pub async fn lock(&self, key: &CacheKey) -> Lock {
+        match &self.lock {
+            Some(lock) => lock.acquire(key).await,
+            None => Lock::Unavailable,
+        }
+    }
+
+    pub async fn unlock(&self, key: &CacheKey, lock: Lock) {
+        if let (Some(redis_lock), Lock::Acquired(token)) = (&self.lock, lock) {
+            redis_lock.release(key, &token).await;
+        }
+    }
+
+    /// Waits for a tile locked by another instance to be cached younger than the time `ttl` gives
+    /// it, `None` when the lock is released without caching it or expires
+    pub async fn wait_for(
+        &self,
+        key: &CacheKey,
+        ttl: impl Fn(&CachedTile) -> Duration,
+    ) -> Option<CachedTile> {
+        let lock = self.lock.as_ref()?;
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        while Instant::now() < deadline {
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+            if let Some(tile) = self.get_fresh(key, &ttl).await {
+                return Some(tile);
+            }
+            if !lock.is_held(key).await {
+                // the holder may have cached the tile just before releasing the lock
+                return self.get_fresh(key, &ttl).await;
             }
         }
         None
     }
+
+    async fn get_fresh(
+        &self,
+        key: &CacheKey,
+        ttl: impl Fn(&CachedTile) -> Duration,
+    ) -> Option<CachedTile> {
+        self.get(key).await.filter(|tile| tile.age() < ttl(tile))
+    }
 }
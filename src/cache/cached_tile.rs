@@ -0,0 +1,300 @@
+use crate::cache::content_encoding::ContentEncoding;
+use crate::tiling::tile_budget::BudgetReport;
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version of the stored layout, entries written with another layout are read as misses
+const FORMAT_VERSION: u8 = 4;
+
+/// Generation time in milliseconds since the Unix epoch
+const GENERATED_SIZE: usize = 8;
+
+/// Hex digits of the tile hash in entity tags
+const HASH_SIZE: usize = 32;
+
+/// Whether the tile was reduced to fit its budget, then the dropped features and the simplify and
+/// cluster scales
+const BUDGET_SIZE: usize = 1 + 8 + 4 + 4;
+
+/// Version byte, the generation time, the tile hash, then the budget report
+const HEADER_SIZE: usize = 1 + GENERATED_SIZE + HASH_SIZE + BUDGET_SIZE;
+
+/// Each variant starts with its encoding and its length
+const VARIANT_HEADER_SIZE: usize = 1 + 4;
+
+/// A rendered tile as stored in the cache tiers, compressed in every cached encoding, with the
+/// time it was rendered so its freshness follows the configured TTL rather than the time it has
+/// left in a tier
+#[derive(Clone, Debug)]
+pub struct CachedTile {
+    /// The tile in each cached encoding, only identity when no encoding is cached and none for an
+    /// empty tile
+    pub variants: Vec<(ContentEncoding, Bytes)>,
+    pub generated: SystemTime,
+    /// Hash of the uncompressed tile
+    pub hash: String,
+    /// What was given up to fit the tile into its budget, sent with every response of the tile
+    pub budget: Option<BudgetReport>,
+}
+
+fn tile_hash(data: &[u8]) -> String {
+    let digest = Sha256::digest(data);
+    let mut hash = format!("{:x}", digest);
+    hash.truncate(HASH_SIZE);
+    hash
+}
+
+impl CachedTile {
+    /// A tile rendered now, compressed in the encodings. Encodings failing to compress the tile
+    /// are left out, and an empty tile is stored without any variant.
+    pub fn new(data: &[u8], encodings: &[ContentEncoding], budget: Option<BudgetReport>) -> Self {
+        let budget = budget.filter(BudgetReport::is_reduced);
+        if data.is_empty() {
+            return Self {
+                variants: vec![],
+                generated: SystemTime::now(),
+                hash: tile_hash(data),
+                budget,
+            };
+        }
+
+        let mut variants: Vec<(ContentEncoding, Bytes)> = encodings
+            .iter()
+            .filter_map(|encoding| match encoding.compress(data) {
+                Ok(compressed) => Some((*encoding, Bytes::from(compressed))),
+                Err(error) => {
+                    tracing::warn!("failed to compress a tile as {:?}: {}", encoding, error);
+                    None
+                }
+            })
+            .collect();
+        if variants.is_empty() {
+            variants.push((ContentEncoding::Identity, Bytes::copy_from_slice(data)));
+        }
+
+        Self {
+            variants,
+            generated: SystemTime::now(),
+            hash: tile_hash(data),
+            budget,
+        }
+    }
+
+    /// Whether the tile has no features
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Time since the tile was rendered
+    pub fn age(&self) -> Duration {
+        self.generated.elapsed().unwrap_or_default()
+    }
+
+    /// The best encoding the `Accept-Encoding` header allows, identity when it accepts none of the
+    /// cached encodings
+    pub fn negotiate(&self, accept_encoding: Option<&str>) -> ContentEncoding {
+        let available: Vec<ContentEncoding> = self
+            .variants
+            .iter()
+            .map(|(encoding, _)| *encoding)
+            .collect();
+        ContentEncoding::negotiate(accept_encoding, &available)
+    }
+
+    /// Strong entity tag of the tile in an encoding, differing between encodings since their
+    /// bytes differ
+    pub fn etag(&self, encoding: ContentEncoding) -> String {
+        match encoding.header_value() {
+            Some(name) => format!("\"{}-{}\"", self.hash, name),
+            None => format!("\"{}\"", self.hash),
+        }
+    }
+
+    /// The tile in an encoding, decompressed for encodings that aren't cached
+    pub fn data(&self, encoding: ContentEncoding) -> io::Result<Bytes> {
+        if let Some((_, data)) = self.variants.iter().find(|(cached, _)| *cached == encoding) {
+            return Ok(data.clone());
+        }
+
+        let Some((cached, data)) = self.variants.first() else {
+            return Ok(Bytes::new());
+        };
+        cached.decompress(data).map(Bytes::from)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let generated = self
+            .generated
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis() as u64);
+
+        let size = self
+            .variants
+            .iter()
+            .map(|(_, data)| VARIANT_HEADER_SIZE + data.len())
+            .sum::<usize>();
+        let mut value = Vec::with_capacity(HEADER_SIZE + size);
+        value.push(FORMAT_VERSION);
+        value.extend_from_slice(&generated.to_le_bytes());
+        value.extend_from_slice(self.hash.as_bytes());
+        let budget = self.budget.clone().unwrap_or_default();
+        value.push(self.budget.is_some() as u8);
+        value.extend_from_slice(&(budget.dropped_features as u64).to_le_bytes());
+        value.extend_from_slice(&budget.simplify_scale.to_le_bytes());
+        value.extend_from_slice(&budget.cluster_scale.to_le_bytes());
+        for (encoding, data) in self.variants.iter() {
+            value.push(*encoding as u8);
+            value.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            value.extend_from_slice(data);
+        }
+        value
+    }
+
+    pub fn decode(value: Vec<u8>) -> Option<Self> {
+        if value.len() < HEADER_SIZE || value[0] != FORMAT_VERSION {
+            return None;
+        }
+
+        let generated = u64::from_le_bytes(value[1..1 + GENERATED_SIZE].try_into().unwrap());
+        let budget_offset = 1 + GENERATED_SIZE + HASH_SIZE;
+        let hash = String::from_utf8(value[1 + GENERATED_SIZE..budget_offset].to_vec()).ok()?;
+        let budget = &value[budget_offset..HEADER_SIZE];
+        let budget = (budget[0] == 1).then(|| BudgetReport {
+            dropped_features: u64::from_le_bytes(budget[1..9].try_into().unwrap()) as usize,
+            simplify_scale: u32::from_le_bytes(budget[9..13].try_into().unwrap()),
+            cluster_scale: u32::from_le_bytes(budget[13..17].try_into().unwrap()),
+        });
+        let value = Bytes::from(value);
+        let mut variants = Vec::new();
+        let mut offset = HEADER_SIZE;
+        while offset < value.len() {
+            let header = value.get(offset..offset + VARIANT_HEADER_SIZE)?;
+            let encoding = ContentEncoding::from_byte(header[0])?;
+            let length = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+            offset += VARIANT_HEADER_SIZE;
+            if offset + length > value.len() {
+                return None;
+            }
+            variants.push((encoding, value.slice(offset..offset + length)));
+            offset += length;
+        }
+        Some(Self {
+            variants,
+            generated: UNIX_EPOCH + Duration::from_millis(generated),
+            hash,
+            budget,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODINGS: [ContentEncoding; 3] = [
+        ContentEncoding::Gzip,
+        ContentEncoding::Brotli,
+        ContentEncoding::Zstd,
+    ];
+
+    fn reduced_budget() -> BudgetReport {
+        BudgetReport {
+            dropped_features: 12,
+            simplify_scale: 4,
+            cluster_scale: 2,
+        }
+    }
+
+    #[test]
+    fn round_trips_every_variant_and_the_budget() {
+        let data = b"tile data ".repeat(100);
+        let tile = CachedTile::new(&data, &ENCODINGS, Some(reduced_budget()));
+
+        let decoded = CachedTile::decode(tile.encode()).unwrap();
+
+        assert_eq!(decoded.variants, tile.variants);
+        assert_eq!(decoded.hash, tile.hash);
+        assert_eq!(decoded.budget, Some(reduced_budget()));
+        assert_eq!(
+            decoded
+                .generated
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis(),
+            tile.generated
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis()
+        );
+        for encoding in ENCODINGS.into_iter().chain([ContentEncoding::Identity]) {
+            let variant = decoded.data(encoding).unwrap();
+            assert_eq!(encoding.decompress(&variant).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn budgets_that_kept_everything_are_not_stored() {
+        let tile = CachedTile::new(b"tile", &ENCODINGS, Some(BudgetReport::default()));
+
+        assert_eq!(tile.budget, None);
+        assert_eq!(CachedTile::decode(tile.encode()).unwrap().budget, None);
+    }
+
+    #[test]
+    fn round_trips_empty_tiles() {
+        let tile = CachedTile::new(&[], &ENCODINGS, None);
+
+        let decoded = CachedTile::decode(tile.encode()).unwrap();
+
+        assert!(decoded.is_empty());
+        assert!(decoded.data(ContentEncoding::Gzip).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_identity_without_cached_encodings() {
+        let tile = CachedTile::new(b"tile", &[], None);
+
+        assert_eq!(tile.variants.len(), 1);
+        assert_eq!(tile.negotiate(Some("gzip, br")), ContentEncoding::Identity);
+        assert_eq!(
+            tile.data(ContentEncoding::Identity).unwrap().as_ref(),
+            b"tile"
+        );
+    }
+
+    #[test]
+    fn entries_of_other_versions_are_misses() {
+        let mut value = CachedTile::new(b"tile", &ENCODINGS, None).encode();
+        value[0] = FORMAT_VERSION - 1;
+
+        assert!(CachedTile::decode(value).is_none());
+    }
+
+    #[test]
+    fn truncated_entries_are_misses() {
+        let value = CachedTile::new(b"tile", &ENCODINGS, None).encode();
+
+        assert!(CachedTile::decode(value[..HEADER_SIZE - 1].to_vec()).is_none());
+        assert!(CachedTile::decode(value[..value.len() - 1].to_vec()).is_none());
+    }
+
+    #[test]
+    fn entity_tags_differ_between_encodings() {
+        let tile = CachedTile::new(b"tile", &ENCODINGS, None);
+
+        assert_eq!(
+            tile.etag(ContentEncoding::Identity),
+            format!("\"{}\"", tile.hash)
+        );
+        assert_eq!(
+            tile.etag(ContentEncoding::Gzip),
+            format!("\"{}-gzip\"", tile.hash)
+        );
+        assert_ne!(
+            tile.etag(ContentEncoding::Brotli),
+            tile.etag(ContentEncoding::Zstd)
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Stops calling a failing backend for a while. After `threshold` consecutive failures calls are
+/// skipped until `cooldown` has passed, then a single trial call decides whether to close again.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            failures: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+        }
+    }
+
+    /// Whether a call may go through. Once the cooldown is over the circuit is held open for
+    /// another cooldown while the trial call runs, so only one caller tries the backend.
+    pub fn allow(&self) -> bool {
+        let mut open_until = self.open_until.lock().unwrap();
+        match *open_until {
+            Some(until) if Instant::now() < until => false,
+            Some(_) => {
+                *open_until = Some(Instant::now() + self.cooldown);
+                true
+            }
+            None => true,
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+        let mut open_until = self.open_until.lock().unwrap();
+        if open_until.take().is_some() {
+            tracing::info!("cache is reachable again");
+        }
+    }
+
+    pub fn record_failure(&self) {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.threshold {
+            let mut open_until = self.open_until.lock().unwrap();
+            if open_until.is_none() {
+                tracing::warn!(
+                    "cache failed {} times in a row, skipping it for {:?}",
+                    failures,
+                    self.cooldown
+                );
+            }
+            *open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.record_failure();
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert!(!breaker.allow());
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        breaker.record_failure();
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn lets_one_trial_call_through_after_the_cooldown() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+        breaker.record_failure();
+        assert!(!breaker.allow());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+
+        breaker.record_success();
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn failed_trial_opens_again() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+        breaker.record_failure();
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert!(!breaker.allow());
+    }
+}
@@ -0,0 +1,216 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const ZSTD_LEVEL: i32 = 3;
+
+/// Encodings tiles are cached in, stored as their discriminant
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Identity = 0,
+    Gzip = 1,
+    Brotli = 2,
+    Zstd = 3,
+}
+
+impl ContentEncoding {
+    /// Preferred first when a client accepts several encodings equally
+    const PREFERENCE: [ContentEncoding; 4] = [
+        ContentEncoding::Brotli,
+        ContentEncoding::Zstd,
+        ContentEncoding::Gzip,
+        ContentEncoding::Identity,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "identity" => Some(ContentEncoding::Identity),
+            "gzip" | "x-gzip" => Some(ContentEncoding::Gzip),
+            "br" => Some(ContentEncoding::Brotli),
+            "zstd" => Some(ContentEncoding::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::PREFERENCE
+            .into_iter()
+            .find(|encoding| *encoding as u8 == byte)
+    }
+
+    /// `Content-Encoding` header value, `None` for identity
+    pub fn header_value(self) -> Option<&'static str> {
+        match self {
+            ContentEncoding::Identity => None,
+            ContentEncoding::Gzip => Some("gzip"),
+            ContentEncoding::Brotli => Some("br"),
+            ContentEncoding::Zstd => Some("zstd"),
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Identity => Ok(data.to_vec()),
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            ContentEncoding::Brotli => {
+                let mut compressed = Vec::new();
+                let mut encoder = brotli::CompressorWriter::new(
+                    &mut compressed,
+                    4096,
+                    BROTLI_QUALITY,
+                    BROTLI_WINDOW,
+                );
+                encoder.write_all(data)?;
+                drop(encoder);
+                Ok(compressed)
+            }
+            ContentEncoding::Zstd => zstd::encode_all(data, ZSTD_LEVEL),
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decompressed = Vec::new();
+        match self {
+            ContentEncoding::Identity => decompressed.extend_from_slice(data),
+            ContentEncoding::Gzip => {
+                GzDecoder::new(data).read_to_end(&mut decompressed)?;
+            }
+            ContentEncoding::Brotli => {
+                brotli::Decompressor::new(data, 4096).read_to_end(&mut decompressed)?;
+            }
+            ContentEncoding::Zstd => decompressed = zstd::decode_all(data)?,
+        }
+        Ok(decompressed)
+    }
+
+    /// Picks the encoding with the highest `Accept-Encoding` quality among the available ones,
+    /// identity when none is accepted. Without the header only identity is sent.
+    pub fn negotiate(accept_encoding: Option<&str>, available: &[ContentEncoding]) -> Self {
+        let Some(accept_encoding) = accept_encoding else {
+            return ContentEncoding::Identity;
+        };
+
+        let mut qualities = Vec::new();
+        let mut wildcard = None;
+        for item in accept_encoding.split(',') {
+            let mut parts = item.split(';');
+            let name = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                .find_map(|quality| quality.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            match name {
+                "*" => wildcard = Some(quality),
+                name => {
+                    if let Some(encoding) = Self::from_name(name) {
+                        qualities.push((encoding, quality));
+                    }
+                }
+            }
+        }
+
+        let quality = |encoding: &ContentEncoding| {
+            qualities
+                .iter()
+                .find(|(accepted, _)| accepted == encoding)
+                .map(|(_, quality)| *quality)
+                .or(wildcard)
+                .unwrap_or(0.0)
+        };
+        Self::PREFERENCE
+            .into_iter()
+            .filter(|encoding| {
+                *encoding != ContentEncoding::Identity && available.contains(encoding)
+            })
+            .filter(|encoding| quality(encoding) > 0.0)
+            .fold(None, |best: Option<(Self, f32)>, encoding| {
+                let quality = quality(&encoding);
+                match best {
+                    Some((_, best_quality)) if best_quality >= quality => best,
+                    _ => Some((encoding, quality)),
+                }
+            })
+            .map_or(ContentEncoding::Identity, |(encoding, _)| encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ContentEncoding; 3] = [
+        ContentEncoding::Gzip,
+        ContentEncoding::Brotli,
+        ContentEncoding::Zstd,
+    ];
+
+    #[test]
+    fn compression_round_trips() {
+        let data = b"tile ".repeat(100);
+        for encoding in ALL.into_iter().chain([ContentEncoding::Identity]) {
+            let compressed = encoding.compress(&data).unwrap();
+            assert_eq!(encoding.decompress(&compressed).unwrap(), data);
+            assert_eq!(ContentEncoding::from_byte(encoding as u8), Some(encoding));
+        }
+        assert_eq!(ContentEncoding::from_byte(4), None);
+    }
+
+    #[test]
+    fn sends_identity_without_an_accepted_encoding() {
+        assert_eq!(
+            ContentEncoding::negotiate(None, &ALL),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(Some("deflate"), &ALL),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(Some("gzip"), &[ContentEncoding::Brotli]),
+            ContentEncoding::Identity
+        );
+    }
+
+    #[test]
+    fn prefers_the_highest_quality() {
+        assert_eq!(
+            ContentEncoding::negotiate(Some("gzip;q=0.9, br;q=0.5"), &ALL),
+            ContentEncoding::Gzip
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(Some("gzip, zstd ; q=1.0, br"), &ALL),
+            ContentEncoding::Brotli
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(Some("GZIP, zstd"), &ALL),
+            ContentEncoding::Zstd
+        );
+    }
+
+    #[test]
+    fn honours_refusals_and_wildcards() {
+        assert_eq!(
+            ContentEncoding::negotiate(Some("br;q=0, gzip"), &ALL),
+            ContentEncoding::Gzip
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(Some("*;q=0.5, br;q=0"), &ALL),
+            ContentEncoding::Zstd
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(Some("*;q=0"), &ALL),
+            ContentEncoding::Identity
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(Some("gzip;q=invalid"), &ALL),
+            ContentEncoding::Gzip
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::cache::cache_key::{CacheKey, CacheKeyFilter};
+use crate::cache::tile_cache::{CacheEntry, TileCache};
+use async_trait::async_trait;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+/// Each file starts with its expiry in seconds since the Unix epoch
+const EXPIRY_SIZE: usize = 8;
+
+/// Extension of tiles being written, left behind when a write is interrupted
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// Subdirectories of a directory with their names parsed, skipping anything else
+async fn subdirectories<T: std::str::FromStr>(directory: &Path) -> io::Result<Vec<(T, PathBuf)>> {
+    let mut entries = match fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(error),
+    };
+
+    let mut subdirectories = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if !entry.file_type().await?.is_dir() {
+            continue;
+        }
+        if let Some(name) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        {
+            subdirectories.push((name, entry.path()));
+        }
+    }
+    Ok(subdirectories)
+}
+
+/// Removes a tile's directory with every variant of the tile, returning how many were removed.
+/// Leftover temporary files are removed without being counted.
+async fn remove_tile(directory: &Path) -> io::Result<usize> {
+    let mut variants = 0;
+    let mut entries = fs::read_dir(directory).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.path().extension() != Some(TEMPORARY_EXTENSION.as_ref()) {
+            variants += 1;
+        }
+    }
+    fs::remove_dir_all(directory).await?;
+    Ok(variants)
+}
+
+/// Tiles as files in a `z/x/y` directory tree, shared by every instance using the directory
+pub struct FilesystemCache {
+    directory: PathBuf,
+    writes: AtomicU64,
+}
+
+impl FilesystemCache {
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            directory,
+            writes: AtomicU64::new(0),
+        }
+    }
+
+    /// Temporary file next to a tile, unique to the write so concurrent writes of the tile by
+    /// this or another instance never share one
+    fn temporary_path(&self, path: &Path) -> PathBuf {
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+        name.push(format!(
+            ".{}.{}.{}",
+            std::process::id(),
+            write,
+            TEMPORARY_EXTENSION
+        ));
+        path.with_file_name(name)
+    }
+
+    async fn try_get(&self, path: &PathBuf) -> io::Result<Option<CacheEntry>> {
+        let mut data = match fs::read(path).await {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        if data.len() < EXPIRY_SIZE {
+            return Ok(None);
+        }
+
+        let expires = u64::from_le_bytes(data[..EXPIRY_SIZE].try_into().unwrap());
+        let now = unix_time();
+        if expires <= now {
+            fs::remove_file(path).await?;
+            return Ok(None);
+        }
+
+        data.drain(..EXPIRY_SIZE);
+        Ok(Some(CacheEntry {
+            data,
+            ttl: Some(Duration::from_secs(expires - now)),
+        }))
+    }
+
+    /// Writes to a temporary file renamed into place, so readers never see a partial tile
+    async fn try_set(&self, path: &PathBuf, value: &[u8], ttl: Duration) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut data = Vec::with_capacity(EXPIRY_SIZE + value.len());
+        data.extend_from_slice(&(unix_time() + ttl.as_secs()).to_le_bytes());
+        data.extend_from_slice(value);
+
+        let temporary = self.temporary_path(path);
+        fs::write(&temporary, data).await?;
+        fs::rename(&temporary, path).await
+    }
+
+    /// Walks the `layer/z/x/y` tree, only descending into directories the filter selects
+    async fn try_purge(&self, filter: &CacheKeyFilter) -> io::Result<usize> {
+        let mut purged = 0;
+        for (layer, layer_directory) in subdirectories::<String>(&self.directory).await? {
+            if !filter.matches_layer(&layer) {
+                continue;
+            }
+            for (z, zoom_directory) in subdirectories::<u32>(&layer_directory).await? {
+                let Some((min_x, min_y, max_x, max_y)) = filter.tile_range(z) else {
+                    continue;
+                };
+                for (x, column_directory) in subdirectories::<u32>(&zoom_directory).await? {
+                    if !(min_x..=max_x).contains(&x) {
+                        continue;
+                    }
+                    for (y, tile_directory) in subdirectories::<u32>(&column_directory).await? {
+                        if (min_y..=max_y).contains(&y) {
+                            purged += remove_tile(&tile_directory).await?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(purged)
+    }
+}
+
+#[async_trait]
+impl TileCache for FilesystemCache {
+    async fn get(&self, key: &CacheKey) -> Option<CacheEntry> {
+        let path = self.directory.join(key.path());
+        self.try_get(&path).await.unwrap_or_else(|error| {
+            tracing::warn!("failed to read {}: {}", path.display(), error);
+            None
+        })
+    }
+
+    async fn set(&self, key: &CacheKey, value: &[u8], ttl: Duration) {
+        let path = self.directory.join(key.path());
+        if let Err(error) = self.try_set(&path, value, ttl).await {
+            tracing::warn!("failed to write {}: {}", path.display(), error);
+        }
+    }
+
+    async fn purge(&self, filter: &CacheKeyFilter) -> usize {
+        self.try_purge(filter).await.unwrap_or_else(|error| {
+            tracing::warn!("failed to purge {}: {}", self.directory.display(), error);
+            0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str) -> FilesystemCache {
+        let directory =
+            std::env::temp_dir().join(format!("filesystem-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        FilesystemCache::new(directory)
+    }
+
+    fn key(variant: &str) -> CacheKey {
+        CacheKey::new("roads", 1, 2, 3, variant.to_string())
+    }
+
+    #[test]
+    fn temporary_paths_are_unique_per_write() {
+        let cache = cache("temporary");
+        let path = cache.directory.join(key("mvt").path());
+
+        let first = cache.temporary_path(&path);
+        let second = cache.temporary_path(&path);
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), path.parent());
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("mvt."));
+        assert_eq!(first.extension(), Some(TEMPORARY_EXTENSION.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn tiles_round_trip() {
+        let cache = cache("round-trip");
+        cache
+            .set(&key("mvt"), b"tile", Duration::from_secs(60))
+            .await;
+
+        let entry = cache.get(&key("mvt")).await.unwrap();
+        assert_eq!(entry.data, b"tile");
+        assert!(entry.ttl.unwrap() <= Duration::from_secs(60));
+        assert!(cache.get(&key("geojson")).await.is_none());
+        fs::remove_dir_all(&cache.directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn purges_count_variants_but_not_leftover_temporary_files() {
+        let cache = cache("purge");
+        cache
+            .set(&key("mvt"), b"tile", Duration::from_secs(60))
+            .await;
+        cache
+            .set(&key("mlt"), b"tile", Duration::from_secs(60))
+            .await;
+        let path = cache.directory.join(key("mvt").path());
+        fs::write(cache.temporary_path(&path), b"partial")
+            .await
+            .unwrap();
+
+        let purged = cache
+            .purge(&CacheKeyFilter::tile(Some("roads"), 1, 2, 3))
+            .await;
+        assert_eq!(purged, 2);
+        assert!(cache.get(&key("mvt")).await.is_none());
+        assert!(!path.parent().unwrap().exists());
+        fs::remove_dir_all(&cache.directory).await.unwrap();
+    }
+}
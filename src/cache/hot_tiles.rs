@@ -0,0 +1,36 @@
+use crate::cache::cache_key::{CacheKey, CacheKeyFilter};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// The most recently requested tiles with what is needed to render them again
+pub struct HotTiles<T> {
+    tiles: Mutex<LruCache<CacheKey, T>>,
+}
+
+impl<T: Clone> HotTiles<T> {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            tiles: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Marks a tile as requested, building its request only when the tile isn't hot yet
+    pub fn touch(&self, key: &CacheKey, request: impl FnOnce() -> T) {
+        let mut tiles = self.tiles.lock().unwrap();
+        if tiles.get(key).is_none() {
+            tiles.put(key.clone(), request());
+        }
+    }
+
+    /// Hot tiles the filter selects, most recently requested first
+    pub fn matching(&self, filter: &CacheKeyFilter) -> Vec<(CacheKey, T)> {
+        self.tiles
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| filter.matches(key))
+            .map(|(key, request)| (key.clone(), request.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,94 @@
+use crate::cache::cache_key::{CacheKey, CacheKeyFilter};
+use crate::cache::tile_cache::{CacheEntry, TileCache};
+use async_trait::async_trait;
+use lru::LruCache;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct MemoryEntry {
+    data: Vec<u8>,
+    expires: Instant,
+}
+
+struct MemoryTiles {
+    entries: LruCache<CacheKey, MemoryEntry>,
+    bytes: usize,
+}
+
+/// Tiles in process memory, evicting the least recently used tiles once their total size goes
+/// over `max_bytes`
+pub struct MemoryCache {
+    max_bytes: usize,
+    tiles: Mutex<MemoryTiles>,
+}
+
+impl MemoryCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            tiles: Mutex::new(MemoryTiles {
+                entries: LruCache::unbounded(),
+                bytes: 0,
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl TileCache for MemoryCache {
+    async fn get(&self, key: &CacheKey) -> Option<CacheEntry> {
+        let mut tiles = self.tiles.lock().unwrap();
+        let now = Instant::now();
+        let entry = tiles.entries.get(key)?;
+        if entry.expires > now {
+            return Some(CacheEntry {
+                data: entry.data.clone(),
+                ttl: Some(entry.expires - now),
+            });
+        }
+
+        if let Some(expired) = tiles.entries.pop(key) {
+            tiles.bytes -= expired.data.len();
+        }
+        None
+    }
+
+    async fn set(&self, key: &CacheKey, value: &[u8], ttl: Duration) {
+        if value.len() > self.max_bytes {
+            return;
+        }
+
+        let mut tiles = self.tiles.lock().unwrap();
+        let entry = MemoryEntry {
+            data: value.to_vec(),
+            expires: Instant::now() + ttl,
+        };
+        tiles.bytes += value.len();
+        if let Some(replaced) = tiles.entries.put(key.clone(), entry) {
+            tiles.bytes -= replaced.data.len();
+        }
+        while tiles.bytes > self.max_bytes {
+            let Some((_, evicted)) = tiles.entries.pop_lru() else {
+                break;
+            };
+            tiles.bytes -= evicted.data.len();
+        }
+    }
+
+    async fn purge(&self, filter: &CacheKeyFilter) -> usize {
+        let mut tiles = self.tiles.lock().unwrap();
+        let keys: Vec<CacheKey> = tiles
+            .entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| filter.matches(key))
+            .cloned()
+            .collect();
+        for key in keys.iter() {
+            if let Some(purged) = tiles.entries.pop(key) {
+                tiles.bytes -= purged.data.len();
+            }
+        }
+        keys.len()
+    }
+}
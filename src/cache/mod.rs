@@ -1 +1,14 @@
+pub mod cache_key;
+pub mod cache_policy;
 pub mod cache_provider;
+pub mod cached_tile;
+mod circuit_breaker;
+pub mod content_encoding;
+pub mod filesystem_cache;
+pub mod hot_tiles;
+pub mod memory_cache;
+pub mod redis_cache;
+mod redis_connection;
+pub mod redis_lock;
+pub mod single_flight;
+pub mod tile_cache;
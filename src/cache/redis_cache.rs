@@ -0,0 +1,97 @@
+use crate::cache::cache_key::{CacheKey, CacheKeyFilter};
+use crate::cache::redis_connection::RedisConnection;
+use crate::cache::tile_cache::{CacheEntry, TileCache};
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, RedisResult};
+use std::time::Duration;
+
+/// Keys removed per `UNLINK`
+const PURGE_BATCH_SIZE: usize = 500;
+
+/// Tiles in Redis. Errors are logged and treated as misses, and Redis is skipped for a while
+/// when it keeps failing.
+pub struct RedisCache {
+    connection: RedisConnection,
+}
+
+impl RedisCache {
+    pub fn open(url: &str) -> RedisResult<Self> {
+        Ok(Self {
+            connection: RedisConnection::open(url)?,
+        })
+    }
+}
+
+async fn get_entry(
+    connection: &mut ConnectionManager,
+    key: &CacheKey,
+) -> RedisResult<Option<CacheEntry>> {
+    let key = key.to_string();
+    let (data, ttl): (Option<Vec<u8>>, i64) = redis::pipe()
+        .cmd("GET")
+        .arg(&key)
+        .cmd("PTTL")
+        .arg(&key)
+        .query_async(connection)
+        .await?;
+
+    // PTTL is negative for keys without an expiry
+    let ttl = u64::try_from(ttl).ok().map(Duration::from_millis);
+    Ok(data.map(|data| CacheEntry { data, ttl }))
+}
+
+/// Tiles are found with `SCAN` over every tile key, so purging doesn't block Redis
+async fn purge_keys(
+    connection: &mut ConnectionManager,
+    filter: &CacheKeyFilter,
+) -> RedisResult<usize> {
+    let mut keys = Vec::new();
+    let mut scan_connection = connection.clone();
+    let mut scan = scan_connection.scan_match::<_, String>("tile:*").await?;
+    while let Some(key) = scan.next_item().await {
+        if CacheKey::parse(&key).is_some_and(|key| filter.matches(&key)) {
+            keys.push(key);
+        }
+    }
+    drop(scan);
+
+    for batch in keys.chunks(PURGE_BATCH_SIZE) {
+        redis::cmd("UNLINK")
+            .arg(batch)
+            .exec_async(connection)
+            .await?;
+    }
+    Ok(keys.len())
+}
+
+#[async_trait]
+impl TileCache for RedisCache {
+    async fn get(&self, key: &CacheKey) -> Option<CacheEntry> {
+        let mut connection = self.connection.get().await?;
+        let result = get_entry(&mut connection, key).await;
+        self.connection.record("get", result).flatten()
+    }
+
+    async fn set(&self, key: &CacheKey, value: &[u8], ttl: Duration) {
+        let Some(mut connection) = self.connection.get().await else {
+            return;
+        };
+        let result = redis::cmd("SET")
+            .arg(key.to_string())
+            .arg(value)
+            .arg("EX")
+            .arg(ttl.as_secs().max(1))
+            .exec_async(&mut connection)
+            .await;
+        self.connection.record("set", result);
+    }
+
+    async fn purge(&self, filter: &CacheKeyFilter) -> usize {
+        let Some(mut connection) = self.connection.get().await else {
+            return 0;
+        };
+        let result = purge_keys(&mut connection, filter).await;
+        self.connection.record("purge", result).unwrap_or(0)
+    }
+}
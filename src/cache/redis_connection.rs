@@ -0,0 +1,139 @@
+use crate::cache::circuit_breaker::CircuitBreaker;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{Client, RedisResult};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// Consecutive failures after which Redis is skipped
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// How long Redis is skipped before it is tried again
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Shared multiplexed Redis connection behind a circuit breaker
+pub struct RedisConnection {
+    client: Client,
+    connection: OnceCell<ConnectionManager>,
+    breaker: CircuitBreaker,
+}
+
+impl RedisConnection {
+    pub fn open(url: &str) -> RedisResult<Self> {
+        Ok(Self {
+            client: Client::open(url)?,
+            connection: OnceCell::new(),
+            breaker: CircuitBreaker::new(FAILURE_THRESHOLD, COOLDOWN),
+        })
+    }
+
+    /// The connection, established on first use and reconnected by the manager, or `None` while
+    /// Redis is skipped or can't be reached
+    pub async fn get(&self) -> Option<ConnectionManager> {
+        if !self.breaker.allow() {
+            return None;
+        }
+        // commands on the cached manager report their own result, recording the handle as a
+        // success would reset the failure count before every command
+        if let Some(connection) = self.connection.get() {
+            return Some(connection.clone());
+        }
+
+        let config = ConnectionManagerConfig::new()
+            .set_number_of_retries(1)
+            .set_connection_timeout(CONNECTION_TIMEOUT)
+            .set_response_timeout(RESPONSE_TIMEOUT);
+        let connection = self
+            .connection
+            .get_or_try_init(|| ConnectionManager::new_with_config(self.client.clone(), config))
+            .await
+            .cloned();
+        match connection {
+            Ok(connection) => Some(connection),
+            Err(error) => {
+                self.record_error("connect", error);
+                None
+            }
+        }
+    }
+
+    /// Logs a failed command and counts it against the circuit breaker
+    pub fn record<T>(&self, operation: &str, result: RedisResult<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.breaker.record_success();
+                Some(value)
+            }
+            Err(error) => {
+                self.record_error(operation, error);
+                None
+            }
+        }
+    }
+
+    fn record_error(&self, operation: &str, error: redis::RedisError) {
+        tracing::warn!("redis {} failed: {}", operation, error);
+        self.breaker.record_failure();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Redis stand-in acknowledging the handshake, then never answering a command
+    async fn unresponsive_redis() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut buffer = vec![0; 4096];
+                    let Ok(read) = socket.read(&mut buffer).await else {
+                        return;
+                    };
+                    let handshake = String::from_utf8_lossy(&buffer[..read]).to_string();
+                    let commands = handshake.matches("\r\n*").count() + 1;
+                    socket
+                        .write_all(&b"+OK\r\n".repeat(commands))
+                        .await
+                        .unwrap();
+                    while socket.read(&mut buffer).await.is_ok_and(|read| read > 0) {}
+                });
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn failing_commands_on_a_connected_redis_open_the_breaker() {
+        let connection = RedisConnection::open(&unresponsive_redis().await).unwrap();
+        for _ in 0..FAILURE_THRESHOLD {
+            let mut manager = connection.get().await.unwrap();
+            let result = redis::cmd("GET")
+                .arg("tile")
+                .query_async::<Option<Vec<u8>>>(&mut manager)
+                .await;
+            assert!(connection.record("get", result).is_none());
+        }
+        assert!(connection.get().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn skips_redis_after_failed_connects() {
+        let connection = RedisConnection::open("redis://127.0.0.1:1").unwrap();
+        for _ in 0..FAILURE_THRESHOLD {
+            assert!(connection.get().await.is_none());
+        }
+
+        let start = Instant::now();
+        assert!(connection.get().await.is_none());
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::cache::cache_key::CacheKey;
+use crate::cache::redis_connection::RedisConnection;
+use redis::{RedisResult, Script};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest a lock is held, so a crashed instance doesn't keep a tile locked
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+pub enum Lock {
+    /// This instance renders the tile and releases the lock with the token
+    Acquired(String),
+    /// Another instance is rendering the tile
+    Held,
+    /// No lock is configured or Redis can't be reached, the tile is rendered without one
+    Unavailable,
+}
+
+fn lock_key(key: &CacheKey) -> String {
+    format!("lock:{}", key)
+}
+
+/// Per-tile lock in Redis, letting one instance render a tile while the others wait for it to
+/// show up in the cache
+pub struct RedisLock {
+    connection: RedisConnection,
+    counter: AtomicU64,
+}
+
+impl RedisLock {
+    pub fn open(url: &str) -> RedisResult<Self> {
+        Ok(Self {
+            connection: RedisConnection::open(url)?,
+            counter: AtomicU64::new(0),
+        })
+    }
+
+    /// Token identifying this holder, so a lock that expired and was taken over is not released
+    fn token(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos());
+        let count = self.counter.fetch_add(1, Ordering::Relaxed);
+        format!("{}:{}:{}", std::process::id(), nanos, count)
+    }
+
+    pub async fn acquire(&self, key: &CacheKey) -> Lock {
+        let Some(mut connection) = self.connection.get().await else {
+            return Lock::Unavailable;
+        };
+
+        let token = self.token();
+        let result: RedisResult<Option<String>> = redis::cmd("SET")
+            .arg(lock_key(key))
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(LOCK_TIMEOUT.as_millis() as u64)
+            .query_async(&mut connection)
+            .await;
+
+        match self.connection.record("lock", result) {
+            Some(Some(_)) => Lock::Acquired(token),
+            Some(None) => Lock::Held,
+            None => Lock::Unavailable,
+        }
+    }
+
+    /// Whether another holder still has the tile locked, `false` when Redis can't be reached
+    pub async fn is_held(&self, key: &CacheKey) -> bool {
+        let Some(mut connection) = self.connection.get().await else {
+            return false;
+        };
+
+        let result: RedisResult<bool> = redis::cmd("EXISTS")
+            .arg(lock_key(key))
+            .query_async(&mut connection)
+            .await;
+        self.connection
+            .record("lock check", result)
+            .unwrap_or(false)
+    }
+
+    pub async fn release(&self, key: &CacheKey, token: &str) {
+        let Some(mut connection) = self.connection.get().await else {
+            return;
+        };
+
+        let result: RedisResult<i64> = Script::new(RELEASE_SCRIPT)
+            .key(lock_key(key))
+            .arg(token)
+            .invoke_async(&mut connection)
+            .await;
+        self.connection.record("unlock", result);
+    }
+}
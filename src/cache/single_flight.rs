@@ -0,0 +1,46 @@
+use crate::cache::cache_key::CacheKey;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+/// Runs one computation per key at a time: callers arriving while it runs wait for its result
+/// instead of starting their own. When the running caller is dropped, a waiting caller takes over.
+pub struct SingleFlight<T> {
+    flights: Mutex<HashMap<CacheKey, Arc<OnceCell<T>>>>,
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub async fn run<F, Fut>(&self, key: &CacheKey, compute: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let flight = self
+            .flights
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let value = flight.get_or_init(compute).await.clone();
+
+        let mut flights = self.flights.lock().unwrap();
+        if flights
+            .get(key)
+            .is_some_and(|current| Arc::ptr_eq(current, &flight))
+        {
+            flights.remove(key);
+        }
+        value
+    }
+}
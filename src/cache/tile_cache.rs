@@ -0,0 +1,22 @@
+use crate::cache::cache_key::{CacheKey, CacheKeyFilter};
+use async_trait::async_trait;
+use std::time::Duration;
+
+pub struct CacheEntry {
+    pub data: Vec<u8>,
+    /// Time left until the entry expires, `None` for entries stored without an expiry
+    pub ttl: Option<Duration>,
+}
+
+/// Storage for rendered tiles. Failures are handled inside the backend and reported as misses, a
+/// cache never fails a request.
+#[async_trait]
+pub trait TileCache: Send + Sync {
+    async fn get(&self, key: &CacheKey) -> Option<CacheEntry>;
+
+    /// Stores a value that expires after `ttl`
+    async fn set(&self, key: &CacheKey, value: &[u8], ttl: Duration);
+
+    /// Removes the tiles the filter selects and returns how many were removed
+    async fn purge(&self, filter: &CacheKeyFilter) -> usize;
+}
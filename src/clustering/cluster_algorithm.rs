@@ -0,0 +1,15 @@
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterAlgorithm {
+    /// Clusters points into H3 cells inside the tile query
+    #[default]
+    H3,
+    /// Clusters points into a screen-space pixel grid aligned to tile coordinates
+    Grid,
+    /// Hierarchical greedy radius clustering in the style of supercluster
+    Supercluster,
+    /// Disables point clustering
+    None,
+}
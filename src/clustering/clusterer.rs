@@ -0,0 +1,265 @@
+use crate::clustering::cluster_algorithm::ClusterAlgorithm;
+use crate::clustering::grid_clusterer::GridClusterer;
+use crate::clustering::radius_clusterer::RadiusClusterer;
+use crate::geo::geo_utils::mercator_to_tile;
+use crate::mvt::constants::DEFAULT_TILE_SIZE;
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature};
+use crate::mvt::property_value::PropertyValue;
+use geo_types::{Coord, Geometry, Point};
+
+pub const CLUSTER_COUNT_PROPERTY: &str = "clusterCount";
+const DEFAULT_GRID_SIZE: u32 = 64;
+const DEFAULT_CLUSTER_RADIUS: u32 = 40;
+const DEFAULT_CLUSTER_MAX_ZOOM: u32 = 16;
+
+pub trait Clusterer {
+    /// Clusters the point features of a tile and the buffer around it, keeping the clusters whose
+    /// center falls in the tile. Other geometries pass through.
+    fn cluster(&self, features: Vec<Feature>, coordinates: &Coordinates) -> Vec<Feature>;
+}
+
+pub fn get_clusterer(
+    algorithm: ClusterAlgorithm,
+    radius: Option<u32>,
+    radius_scale: u32,
+    max_zoom: Option<u32>,
+) -> Option<Box<dyn Clusterer + Send + Sync>> {
+    match algorithm {
+        ClusterAlgorithm::Grid => Some(Box::new(GridClusterer::new(
+            radius.unwrap_or(DEFAULT_GRID_SIZE) * radius_scale,
+        ))),
+        ClusterAlgorithm::Supercluster => Some(Box::new(RadiusClusterer::new(
+            radius.unwrap_or(DEFAULT_CLUSTER_RADIUS) * radius_scale,
+            max_zoom.unwrap_or(DEFAULT_CLUSTER_MAX_ZOOM),
+        ))),
+        ClusterAlgorithm::H3 | ClusterAlgorithm::None => None,
+    }
+}
+
+/// Cells of the radius around a radius cluster its points depend on at one zoom: the cluster's
+/// center may be two cells from its seed, whose neighbours' decisions depend on the cells around
+/// them. Clusters merged from the zooms above add half as much again per zoom, so a hierarchy
+/// never depends on more than twice as many.
+const RADIUS_BUFFER_CELLS: u32 = 2 * 6;
+
+/// Pixels beyond the tile edges a clusterer needs points from, so a cluster on an edge is built
+/// from the same points by the tiles on both sides
+pub fn cluster_buffer(
+    algorithm: ClusterAlgorithm,
+    radius: Option<u32>,
+    radius_scale: u32,
+    max_zoom: Option<u32>,
+    zoom: u32,
+) -> u32 {
+    match algorithm {
+        ClusterAlgorithm::Grid => radius.unwrap_or(DEFAULT_GRID_SIZE) * radius_scale,
+        ClusterAlgorithm::Supercluster if zoom <= max_zoom.unwrap_or(DEFAULT_CLUSTER_MAX_ZOOM) => {
+            RADIUS_BUFFER_CELLS * radius.unwrap_or(DEFAULT_CLUSTER_RADIUS) * radius_scale
+        }
+        _ => 0,
+    }
+}
+
+/// Whether a global pixel falls inside the tile, so each cluster is kept by exactly one tile
+pub fn in_tile(pixel: &Point, coordinates: &Coordinates) -> bool {
+    let size = DEFAULT_TILE_SIZE as f64;
+    let min_x = coordinates.x as f64 * size;
+    let min_y = coordinates.y as f64 * size;
+    (min_x..min_x + size).contains(&pixel.x()) && (min_y..min_y + size).contains(&pixel.y())
+}
+
+/// Projects a longitude/latitude point into global pixel space at the given zoom
+pub fn to_pixel(point: &Point, zoom: u32) -> Point {
+    let world = mercator_to_tile(point.x(), point.y(), 0);
+    let scale = (1u64 << zoom) as f64 * DEFAULT_TILE_SIZE as f64;
+    Point(Coord {
+        x: world.x() * scale,
+        y: world.y() * scale,
+    })
+}
+
+pub struct PointCluster {
+    feature: Feature,
+    sum_x: f64,
+    sum_y: f64,
+    count: u64,
+}
+
+impl PointCluster {
+    pub fn new(feature: Feature, point: Point) -> Self {
+        Self {
+            feature,
+            sum_x: point.x(),
+            sum_y: point.y(),
+            count: 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: PointCluster) {
+        self.sum_x += other.sum_x;
+        self.sum_y += other.sum_y;
+        self.count += other.count;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn center(&self) -> Point {
+        Point(Coord {
+            x: self.sum_x / self.count as f64,
+            y: self.sum_y / self.count as f64,
+        })
+    }
+
+    pub fn into_feature(self) -> Feature {
+        let center = self.center();
+        let mut feature = self.feature;
+        feature.geometry = Geometry::Point(center);
+        feature.properties.insert(
+            CLUSTER_COUNT_PROPERTY.to_string(),
+            PropertyValue::Int(self.count as i64),
+        );
+        feature
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::geo::geo_utils::to_point;
+
+    pub fn point_at_pixel(x: f64, y: f64, zoom: u32) -> Feature {
+        let size = DEFAULT_TILE_SIZE as f64;
+        let (longitude, latitude) = to_point(x / size, y / size, zoom);
+        Feature {
+            geometry: Geometry::Point(Point::new(longitude, latitude)),
+            properties: Default::default(),
+        }
+    }
+
+    /// Points inside a tile grown by `buffer` pixels, as the tile query reads them
+    pub fn tile_view(features: &[Feature], coordinates: &Coordinates, buffer: u32) -> Vec<Feature> {
+        let size = DEFAULT_TILE_SIZE as f64;
+        let buffer = buffer as f64;
+        features
+            .iter()
+            .filter(|feature| {
+                let Geometry::Point(point) = feature.geometry else {
+                    return true;
+                };
+                let pixel = to_pixel(&point, coordinates.z);
+                let min_x = coordinates.x as f64 * size - buffer;
+                let min_y = coordinates.y as f64 * size - buffer;
+                (min_x..min_x + size + 2.0 * buffer).contains(&pixel.x())
+                    && (min_y..min_y + size + 2.0 * buffer).contains(&pixel.y())
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Clusters as rounded pixel centers and counts, sorted to compare tiles
+    pub fn summary(features: &[Feature], zoom: u32) -> Vec<(i64, i64, i64)> {
+        let mut summary: Vec<(i64, i64, i64)> = features
+            .iter()
+            .map(|feature| {
+                let Geometry::Point(point) = feature.geometry else {
+                    panic!("clusters are points");
+                };
+                let pixel = to_pixel(&point, zoom);
+                let Some(PropertyValue::Int(count)) =
+                    feature.properties.get(CLUSTER_COUNT_PROPERTY)
+                else {
+                    panic!("clusters have a count");
+                };
+                (
+                    (pixel.x() * 1000.0).round() as i64,
+                    (pixel.y() * 1000.0).round() as i64,
+                    *count,
+                )
+            })
+            .collect();
+        summary.sort();
+        summary
+    }
+
+    /// Points scattered across the edge between tiles 3/3/3 and 3/4/3
+    pub fn points_across_edge() -> Vec<Feature> {
+        let edge = 4.0 * DEFAULT_TILE_SIZE as f64;
+        let mut features = vec![];
+        for step in 0..400 {
+            let x = edge - 300.0 + (step * 37 % 600) as f64 + 0.5;
+            let y = 3.0 * DEFAULT_TILE_SIZE as f64 + 60.0 + (step * 53 % 400) as f64 + 0.25;
+            features.push(point_at_pixel(x, y, 3));
+        }
+        features
+    }
+
+    /// Clustering each tile from its buffered view gives the clusters that clustering every
+    /// point gives, each cluster in exactly one tile
+    pub fn assert_consistent_across_edge(clusterer: &dyn Clusterer, buffer: u32) {
+        let features = points_across_edge();
+        let tiles = [
+            Coordinates { x: 3, y: 3, z: 3 },
+            Coordinates { x: 4, y: 3, z: 3 },
+        ];
+
+        let mut expected = vec![];
+        let mut actual = vec![];
+        for coordinates in tiles.iter() {
+            expected.extend(clusterer.cluster(features.clone(), coordinates));
+            actual
+                .extend(clusterer.cluster(tile_view(&features, coordinates, buffer), coordinates));
+        }
+
+        let actual = summary(&actual, 3);
+        assert_eq!(actual, summary(&expected, 3));
+        let total: i64 = actual.iter().map(|(_, _, count)| count).sum();
+        assert_eq!(total, features.len() as i64);
+    }
+
+    #[test]
+    fn buffer_covers_the_cluster_extent() {
+        assert_eq!(cluster_buffer(ClusterAlgorithm::Grid, None, 1, None, 5), 64);
+        assert_eq!(
+            cluster_buffer(ClusterAlgorithm::Grid, Some(100), 2, None, 5),
+            200
+        );
+        assert_eq!(
+            cluster_buffer(ClusterAlgorithm::Supercluster, Some(40), 1, None, 5),
+            480
+        );
+        assert_eq!(
+            cluster_buffer(ClusterAlgorithm::Supercluster, Some(40), 1, Some(4), 5),
+            0
+        );
+        assert_eq!(
+            cluster_buffer(ClusterAlgorithm::H3, Some(40), 1, None, 5),
+            0
+        );
+    }
+
+    #[test]
+    fn tile_edges_belong_to_one_tile() {
+        let coordinates = Coordinates { x: 1, y: 2, z: 3 };
+        assert!(in_tile(&Point::new(512.0, 1024.0), &coordinates));
+        assert!(in_tile(&Point::new(1023.9, 1535.9), &coordinates));
+        assert!(!in_tile(&Point::new(1024.0, 1100.0), &coordinates));
+        assert!(!in_tile(&Point::new(600.0, 1536.0), &coordinates));
+    }
+
+    #[test]
+    fn merged_clusters_are_centered_on_their_points() {
+        let mut cluster = PointCluster::new(point_at_pixel(0.0, 0.0, 0), Point::new(0.0, 0.0));
+        cluster.merge(PointCluster::new(
+            point_at_pixel(0.0, 0.0, 0),
+            Point::new(2.0, 4.0),
+        ));
+        let feature = cluster.into_feature();
+        assert_eq!(feature.geometry, Geometry::Point(Point::new(1.0, 2.0)));
+        assert_eq!(
+            feature.properties.get(CLUSTER_COUNT_PROPERTY),
+            Some(&PropertyValue::Int(2))
+        );
+    }
+}
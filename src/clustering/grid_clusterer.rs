@@ -0,0 +1,91 @@
+use crate::clustering::clusterer::{in_tile, to_pixel, Clusterer, PointCluster};
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature};
+use geo_types::Geometry;
+use std::collections::HashMap;
+
+/// Buckets points into square pixel cells anchored at the world origin so cells line up with tile edges
+pub struct GridClusterer {
+    cell_size: f64,
+}
+
+impl GridClusterer {
+    pub fn new(cell_size: u32) -> Self {
+        Self {
+            cell_size: cell_size.max(1) as f64,
+        }
+    }
+}
+
+impl Clusterer for GridClusterer {
+    fn cluster(&self, features: Vec<Feature>, coordinates: &Coordinates) -> Vec<Feature> {
+        let mut clustered: Vec<Feature> = vec![];
+        let mut cells: HashMap<(i64, i64), PointCluster> = HashMap::new();
+        let mut cell_order: Vec<(i64, i64)> = vec![];
+
+        for feature in features {
+            let Geometry::Point(point) = feature.geometry else {
+                clustered.push(feature);
+                continue;
+            };
+
+            let pixel = to_pixel(&point, coordinates.z);
+            let cell = (
+                (pixel.x() / self.cell_size).floor() as i64,
+                (pixel.y() / self.cell_size).floor() as i64,
+            );
+
+            let cluster = PointCluster::new(feature, point);
+            if let Some(existing) = cells.get_mut(&cell) {
+                existing.merge(cluster);
+            } else {
+                cells.insert(cell, cluster);
+                cell_order.push(cell);
+            }
+        }
+
+        for cell in cell_order {
+            let Some(cluster) = cells.remove(&cell) else {
+                continue;
+            };
+            if in_tile(&to_pixel(&cluster.center(), coordinates.z), coordinates) {
+                clustered.push(cluster.into_feature());
+            }
+        }
+
+        clustered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::clusterer::tests::{assert_consistent_across_edge, point_at_pixel};
+
+    #[test]
+    fn clusters_points_of_a_cell() {
+        let features = vec![
+            point_at_pixel(10.0, 10.0, 2),
+            point_at_pixel(50.0, 20.0, 2),
+            point_at_pixel(70.0, 20.0, 2),
+        ];
+        let clustered = GridClusterer::new(64).cluster(features, &Coordinates { x: 0, y: 0, z: 2 });
+        assert_eq!(clustered.len(), 2);
+    }
+
+    #[test]
+    fn cells_straddling_tile_edges_are_clustered_once() {
+        // 100px cells don't line up with the 512px tiles
+        assert_consistent_across_edge(&GridClusterer::new(100), 100);
+    }
+
+    #[test]
+    fn keeps_other_geometries() {
+        let line = Feature {
+            geometry: Geometry::LineString(vec![(0.0, 0.0), (1.0, 1.0)].into()),
+            properties: Default::default(),
+        };
+        let clustered =
+            GridClusterer::new(64).cluster(vec![line], &Coordinates { x: 0, y: 0, z: 2 });
+        assert_eq!(clustered.len(), 1);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod cluster_algorithm;
+pub mod clusterer;
+mod grid_clusterer;
+mod radius_clusterer;
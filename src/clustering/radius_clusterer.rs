@@ -0,0 +1,234 @@
+use crate::clustering::clusterer::{in_tile, to_pixel, Clusterer, PointCluster};
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature};
+use geo_types::{Geometry, Point};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+type Cell = (i64, i64);
+
+/// Hierarchical radius clustering in the style of supercluster, built bottom up from `max_zoom` so
+/// clusters at a zoom are merges of the clusters at the zoom above. At each zoom clusters are
+/// gathered in global cells as wide as the radius, then a cell holding more points than every cell
+/// within the radius of it becomes a cluster and absorbs the neighbours within the radius that
+/// aren't clusters themselves. A cluster only depends on cells a few radii around it at each zoom,
+/// radii that halve with every zoom above, so neighbouring tiles build the same clusters across
+/// their edge.
+pub struct RadiusClusterer {
+    radius: f64,
+    max_zoom: u32,
+}
+
+struct CellCluster {
+    cluster: PointCluster,
+    pixel: Point,
+}
+
+impl RadiusClusterer {
+    pub fn new(radius: u32, max_zoom: u32) -> Self {
+        Self {
+            radius: radius.max(1) as f64,
+            max_zoom,
+        }
+    }
+
+    fn cell_of(&self, pixel: &Point) -> Cell {
+        (
+            (pixel.x() / self.radius).floor() as i64,
+            (pixel.y() / self.radius).floor() as i64,
+        )
+    }
+
+    /// Cells around a cell whose points are centered within the radius of its own
+    fn neighbours<'a>(
+        &'a self,
+        cells: &'a BTreeMap<Cell, CellCluster>,
+        cell: Cell,
+    ) -> impl Iterator<Item = Cell> + 'a {
+        let origin = cells[&cell].pixel;
+        (cell.0 - 1..=cell.0 + 1)
+            .flat_map(move |x| (cell.1 - 1..=cell.1 + 1).map(move |y| (x, y)))
+            .filter(move |neighbour| *neighbour != cell)
+            .filter(move |neighbour| {
+                cells.get(neighbour).is_some_and(|other| {
+                    let dx = other.pixel.x() - origin.x();
+                    let dy = other.pixel.y() - origin.y();
+                    dx * dx + dy * dy <= self.radius * self.radius
+                })
+            })
+    }
+
+    fn cluster_zoom(&self, clusters: Vec<PointCluster>, zoom: u32) -> Vec<PointCluster> {
+        let mut cells: BTreeMap<Cell, PointCluster> = BTreeMap::new();
+        for cluster in clusters {
+            let cell = self.cell_of(&to_pixel(&cluster.center(), zoom));
+            match cells.get_mut(&cell) {
+                Some(existing) => existing.merge(cluster),
+                None => {
+                    cells.insert(cell, cluster);
+                }
+            }
+        }
+        let cells: BTreeMap<Cell, CellCluster> = cells
+            .into_iter()
+            .map(|(cell, cluster)| {
+                let pixel = to_pixel(&cluster.center(), zoom);
+                (cell, CellCluster { cluster, pixel })
+            })
+            .collect();
+
+        // more points first, ties go to the upper left cell
+        let priority = |cell: &Cell| (cells[cell].cluster.count(), Reverse(*cell));
+        let is_seed = |cell: Cell| {
+            self.neighbours(&cells, cell)
+                .all(|neighbour| priority(&neighbour) < priority(&cell))
+        };
+        let targets: BTreeMap<Cell, Cell> = cells
+            .keys()
+            .filter(|cell| !is_seed(**cell))
+            .filter_map(|cell| {
+                self.neighbours(&cells, *cell)
+                    .filter(|neighbour| is_seed(*neighbour))
+                    .max_by_key(priority)
+                    .map(|seed| (*cell, seed))
+            })
+            .collect();
+
+        let mut cells: BTreeMap<Cell, PointCluster> = cells
+            .into_iter()
+            .map(|(cell, cell_cluster)| (cell, cell_cluster.cluster))
+            .collect();
+        for (cell, seed) in targets {
+            if let Some(cluster) = cells.remove(&cell) {
+                if let Some(seed) = cells.get_mut(&seed) {
+                    seed.merge(cluster);
+                }
+            }
+        }
+        cells.into_values().collect()
+    }
+}
+
+impl Clusterer for RadiusClusterer {
+    fn cluster(&self, features: Vec<Feature>, coordinates: &Coordinates) -> Vec<Feature> {
+        if coordinates.z > self.max_zoom {
+            return features;
+        }
+
+        let mut clustered: Vec<Feature> = vec![];
+        let mut clusters: Vec<PointCluster> = vec![];
+        for feature in features {
+            if let Geometry::Point(point) = feature.geometry {
+                clusters.push(PointCluster::new(feature, point));
+            } else {
+                clustered.push(feature);
+            }
+        }
+
+        let clusters = (coordinates.z..=self.max_zoom)
+            .rev()
+            .fold(clusters, |clusters, zoom| self.cluster_zoom(clusters, zoom));
+        clustered.extend(
+            clusters
+                .into_iter()
+                .filter(|cluster| in_tile(&to_pixel(&cluster.center(), coordinates.z), coordinates))
+                .map(PointCluster::into_feature),
+        );
+        clustered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustering::cluster_algorithm::ClusterAlgorithm;
+    use crate::clustering::clusterer::tests::{assert_consistent_across_edge, point_at_pixel};
+    use crate::clustering::clusterer::{cluster_buffer, CLUSTER_COUNT_PROPERTY};
+    use crate::mvt::property_value::PropertyValue;
+
+    #[test]
+    fn clusters_points_within_the_radius() {
+        let features = vec![
+            point_at_pixel(100.0, 100.0, 2),
+            point_at_pixel(120.0, 100.0, 2),
+            point_at_pixel(300.0, 300.0, 2),
+        ];
+        let clustered =
+            RadiusClusterer::new(40, 16).cluster(features, &Coordinates { x: 0, y: 0, z: 2 });
+        assert_eq!(clustered.len(), 2);
+    }
+
+    #[test]
+    fn clusters_across_tile_edges_are_kept_once() {
+        for max_zoom in [3, 5, 16] {
+            let buffer = cluster_buffer(
+                ClusterAlgorithm::Supercluster,
+                Some(40),
+                1,
+                Some(max_zoom),
+                3,
+            );
+            assert_consistent_across_edge(&RadiusClusterer::new(40, max_zoom), buffer);
+        }
+    }
+
+    #[test]
+    fn row_order_does_not_change_clusters() {
+        let features: Vec<Feature> = (0..50)
+            .map(|step| point_at_pixel(100.0 + (step * 17 % 300) as f64, 200.0, 2))
+            .collect();
+        let coordinates = Coordinates { x: 0, y: 0, z: 2 };
+        let clusterer = RadiusClusterer::new(40, 16);
+        let mut reversed = features.clone();
+        reversed.reverse();
+
+        let mut forward = clusterer.cluster(features, &coordinates);
+        let mut backward = clusterer.cluster(reversed, &coordinates);
+        for clusters in [&mut forward, &mut backward] {
+            clusters.sort_by(|a, b| format!("{:?}", a.geometry).cmp(&format!("{:?}", b.geometry)));
+        }
+        assert_eq!(
+            forward.iter().map(|f| &f.geometry).collect::<Vec<_>>(),
+            backward.iter().map(|f| &f.geometry).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn leaves_points_above_max_zoom() {
+        let features = vec![
+            point_at_pixel(100.0, 100.0, 5),
+            point_at_pixel(101.0, 100.0, 5),
+        ];
+        let clustered =
+            RadiusClusterer::new(40, 4).cluster(features, &Coordinates { x: 0, y: 0, z: 5 });
+        assert_eq!(clustered.len(), 2);
+    }
+
+    #[test]
+    fn merges_clusters_of_the_zooms_above() {
+        // at zoom 3 the first three points form a cluster centered far enough from the last one
+        // to stay apart at zoom 2, where clustering the points directly would merge all four
+        let features = vec![
+            point_at_pixel(100.0, 100.0, 2),
+            point_at_pixel(102.0, 100.0, 2),
+            point_at_pixel(120.0, 100.0, 2),
+            point_at_pixel(150.0, 100.0, 2),
+        ];
+        let coordinates = Coordinates { x: 0, y: 0, z: 2 };
+
+        let single_zoom = RadiusClusterer::new(40, 2).cluster(features.clone(), &coordinates);
+        assert_eq!(single_zoom.len(), 1);
+
+        let mut counts: Vec<Option<i64>> = RadiusClusterer::new(40, 3)
+            .cluster(features, &coordinates)
+            .iter()
+            .map(|feature| {
+                feature
+                    .properties
+                    .get(CLUSTER_COUNT_PROPERTY)
+                    .and_then(PropertyValue::as_i64)
+            })
+            .collect();
+        counts.sort();
+        assert_eq!(counts, [Some(1), Some(3)]);
+    }
+}
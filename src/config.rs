@@ -1,12 +1,67 @@
+use crate::clustering::cluster_algorithm::ClusterAlgorithm;
+use crate::db::property_format::{ArrayFormat, UuidFormat};
+use crate::generalization::property_quantizer::FloatEncoding;
+use crate::simplification::simplify_algorithm::SimplifyAlgorithm;
 use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct LayerConfig {
+    pub query: Option<String>,
+    pub geo_col: Option<String>,
+    pub srid: Option<String>,
+    pub pmtiles: Option<String>,
+    pub mbtiles: Option<String>,
+    pub cache_ttl: Option<u64>,
+    pub cache_zoom_ttls: Option<HashMap<String, u64>>,
+    pub cluster: Option<ClusterAlgorithm>,
+    pub cluster_radius: Option<u32>,
+    pub cluster_max_zoom: Option<u32>,
+    pub simplify: Option<SimplifyAlgorithm>,
+    pub simplify_tolerance: Option<f64>,
+    pub simplify_preserve_topology: Option<bool>,
+    pub min_polygon_area: Option<f64>,
+    pub min_line_length: Option<f64>,
+    pub min_feature_max_zoom: Option<u32>,
+    pub replace_small_features: Option<bool>,
+    pub max_tile_size: Option<usize>,
+    pub max_features: Option<usize>,
+    pub rank_property: Option<String>,
+    pub max_rows: Option<usize>,
+    pub date_format: Option<String>,
+    pub uuid_format: Option<UuidFormat>,
+    pub array_format: Option<ArrayFormat>,
+    pub array_delimiter: Option<String>,
+    pub properties: Option<String>,
+    pub property_aliases: Option<HashMap<String, String>>,
+    pub zoom_properties: Option<HashMap<String, String>>,
+    pub property_precision: Option<HashMap<String, u32>>,
+    pub float_encoding: Option<FloatEncoding>,
+    pub integer_floats: Option<bool>,
+}
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub database_url: String,
     pub cache_url: Option<String>,
+    pub cache_tiers: Option<String>,
+    pub cache_memory_max_bytes: Option<usize>,
+    pub cache_directory: Option<String>,
+    pub cache_lock: Option<bool>,
+    pub expiry_channel: Option<String>,
+    pub expiry_refresh_tiles: Option<usize>,
     pub cache_control_header: Option<String>,
+    pub cache_ttl: Option<u64>,
+    pub cache_zoom_ttls: Option<HashMap<String, u64>>,
+    pub cache_stale_while_revalidate: Option<u64>,
+    pub cache_stale_if_error: Option<u64>,
+    pub cache_encodings: Option<String>,
+    pub cache_empty_ttl: Option<u64>,
+    pub empty_tile_status: Option<u16>,
     pub allowed_origins: Option<String>,
+    pub admin_token: Option<String>,
     pub disable_gzip: Option<bool>,
+    pub layers: Option<HashMap<String, LayerConfig>>,
 }
 
 impl Config {
@@ -17,4 +72,12 @@ impl Config {
             .build()?
             .try_deserialize()
     }
+
+    pub fn get_layer_config(&self, name: &str) -> LayerConfig {
+        self.layers
+            .as_ref()
+            .and_then(|layers| layers.get(&name.to_lowercase()))
+            .cloned()
+            .unwrap_or_default()
+    }
 }
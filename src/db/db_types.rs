@@ -1,7 +1,13 @@
+use crate::db::property_decoder::{column_property_type, decode_property};
+use crate::db::property_format::PropertyFormat;
+use crate::db::property_selection::PropertySelection;
+use crate::mvt::property_value::{Properties, PropertyType};
 use geo_types::Geometry;
 use sqlx::error::BoxDynError;
-use sqlx::postgres::PgTypeInfo;
-use sqlx::{Database, Decode, FromRow, Postgres};
+use sqlx::postgres::{PgColumn, PgHasArrayType, PgRow, PgTypeInfo, PgValueFormat};
+use sqlx::{Column, Database, Decode, Postgres, Row};
+use std::collections::BTreeMap;
+use std::fmt::Write;
 use std::io::Cursor;
 use wkb::WKBReadExt;
 
@@ -25,11 +31,230 @@ impl<'r> Decode<'r, Postgres> for GeometryWkb {
     }
 }
 
-#[derive(Debug, FromRow)]
+/// A NUMERIC as its decimal text, `NaN` or `Infinity`, decoded without the 28 digit limit of
+/// `Decimal`
+#[derive(Debug)]
+pub struct NumericText(pub String);
+
+impl sqlx::Type<Postgres> for NumericText {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("numeric")
+    }
+}
+
+impl PgHasArrayType for NumericText {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("_numeric")
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for NumericText {
+    fn decode(value: <Postgres as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        match value.format() {
+            PgValueFormat::Text => Ok(NumericText(value.as_str()?.to_string())),
+            PgValueFormat::Binary => numeric_text(value.as_bytes()?).map(NumericText),
+        }
+    }
+}
+
+const NUMERIC_NEGATIVE: u16 = 0x4000;
+const NUMERIC_NAN: u16 = 0xC000;
+const NUMERIC_INFINITY: u16 = 0xD000;
+const NUMERIC_NEGATIVE_INFINITY: u16 = 0xF000;
+
+/// Formats the binary NUMERIC layout: the digit count, the weight of the first digit, the sign
+/// and the display scale, then base 10000 digits
+fn numeric_text(bytes: &[u8]) -> Result<String, BoxDynError> {
+    let field = |index: usize| {
+        bytes
+            .get(index * 2..index * 2 + 2)
+            .map(|field| u16::from_be_bytes([field[0], field[1]]))
+            .ok_or("truncated numeric")
+    };
+    let digit_count = field(0)? as usize;
+    let weight = field(1)? as i16 as i64;
+    let sign = field(2)?;
+    let scale = field(3)? as usize;
+    match sign {
+        NUMERIC_NAN => return Ok("NaN".to_string()),
+        NUMERIC_INFINITY => return Ok("Infinity".to_string()),
+        NUMERIC_NEGATIVE_INFINITY => return Ok("-Infinity".to_string()),
+        _ => {}
+    }
+    let digits = (0..digit_count)
+        .map(|index| field(4 + index))
+        .collect::<Result<Vec<u16>, _>>()?;
+    let digit = |index: i64| match usize::try_from(index) {
+        Ok(index) => digits.get(index).copied().unwrap_or(0),
+        Err(_) => 0,
+    };
+
+    let mut text = String::new();
+    if sign == NUMERIC_NEGATIVE {
+        text.push('-');
+    }
+    if weight < 0 {
+        text.push('0');
+    }
+    for index in 0..=weight {
+        match index {
+            0 => write!(text, "{}", digit(index))?,
+            _ => write!(text, "{:04}", digit(index))?,
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let mut index = weight + 1;
+        while fraction.len() < scale {
+            write!(fraction, "{:04}", digit(index))?;
+            index += 1;
+        }
+        fraction.truncate(scale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Ok(text)
+}
+
+#[derive(Debug)]
 pub struct TileRow {
-    #[sqlx(rename = "__internal_geometry_bin__")]
     pub geometry_bin: GeometryWkb,
-    #[sqlx(rename = "h3clustercount")]
     pub h3_cluster_count: i64,
-    pub properties: Option<serde_json::Value>,
+    pub properties: Properties,
+}
+
+/// Selected columns of the user query, except the geometry column, with their property names
+fn property_columns<'a>(
+    row: &'a PgRow,
+    geo_col: &'a str,
+    property_selection: &'a PropertySelection,
+) -> impl Iterator<Item = (&'a PgColumn, &'a str)> {
+    row.columns().iter().filter_map(move |column| {
+        let name = column.name();
+        if name == geo_col || name == "h3clustercount" || name.starts_with("__internal_") {
+            return None;
+        }
+        Some((column, property_selection.select(name)?))
+    })
+}
+
+/// Types of the properties `decode_properties` reads from the rows of a query, known from the
+/// column types before any value is read
+pub fn property_types(
+    row: &PgRow,
+    geo_col: &str,
+    property_format: &PropertyFormat,
+    property_selection: &PropertySelection,
+) -> BTreeMap<String, PropertyType> {
+    property_columns(row, geo_col, property_selection)
+        .filter_map(|(column, property_name)| {
+            let property_type = column_property_type(column, property_format)?;
+            Some((property_name.to_string(), property_type))
+        })
+        .collect()
+}
+
+/// Reads the selected columns of the user query, except the geometry column, as typed properties
+pub fn decode_properties(
+    row: &PgRow,
+    geo_col: &str,
+    property_format: &PropertyFormat,
+    property_selection: &PropertySelection,
+) -> Properties {
+    let mut properties = Properties::new();
+    for (column, property_name) in property_columns(row, geo_col, property_selection) {
+        let name = column.name();
+
+        match decode_property(row, column, property_format) {
+            Ok(Some(value)) => {
+                properties.insert(property_name.to_string(), value);
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::warn!("dropping property {} it failed to decode: {}", name, error)
+            }
+        }
+    }
+    properties
+}
+
+impl TileRow {
+    pub fn from_row(
+        row: &PgRow,
+        geo_col: &str,
+        property_format: &PropertyFormat,
+        property_selection: &PropertySelection,
+    ) -> Result<Self, sqlx::Error> {
+        let geometry_bin = row.try_get("__internal_geometry_bin__")?;
+        let h3_cluster_count = row.try_get("h3clustercount")?;
+        let properties = decode_properties(row, geo_col, property_format, property_selection);
+
+        Ok(TileRow {
+            geometry_bin,
+            h3_cluster_count,
+            properties,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(weight: i16, sign: u16, scale: u16, digits: &[u16]) -> Vec<u8> {
+        [digits.len() as u16, weight as u16, sign, scale]
+            .iter()
+            .chain(digits)
+            .flat_map(|field| field.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn formats_binary_numerics() {
+        assert_eq!(numeric_text(&numeric(0, 0, 0, &[])).unwrap(), "0");
+        assert_eq!(
+            numeric_text(&numeric(1, 0, 0, &[12, 3456])).unwrap(),
+            "123456"
+        );
+        assert_eq!(
+            numeric_text(&numeric(0, NUMERIC_NEGATIVE, 3, &[12, 3400])).unwrap(),
+            "-12.340"
+        );
+        assert_eq!(numeric_text(&numeric(-1, 0, 6, &[12])).unwrap(), "0.001200");
+        assert_eq!(
+            numeric_text(&numeric(-2, 0, 8, &[12])).unwrap(),
+            "0.00000012"
+        );
+        assert_eq!(numeric_text(&numeric(2, 0, 0, &[1])).unwrap(), "100000000");
+    }
+
+    #[test]
+    fn formats_numerics_beyond_decimal_precision() {
+        let digits = [1, 2345, 6789, 123, 4567, 8901, 2345, 6789, 123, 4567];
+        assert_eq!(
+            numeric_text(&numeric(9, 0, 0, &digits)).unwrap(),
+            "1234567890123456789012345678901234567"
+        );
+    }
+
+    #[test]
+    fn formats_special_numerics() {
+        assert_eq!(
+            numeric_text(&numeric(0, NUMERIC_NAN, 0, &[])).unwrap(),
+            "NaN"
+        );
+        assert_eq!(
+            numeric_text(&numeric(0, NUMERIC_INFINITY, 0, &[])).unwrap(),
+            "Infinity"
+        );
+        assert_eq!(
+            numeric_text(&numeric(0, NUMERIC_NEGATIVE_INFINITY, 0, &[])).unwrap(),
+            "-Infinity"
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_numerics() {
+        assert!(numeric_text(&numeric(1, 0, 0, &[12, 3456])[..10]).is_err());
+    }
 }
@@ -1 +1,4 @@
 pub mod db_types;
+mod property_decoder;
+pub mod property_format;
+pub mod property_selection;
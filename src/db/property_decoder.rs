@@ -0,0 +1,190 @@
+use crate::db::db_types::NumericText;
+use crate::db::property_format::{DateFormat, PropertyFormat};
+use crate::mvt::property_value::{PropertyType, PropertyValue};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::{PgColumn, PgHasArrayType, PgRow, PgTypeKind};
+use sqlx::{Column, Decode, Postgres, Row, Type, TypeInfo};
+use uuid::Uuid;
+
+fn get<T>(row: &PgRow, index: usize) -> Result<Option<T>, sqlx::Error>
+where
+    T: for<'r> Decode<'r, Postgres> + Type<Postgres>,
+{
+    row.try_get::<Option<T>, _>(index)
+}
+
+fn get_array<T>(
+    row: &PgRow,
+    index: usize,
+    format: &PropertyFormat,
+    to_value: impl Fn(T) -> PropertyValue,
+) -> Result<Option<PropertyValue>, sqlx::Error>
+where
+    T: for<'r> Decode<'r, Postgres> + Type<Postgres> + PgHasArrayType,
+{
+    let elements = row.try_get::<Option<Vec<Option<T>>>, _>(index)?;
+    Ok(elements.map(|elements| {
+        format.format_array(
+            elements
+                .into_iter()
+                .map(|element| element.map(&to_value))
+                .collect(),
+        )
+    }))
+}
+
+/// Whole numerics become integers so they keep their precision, the rest become doubles. Numerics
+/// `Decimal` can't hold, such as `NaN` or more than 28 digits, become doubles as well, or their
+/// text when out of the range of doubles.
+fn numeric_value(NumericText(text): NumericText) -> PropertyValue {
+    if let Ok(value) = text.parse::<Decimal>() {
+        if value.fract().is_zero() {
+            if let Some(value) = value.to_i64() {
+                return PropertyValue::Int(value);
+            }
+        }
+        if let Some(value) = value.to_f64() {
+            return PropertyValue::Double(value);
+        }
+    }
+    match text.parse::<f64>() {
+        Ok(value) if value.is_finite() || !text.chars().any(|char| char.is_ascii_digit()) => {
+            PropertyValue::Double(value)
+        }
+        _ => PropertyValue::String(text),
+    }
+}
+
+fn time_value(value: NaiveTime) -> PropertyValue {
+    PropertyValue::String(value.format("%H:%M:%S%.f").to_string())
+}
+
+/// Type of the properties `decode_property` reads from a column, `None` for unsupported types
+pub fn column_property_type(column: &PgColumn, format: &PropertyFormat) -> Option<PropertyType> {
+    let type_info = column.type_info();
+    let property_type = match type_info.name() {
+        "BOOL" => PropertyType::Bool,
+        "INT2" | "INT4" | "INT8" => PropertyType::Int,
+        "OID" => PropertyType::UInt,
+        "FLOAT4" => PropertyType::Float,
+        "FLOAT8" | "NUMERIC" => PropertyType::Double,
+        "DATE" | "TIMESTAMP" | "TIMESTAMPTZ" => match format.date_format {
+            DateFormat::EpochSeconds | DateFormat::EpochMillis => PropertyType::Int,
+            DateFormat::Rfc3339 | DateFormat::Pattern(_) => PropertyType::String,
+        },
+        "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "citext" | "JSON" | "JSONB" | "UUID" | "TIME"
+        | "BOOL[]" | "INT2[]" | "INT4[]" | "INT8[]" | "FLOAT4[]" | "FLOAT8[]" | "NUMERIC[]"
+        | "TEXT[]" | "VARCHAR[]" | "CHAR[]" | "NAME[]" | "UUID[]" | "DATE[]" | "TIMESTAMP[]"
+        | "TIMESTAMPTZ[]" => PropertyType::String,
+        _ if matches!(type_info.kind(), PgTypeKind::Enum(_)) => PropertyType::String,
+        _ => return None,
+    };
+    Some(property_type)
+}
+
+/// Decodes a column into a property based on its Postgres type, `None` for nulls and unsupported types
+pub fn decode_property(
+    row: &PgRow,
+    column: &PgColumn,
+    format: &PropertyFormat,
+) -> Result<Option<PropertyValue>, sqlx::Error> {
+    let index = column.ordinal();
+    let type_info = column.type_info();
+
+    let value = match type_info.name() {
+        "BOOL" => get::<bool>(row, index)?.map(PropertyValue::Bool),
+        "INT2" => get::<i16>(row, index)?.map(|value| PropertyValue::Int(value as i64)),
+        "INT4" => get::<i32>(row, index)?.map(|value| PropertyValue::Int(value as i64)),
+        "INT8" => get::<i64>(row, index)?.map(PropertyValue::Int),
+        "OID" => get::<Oid>(row, index)?.map(|value| PropertyValue::UInt(value.0 as u64)),
+        "FLOAT4" => get::<f32>(row, index)?.map(PropertyValue::Float),
+        "FLOAT8" => get::<f64>(row, index)?.map(PropertyValue::Double),
+        "NUMERIC" => get::<NumericText>(row, index)?.map(numeric_value),
+        "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "citext" => {
+            get::<String>(row, index)?.map(PropertyValue::String)
+        }
+        "JSON" | "JSONB" => get::<serde_json::Value>(row, index)?
+            .map(|value| PropertyValue::String(value.to_string())),
+        "UUID" => get::<Uuid>(row, index)?.map(|value| format.format_uuid(value)),
+        "DATE" => get::<NaiveDate>(row, index)?.map(|value| format.format_date(value)),
+        "TIME" => get::<NaiveTime>(row, index)?.map(time_value),
+        "TIMESTAMP" => {
+            get::<NaiveDateTime>(row, index)?.map(|value| format.format_naive_timestamp(value))
+        }
+        "TIMESTAMPTZ" => {
+            get::<DateTime<Utc>>(row, index)?.map(|value| format.format_timestamp(value))
+        }
+        "BOOL[]" => get_array::<bool>(row, index, format, PropertyValue::Bool)?,
+        "INT2[]" => get_array::<i16>(row, index, format, |value| PropertyValue::Int(value as i64))?,
+        "INT4[]" => get_array::<i32>(row, index, format, |value| PropertyValue::Int(value as i64))?,
+        "INT8[]" => get_array::<i64>(row, index, format, PropertyValue::Int)?,
+        "FLOAT4[]" => get_array::<f32>(row, index, format, PropertyValue::Float)?,
+        "FLOAT8[]" => get_array::<f64>(row, index, format, PropertyValue::Double)?,
+        "NUMERIC[]" => get_array::<NumericText>(row, index, format, numeric_value)?,
+        "TEXT[]" | "VARCHAR[]" | "CHAR[]" | "NAME[]" => {
+            get_array::<String>(row, index, format, PropertyValue::String)?
+        }
+        "UUID[]" => get_array::<Uuid>(row, index, format, |value| format.format_uuid(value))?,
+        "DATE[]" => get_array::<NaiveDate>(row, index, format, |value| format.format_date(value))?,
+        "TIMESTAMP[]" => get_array::<NaiveDateTime>(row, index, format, |value| {
+            format.format_naive_timestamp(value)
+        })?,
+        "TIMESTAMPTZ[]" => {
+            get_array::<DateTime<Utc>>(row, index, format, |value| format.format_timestamp(value))?
+        }
+        _ if matches!(type_info.kind(), PgTypeKind::Enum(_)) => row
+            .try_get_unchecked::<Option<String>, _>(index)?
+            .map(PropertyValue::String),
+        name => {
+            tracing::debug!(
+                "skipping property {} with unsupported type {}",
+                column.name(),
+                name
+            );
+            None
+        }
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(text: &str) -> PropertyValue {
+        numeric_value(NumericText(text.to_string()))
+    }
+
+    #[test]
+    fn whole_numerics_become_integers() {
+        assert!(matches!(value("42"), PropertyValue::Int(42)));
+        assert!(matches!(value("-7.000"), PropertyValue::Int(-7)));
+    }
+
+    #[test]
+    fn fractional_numerics_become_doubles() {
+        assert!(matches!(value("1.25"), PropertyValue::Double(value) if value == 1.25));
+    }
+
+    #[test]
+    fn numerics_beyond_decimal_become_doubles() {
+        let long = "1234567890123456789012345678901234567";
+        assert!(
+            matches!(value(long), PropertyValue::Double(value) if value == 1.2345678901234568e36)
+        );
+        assert!(matches!(value("NaN"), PropertyValue::Double(value) if value.is_nan()));
+        assert!(
+            matches!(value("-Infinity"), PropertyValue::Double(value) if value == f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn numerics_beyond_doubles_keep_their_text() {
+        let huge = format!("1{}", "0".repeat(400));
+        assert!(matches!(value(&huge), PropertyValue::String(text) if text == huge));
+    }
+}
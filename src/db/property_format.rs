@@ -0,0 +1,131 @@
+use crate::config::LayerConfig;
+use crate::mvt::property_value::PropertyValue;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+const DEFAULT_ARRAY_DELIMITER: &str = ",";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UuidFormat {
+    #[default]
+    Hyphenated,
+    Simple,
+    Urn,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArrayFormat {
+    /// JSON array string
+    #[default]
+    Json,
+    /// Elements joined by the array delimiter
+    Delimited,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DateFormat {
+    Rfc3339,
+    EpochSeconds,
+    EpochMillis,
+    /// A chrono strftime pattern
+    Pattern(String),
+}
+
+impl From<&str> for DateFormat {
+    fn from(value: &str) -> Self {
+        match value {
+            "rfc3339" => DateFormat::Rfc3339,
+            "epoch_seconds" => DateFormat::EpochSeconds,
+            "epoch_millis" => DateFormat::EpochMillis,
+            pattern => DateFormat::Pattern(pattern.to_string()),
+        }
+    }
+}
+
+/// How column types without an MVT equivalent are written as properties
+#[derive(Clone, Debug)]
+pub struct PropertyFormat {
+    pub date_format: DateFormat,
+    pub uuid_format: UuidFormat,
+    pub array_format: ArrayFormat,
+    pub array_delimiter: String,
+}
+
+impl PropertyFormat {
+    pub fn from_layer_config(layer_config: &LayerConfig) -> Self {
+        Self {
+            date_format: layer_config
+                .date_format
+                .as_deref()
+                .map_or(DateFormat::Rfc3339, DateFormat::from),
+            uuid_format: layer_config.uuid_format.unwrap_or_default(),
+            array_format: layer_config.array_format.unwrap_or_default(),
+            array_delimiter: layer_config
+                .array_delimiter
+                .clone()
+                .unwrap_or(DEFAULT_ARRAY_DELIMITER.to_string()),
+        }
+    }
+
+    pub fn format_timestamp(&self, timestamp: DateTime<Utc>) -> PropertyValue {
+        match &self.date_format {
+            DateFormat::Rfc3339 => PropertyValue::String(timestamp.to_rfc3339()),
+            DateFormat::EpochSeconds => PropertyValue::Int(timestamp.timestamp()),
+            DateFormat::EpochMillis => PropertyValue::Int(timestamp.timestamp_millis()),
+            DateFormat::Pattern(pattern) => {
+                PropertyValue::String(timestamp.format(pattern).to_string())
+            }
+        }
+    }
+
+    pub fn format_naive_timestamp(&self, timestamp: NaiveDateTime) -> PropertyValue {
+        match &self.date_format {
+            DateFormat::Rfc3339 => {
+                PropertyValue::String(timestamp.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+            }
+            _ => self.format_timestamp(timestamp.and_utc()),
+        }
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> PropertyValue {
+        match &self.date_format {
+            DateFormat::Rfc3339 => PropertyValue::String(date.format("%Y-%m-%d").to_string()),
+            _ => self.format_timestamp(date.and_time(Default::default()).and_utc()),
+        }
+    }
+
+    pub fn format_uuid(&self, uuid: Uuid) -> PropertyValue {
+        let formatted = match self.uuid_format {
+            UuidFormat::Hyphenated => uuid.hyphenated().to_string(),
+            UuidFormat::Simple => uuid.simple().to_string(),
+            UuidFormat::Urn => uuid.urn().to_string(),
+        };
+        PropertyValue::String(formatted)
+    }
+
+    pub fn format_array(&self, elements: Vec<Option<PropertyValue>>) -> PropertyValue {
+        match self.array_format {
+            ArrayFormat::Json => {
+                let elements: Vec<serde_json::Value> = elements
+                    .iter()
+                    .map(|element| {
+                        element
+                            .as_ref()
+                            .map_or(serde_json::Value::Null, PropertyValue::to_json)
+                    })
+                    .collect();
+                PropertyValue::String(serde_json::Value::Array(elements).to_string())
+            }
+            ArrayFormat::Delimited => {
+                let elements: Vec<String> = elements
+                    .iter()
+                    .map(|element| element.as_ref().map_or(String::new(), |e| e.to_string()))
+                    .collect();
+                PropertyValue::String(elements.join(&self.array_delimiter))
+            }
+        }
+    }
+}
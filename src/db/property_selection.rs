@@ -0,0 +1,58 @@
+use crate::config::LayerConfig;
+use std::collections::{HashMap, HashSet};
+
+fn parse_list(list: &str) -> HashSet<String> {
+    list.split([' ', ','])
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Which columns become properties at a zoom and what they are renamed to
+#[derive(Clone, Debug, Default)]
+pub struct PropertySelection {
+    include: Option<HashSet<String>>,
+    aliases: HashMap<String, String>,
+}
+
+impl PropertySelection {
+    /// Uses the zoom property set with the highest minimum zoom at or below `zoom`, falling back to
+    /// the layer's property list
+    pub fn for_zoom(layer_config: &LayerConfig, zoom: u32) -> Self {
+        let zoom_properties = layer_config.zoom_properties.as_ref().and_then(|sets| {
+            sets.iter()
+                .filter_map(|(min_zoom, list)| Some((min_zoom.parse::<u32>().ok()?, list)))
+                .filter(|(min_zoom, _)| *min_zoom <= zoom)
+                .max_by_key(|(min_zoom, _)| *min_zoom)
+                .map(|(_, list)| list)
+        });
+
+        let include = zoom_properties
+            .or(layer_config.properties.as_ref())
+            .map(|list| parse_list(list));
+
+        Self {
+            include,
+            aliases: layer_config.property_aliases.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Uses the layer's property list regardless of zoom
+    pub fn for_layer(layer_config: &LayerConfig) -> Self {
+        Self {
+            include: layer_config.properties.as_deref().map(parse_list),
+            aliases: layer_config.property_aliases.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Returns the property name for a column or `None` when it is left out
+    pub fn select<'a>(&'a self, column: &'a str) -> Option<&'a str> {
+        if let Some(include) = &self.include {
+            if !include.contains(column) {
+                return None;
+            }
+        }
+
+        Some(self.aliases.get(column).map_or(column, String::as_str))
+    }
+}
@@ -1,10 +1,24 @@
 use crate::cache::cache_provider::CacheProvider;
+use crate::cache::hot_tiles::HotTiles;
+use crate::cache::single_flight::SingleFlight;
 use crate::config::Config;
+use crate::routes::mvt_handler::{TileRequest, TileResult};
+use crate::tiling::tile_source::TileSource;
 use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// PMTiles and MBTiles sources of static layers, keyed by lowercase layer name
+pub type StaticLayers = Arc<HashMap<String, Arc<TileSource>>>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: PgPool,
     pub cache: CacheProvider,
     pub config: Config,
+    pub static_layers: StaticLayers,
+    /// Tiles being rendered, shared with concurrent requests for the same tile
+    pub flights: Arc<SingleFlight<TileResult>>,
+    /// Recently requested tiles, rendered again when a notification expires them
+    pub hot_tiles: Option<Arc<HotTiles<TileRequest>>>,
 }
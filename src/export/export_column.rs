@@ -0,0 +1,50 @@
+use crate::db::property_selection::PropertySelection;
+use sqlx::postgres::PgRow;
+use sqlx::{Column, Row, TypeInfo};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportColumnType {
+    Bool,
+    Long,
+    Float,
+    Double,
+    String,
+}
+
+/// A property column of an export, known before the first feature is written
+#[derive(Clone, Debug)]
+pub struct ExportColumn {
+    pub name: String,
+    pub column_type: ExportColumnType,
+}
+
+impl ExportColumnType {
+    fn from_type_name(type_name: &str) -> Self {
+        match type_name {
+            "BOOL" => ExportColumnType::Bool,
+            "INT2" | "INT4" | "INT8" | "OID" => ExportColumnType::Long,
+            "FLOAT4" => ExportColumnType::Float,
+            "FLOAT8" | "NUMERIC" => ExportColumnType::Double,
+            _ => ExportColumnType::String,
+        }
+    }
+}
+
+/// Lists the property columns of a row in query order, with the same selection and aliases as its
+/// decoded properties
+pub fn export_columns(
+    row: &PgRow,
+    geo_col: &str,
+    property_selection: &PropertySelection,
+) -> Vec<ExportColumn> {
+    row.columns()
+        .iter()
+        .filter(|column| column.name() != geo_col && !column.name().starts_with("__internal_"))
+        .filter_map(|column| {
+            Some(ExportColumn {
+                name: property_selection.select(column.name())?.to_string(),
+                column_type: ExportColumnType::from_type_name(column.type_info().name()),
+            })
+        })
+        .collect()
+}
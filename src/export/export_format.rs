@@ -0,0 +1,30 @@
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    #[serde(alias = "geojsonl", alias = "ndjson")]
+    GeoJsonSeq,
+    #[serde(alias = "fgb")]
+    FlatGeobuf,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::GeoJsonSeq => "application/geo+json-seq",
+            ExportFormat::FlatGeobuf => "application/flatgeobuf",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::GeoJsonSeq => "geojsonl",
+            ExportFormat::FlatGeobuf => "fgb",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use geo_types::Geometry;
+use indoc::indoc;
+use std::str::FromStr;
+use wkt::{ToWkt, Wkt};
+
+/// Builds a polygon in longitude/latitude from a `min_x,min_y,max_x,max_y` bounding box
+pub fn bbox_to_wkt(bbox: &str) -> Option<String> {
+    let values: Vec<f64> = bbox
+        .split(',')
+        .map(|value| value.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let [min_x, min_y, max_x, max_y] = values[..] else {
+        return None;
+    };
+    if values.iter().any(|value| !value.is_finite()) {
+        return None;
+    }
+
+    Some(format!(
+        "POLYGON(({min_x} {min_y},{max_x} {min_y},{max_x} {max_y},{min_x} {max_y},{min_x} {min_y}))"
+    ))
+}
+
+/// Checks a WKT polygon or multipolygon in longitude/latitude and writes it back in a canonical form,
+/// so only well formed areas reach the database
+pub fn polygon_to_wkt(polygon: &str) -> Option<String> {
+    let wkt = Wkt::<f64>::from_str(polygon.trim()).ok()?;
+    let geometry = Geometry::try_from(wkt).ok()?;
+    let polygons = match &geometry {
+        Geometry::Polygon(polygon) => std::slice::from_ref(polygon),
+        Geometry::MultiPolygon(multi_polygon) => multi_polygon.0.as_slice(),
+        _ => return None,
+    };
+    let mut rings = polygons
+        .iter()
+        .flat_map(|polygon| [polygon.exterior()].into_iter().chain(polygon.interiors()));
+    if polygons.is_empty()
+        || rings.any(|ring| {
+            ring.0.len() < 4
+                || ring
+                    .coords()
+                    .any(|coord| !coord.x.is_finite() || !coord.y.is_finite())
+        })
+    {
+        return None;
+    }
+    Some(geometry.wkt_string())
+}
+
+/// Selects every row of the query intersecting the export area, bound as WKT in `$1`, with its
+/// unsimplified geometry in longitude/latitude
+pub fn get_export_query(query: &str, geo_col: &str, srid: &str) -> String {
+    format!(
+        indoc! {r#"
+        SELECT
+            t.*,
+            ST_AsBinary(ST_Transform({geo_col}, 4326)) as __internal_geometry_bin__
+        FROM ({query}) t
+        WHERE
+            ST_INTERSECTS(ST_Transform(ST_GeomFromText($1, 4326), {srid}), {geo_col})
+    "#},
+        query = query,
+        geo_col = geo_col,
+        srid = srid
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_polygons_from_bboxes() {
+        assert_eq!(
+            bbox_to_wkt("-1, -2,3,4").as_deref(),
+            Some("POLYGON((-1 -2,3 -2,3 4,-1 4,-1 -2))")
+        );
+        assert_eq!(bbox_to_wkt("1,2,3"), None);
+        assert_eq!(bbox_to_wkt("1,2,3,inf"), None);
+        assert_eq!(bbox_to_wkt("1,2,3,a"), None);
+    }
+
+    #[test]
+    fn accepts_polygons_and_multipolygons() {
+        assert_eq!(
+            polygon_to_wkt(" POLYGON((0 0, 1 0, 1 1, 0 0)) ").as_deref(),
+            Some("POLYGON((0 0,1 0,1 1,0 0))")
+        );
+        assert!(polygon_to_wkt("MULTIPOLYGON(((0 0,1 0,1 1,0 0)),((2 2,3 2,3 3,2 2)))").is_some());
+    }
+
+    #[test]
+    fn rejects_other_or_malformed_geometries() {
+        assert_eq!(polygon_to_wkt("POINT(1 2)"), None);
+        assert_eq!(polygon_to_wkt("POLYGON((0 0, 1 0, 1 1"), None);
+        assert_eq!(polygon_to_wkt("POLYGON((0 0, 1 1))"), None);
+        assert_eq!(polygon_to_wkt("POLYGON EMPTY"), None);
+        assert_eq!(polygon_to_wkt("1) OR 1=1 --"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::config::LayerConfig;
+use crate::db::db_types::{decode_properties, GeometryWkb};
+use crate::db::property_format::PropertyFormat;
+use crate::db::property_selection::PropertySelection;
+use crate::export::export_column::export_columns;
+use crate::export::export_format::ExportFormat;
+use crate::export::export_query_constructor::get_export_query;
+use crate::export::export_writer::ExportWriter;
+use crate::mvt::mapbox_vector_tile::Feature;
+use bytes::Bytes;
+use futures::{Stream, TryStreamExt};
+use sqlx::{PgPool, Row};
+use std::io;
+use tokio::sync::mpsc::{channel, Sender};
+
+/// Bytes collected before a chunk is handed to the response body
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub struct ExportLayer {
+    pub name: String,
+    pub config: LayerConfig,
+    pub query: String,
+    pub geo_col: String,
+    pub srid: String,
+}
+
+pub struct ExportService {
+    pool: PgPool,
+}
+
+type ChunkSender = Sender<Result<Bytes, io::Error>>;
+
+/// Sends a chunk, returning false once the client has gone away
+async fn send_chunk(sender: &ChunkSender, chunk: &mut Vec<u8>) -> bool {
+    let bytes = Bytes::from(std::mem::take(chunk));
+    sender.send(Ok(bytes)).await.is_ok()
+}
+
+async fn write_export(
+    pool: &PgPool,
+    layer: &ExportLayer,
+    area: &str,
+    format: ExportFormat,
+    sender: &ChunkSender,
+) -> Result<(), sqlx::Error> {
+    let raw_query = get_export_query(&layer.query, &layer.geo_col, &layer.srid);
+    let property_format = PropertyFormat::from_layer_config(&layer.config);
+    let property_selection = PropertySelection::for_layer(&layer.config);
+    let mut rows = sqlx::query(&raw_query).bind(area).fetch(pool);
+
+    let mut writer: Option<ExportWriter> = None;
+    let mut chunk = vec![];
+    while let Some(row) = rows.try_next().await? {
+        // columns are only known once the first row arrives
+        let writer = writer.get_or_insert_with(|| {
+            let columns = export_columns(&row, &layer.geo_col, &property_selection);
+            let writer = ExportWriter::new(format, &layer.name, columns);
+            chunk.extend(writer.header());
+            writer
+        });
+
+        let geometry: GeometryWkb = row.try_get("__internal_geometry_bin__")?;
+        let feature = Feature {
+            geometry: geometry.0,
+            properties: decode_properties(
+                &row,
+                &layer.geo_col,
+                &property_format,
+                &property_selection,
+            ),
+        };
+        chunk.extend(writer.write_feature(&feature));
+
+        if chunk.len() >= CHUNK_SIZE && !send_chunk(sender, &mut chunk).await {
+            return Ok(());
+        }
+    }
+
+    if writer.is_none() {
+        chunk.extend(ExportWriter::new(format, &layer.name, vec![]).header());
+    }
+    send_chunk(sender, &mut chunk).await;
+    Ok(())
+}
+
+impl ExportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Streams every feature of the layer intersecting `area`, a WKT geometry in longitude/latitude.
+    /// Rows are read in a background task and written to the stream in chunks as they arrive.
+    pub fn export(
+        self,
+        layer: ExportLayer,
+        area: String,
+        format: ExportFormat,
+    ) -> impl Stream<Item = Result<Bytes, io::Error>> {
+        let (sender, receiver) = channel(4);
+        tokio::spawn(async move {
+            if let Err(error) = write_export(&self.pool, &layer, &area, format, &sender).await {
+                tracing::warn!("export of layer {} failed: {}", layer.name, error);
+                let _ = sender.send(Err(io::Error::other(error))).await;
+            }
+        });
+
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        })
+    }
+}
@@ -0,0 +1,191 @@
+use crate::export::export_column::ExportColumn;
+use crate::export::export_format::ExportFormat;
+use crate::export::flatgeobuf_writer::FlatGeobufWriter;
+use crate::mvt::mapbox_vector_tile::Feature;
+use geo_types::{Coord, Geometry, LineString, Polygon};
+use serde_json::{json, Map, Value};
+use wkt::ToWkt;
+
+/// Quotes a CSV field when it holds a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn position(coord: &Coord) -> Value {
+    json!([coord.x, coord.y])
+}
+
+fn line_string_to_json(line_string: &LineString) -> Value {
+    Value::Array(line_string.0.iter().map(position).collect())
+}
+
+fn polygon_to_json(polygon: &Polygon) -> Value {
+    Value::Array(
+        [polygon.exterior()]
+            .into_iter()
+            .chain(polygon.interiors())
+            .map(line_string_to_json)
+            .collect(),
+    )
+}
+
+/// GeoJSON geometry object of a geometry in longitude/latitude
+fn geometry_to_json(geometry: &Geometry) -> Option<Value> {
+    let (geometry_type, coordinates) = match geometry {
+        Geometry::Point(point) => ("Point", position(&point.0)),
+        Geometry::MultiPoint(multi_point) => (
+            "MultiPoint",
+            Value::Array(multi_point.iter().map(|point| position(&point.0)).collect()),
+        ),
+        Geometry::LineString(line_string) => ("LineString", line_string_to_json(line_string)),
+        Geometry::MultiLineString(multi_line_string) => (
+            "MultiLineString",
+            Value::Array(multi_line_string.iter().map(line_string_to_json).collect()),
+        ),
+        Geometry::Polygon(polygon) => ("Polygon", polygon_to_json(polygon)),
+        Geometry::MultiPolygon(multi_polygon) => (
+            "MultiPolygon",
+            Value::Array(multi_polygon.iter().map(polygon_to_json).collect()),
+        ),
+        _ => return None,
+    };
+
+    Some(json!({ "type": geometry_type, "coordinates": coordinates }))
+}
+
+fn feature_to_json(feature: &Feature) -> Option<Value> {
+    let properties: Map<String, Value> = feature
+        .properties
+        .iter()
+        .map(|(key, value)| (key.clone(), value.to_json()))
+        .collect();
+
+    Some(json!({
+        "type": "Feature",
+        "geometry": geometry_to_json(&feature.geometry)?,
+        "properties": properties,
+    }))
+}
+
+/// Encodes exported features one at a time into chunks of the response body
+pub enum ExportWriter {
+    GeoJsonSeq,
+    FlatGeobuf(FlatGeobufWriter),
+    Csv(Vec<ExportColumn>),
+}
+
+impl ExportWriter {
+    pub fn new(format: ExportFormat, name: &str, columns: Vec<ExportColumn>) -> Self {
+        match format {
+            ExportFormat::GeoJsonSeq => ExportWriter::GeoJsonSeq,
+            ExportFormat::FlatGeobuf => {
+                ExportWriter::FlatGeobuf(FlatGeobufWriter::new(name, columns))
+            }
+            ExportFormat::Csv => ExportWriter::Csv(columns),
+        }
+    }
+
+    pub fn header(&self) -> Vec<u8> {
+        match self {
+            ExportWriter::GeoJsonSeq => vec![],
+            ExportWriter::FlatGeobuf(writer) => writer.header(),
+            ExportWriter::Csv(columns) => {
+                let mut fields = vec!["wkt".to_string()];
+                fields.extend(columns.iter().map(|column| csv_field(&column.name)));
+                format!("{}\n", fields.join(",")).into_bytes()
+            }
+        }
+    }
+
+    /// Features are in longitude/latitude
+    pub fn write_feature(&self, feature: &Feature) -> Vec<u8> {
+        match self {
+            ExportWriter::GeoJsonSeq => match feature_to_json(feature) {
+                Some(feature) => format!("{}\n", feature).into_bytes(),
+                None => vec![],
+            },
+            ExportWriter::FlatGeobuf(writer) => writer.write_feature(feature),
+            ExportWriter::Csv(columns) => {
+                let mut fields = vec![csv_field(&feature.geometry.wkt_string())];
+                fields.extend(columns.iter().map(|column| {
+                    feature
+                        .properties
+                        .get(&column.name)
+                        .map(|value| csv_field(&value.to_string()))
+                        .unwrap_or_default()
+                }));
+                format!("{}\n", fields.join(",")).into_bytes()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::export_column::ExportColumnType;
+    use crate::mvt::property_value::PropertyValue;
+    use geo_types::{line_string, point};
+
+    fn feature(geometry: Geometry) -> Feature {
+        Feature {
+            geometry,
+            properties: [
+                (
+                    "name".to_string(),
+                    PropertyValue::String("a, \"b\"".to_string()),
+                ),
+                ("lanes".to_string(), PropertyValue::Int(2)),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+
+    #[test]
+    fn writes_geojson_features_one_per_line() {
+        let writer = ExportWriter::new(ExportFormat::GeoJsonSeq, "roads", vec![]);
+        let line = writer.write_feature(&feature(Geometry::LineString(
+            line_string![(x: 1.5, y: 2.0), (x: 3.0, y: 4.0)],
+        )));
+
+        assert_eq!(line.last(), Some(&b'\n'));
+        let value: Value = serde_json::from_slice(&line).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "LineString", "coordinates": [[1.5, 2.0], [3.0, 4.0]]},
+                "properties": {"name": "a, \"b\"", "lanes": 2},
+            })
+        );
+    }
+
+    #[test]
+    fn writes_csv_rows_with_quoted_fields() {
+        let columns = vec![
+            ExportColumn {
+                name: "name".to_string(),
+                column_type: ExportColumnType::String,
+            },
+            ExportColumn {
+                name: "missing".to_string(),
+                column_type: ExportColumnType::Long,
+            },
+        ];
+        let writer = ExportWriter::new(ExportFormat::Csv, "roads", columns);
+
+        assert_eq!(writer.header(), b"wkt,name,missing\n");
+        assert_eq!(
+            String::from_utf8(
+                writer.write_feature(&feature(Geometry::Point(point!(x: 1.0, y: 2.0))))
+            )
+            .unwrap(),
+            "POINT(1 2),\"a, \"\"b\"\"\",\n"
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use crate::export::export_column::{ExportColumn, ExportColumnType};
+use crate::mvt::mapbox_vector_tile::Feature;
+use crate::mvt::property_value::{Properties, PropertyValue};
+use flatbuffers::{FlatBufferBuilder, WIPOffset};
+use flatgeobuf::{
+    Column, ColumnArgs, ColumnType, Crs, CrsArgs, Feature as FgbFeature, FeatureArgs,
+    Geometry as FgbGeometry, GeometryArgs, GeometryType, Header, HeaderArgs,
+};
+use geo_types::{Coord, Geometry, LineString, Polygon};
+
+const MAGIC_BYTES: [u8; 8] = [b'f', b'g', b'b', 3, b'f', b'g', b'b', 0];
+
+/// Writes features as a FlatGeobuf without a spatial index so it can be streamed as rows arrive
+pub struct FlatGeobufWriter {
+    name: String,
+    columns: Vec<ExportColumn>,
+}
+
+fn push_coords<'a>(xy: &mut Vec<f64>, coords: impl Iterator<Item = &'a Coord>) {
+    for coord in coords {
+        xy.push(coord.x);
+        xy.push(coord.y);
+    }
+}
+
+/// Flattens rings or lines into one coordinate list, `ends` holding the end index of each part
+fn push_parts<'a>(
+    xy: &mut Vec<f64>,
+    ends: &mut Vec<u32>,
+    parts: impl Iterator<Item = &'a LineString>,
+) {
+    for part in parts {
+        push_coords(xy, part.0.iter());
+        ends.push((xy.len() / 2) as u32);
+    }
+}
+
+fn polygon_rings(polygon: &Polygon) -> impl Iterator<Item = &LineString> {
+    [polygon.exterior()].into_iter().chain(polygon.interiors())
+}
+
+fn build_geometry<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    geometry_type: GeometryType,
+    xy: &[f64],
+    ends: &[u32],
+    parts: Option<&[WIPOffset<FgbGeometry<'a>>]>,
+) -> WIPOffset<FgbGeometry<'a>> {
+    let xy = (!xy.is_empty()).then(|| builder.create_vector(xy));
+    // single part geometries leave out ends
+    let ends = (ends.len() > 1).then(|| builder.create_vector(ends));
+    let parts = parts.map(|parts| builder.create_vector(parts));
+    FgbGeometry::create(
+        builder,
+        &GeometryArgs {
+            xy,
+            ends,
+            parts,
+            type_: geometry_type,
+            ..Default::default()
+        },
+    )
+}
+
+fn write_geometry<'a>(
+    builder: &mut FlatBufferBuilder<'a>,
+    geometry: &Geometry,
+) -> Option<WIPOffset<FgbGeometry<'a>>> {
+    let mut xy = vec![];
+    let mut ends = vec![];
+    let geometry_type = match geometry {
+        Geometry::Point(point) => {
+            push_coords(&mut xy, [point.0].iter());
+            GeometryType::Point
+        }
+        Geometry::MultiPoint(multi_point) => {
+            push_coords(&mut xy, multi_point.iter().map(|point| &point.0));
+            GeometryType::MultiPoint
+        }
+        Geometry::LineString(line_string) => {
+            push_coords(&mut xy, line_string.0.iter());
+            GeometryType::LineString
+        }
+        Geometry::MultiLineString(multi_line_string) => {
+            push_parts(&mut xy, &mut ends, multi_line_string.iter());
+            GeometryType::MultiLineString
+        }
+        Geometry::Polygon(polygon) => {
+            push_parts(&mut xy, &mut ends, polygon_rings(polygon));
+            GeometryType::Polygon
+        }
+        Geometry::MultiPolygon(multi_polygon) => {
+            let parts: Vec<_> = multi_polygon
+                .iter()
+                .map(|polygon| {
+                    let mut xy = vec![];
+                    let mut ends = vec![];
+                    push_parts(&mut xy, &mut ends, polygon_rings(polygon));
+                    build_geometry(builder, GeometryType::Polygon, &xy, &ends, None)
+                })
+                .collect();
+            return Some(build_geometry(
+                builder,
+                GeometryType::MultiPolygon,
+                &[],
+                &[],
+                Some(&parts),
+            ));
+        }
+        _ => return None,
+    };
+
+    Some(build_geometry(builder, geometry_type, &xy, &ends, None))
+}
+
+/// Encodes a value in the column's type, `None` for values the column can't hold, which are left
+/// null rather than written as zero
+fn property_bytes(column_type: ExportColumnType, value: &PropertyValue) -> Option<Vec<u8>> {
+    let bytes = match column_type {
+        ExportColumnType::Bool => match value {
+            PropertyValue::Bool(value) => vec![*value as u8],
+            _ => return None,
+        },
+        ExportColumnType::Long => value.as_i64()?.to_le_bytes().to_vec(),
+        ExportColumnType::Float => (value.as_f64()? as f32).to_le_bytes().to_vec(),
+        ExportColumnType::Double => value.as_f64()?.to_le_bytes().to_vec(),
+        ExportColumnType::String => {
+            let string = value.to_string();
+            let mut bytes = (string.len() as u32).to_le_bytes().to_vec();
+            bytes.extend_from_slice(string.as_bytes());
+            bytes
+        }
+    };
+    Some(bytes)
+}
+
+impl FlatGeobufWriter {
+    pub fn new(name: &str, columns: Vec<ExportColumn>) -> Self {
+        Self {
+            name: name.to_string(),
+            columns,
+        }
+    }
+
+    /// Magic bytes followed by the size prefixed header
+    pub fn header(&self) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let columns: Vec<_> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let name = builder.create_string(&column.name);
+                let type_ = match column.column_type {
+                    ExportColumnType::Bool => ColumnType::Bool,
+                    ExportColumnType::Long => ColumnType::Long,
+                    ExportColumnType::Float => ColumnType::Float,
+                    ExportColumnType::Double => ColumnType::Double,
+                    ExportColumnType::String => ColumnType::String,
+                };
+                Column::create(
+                    &mut builder,
+                    &ColumnArgs {
+                        name: Some(name),
+                        type_,
+                        nullable: true,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+        let columns = builder.create_vector(&columns);
+        let name = builder.create_string(&self.name);
+        let crs = Crs::create(
+            &mut builder,
+            &CrsArgs {
+                code: 4326,
+                ..Default::default()
+            },
+        );
+        let header = Header::create(
+            &mut builder,
+            &HeaderArgs {
+                name: Some(name),
+                geometry_type: GeometryType::Unknown,
+                columns: Some(columns),
+                crs: Some(crs),
+                index_node_size: 0,
+                ..Default::default()
+            },
+        );
+        builder.finish_size_prefixed(header, None);
+
+        let mut data = MAGIC_BYTES.to_vec();
+        data.extend_from_slice(builder.finished_data());
+        data
+    }
+
+    fn properties(&self, properties: &Properties) -> Vec<u8> {
+        let mut data = vec![];
+        for (index, column) in self.columns.iter().enumerate() {
+            let Some(value) = properties.get(&column.name) else {
+                continue;
+            };
+            if let Some(bytes) = property_bytes(column.column_type, value) {
+                data.extend_from_slice(&(index as u16).to_le_bytes());
+                data.extend_from_slice(&bytes);
+            }
+        }
+        data
+    }
+
+    /// A size prefixed feature, empty for geometries FlatGeobuf cannot hold
+    pub fn write_feature(&self, feature: &Feature) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let Some(geometry) = write_geometry(&mut builder, &feature.geometry) else {
+            return vec![];
+        };
+        let properties = self.properties(&feature.properties);
+        let properties = builder.create_vector(&properties);
+        let fgb_feature = FgbFeature::create(
+            &mut builder,
+            &FeatureArgs {
+                geometry: Some(geometry),
+                properties: Some(properties),
+                ..Default::default()
+            },
+        );
+        builder.finish_size_prefixed(fgb_feature, None);
+        builder.finished_data().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flatgeobuf::geozero::FeatureProperties;
+    use flatgeobuf::{FallibleStreamingIterator, FgbReader};
+    use geo_types::{point, polygon, MultiPolygon};
+
+    fn column(name: &str, column_type: ExportColumnType) -> ExportColumn {
+        ExportColumn {
+            name: name.to_string(),
+            column_type,
+        }
+    }
+
+    fn feature(geometry: Geometry, properties: Vec<(&str, PropertyValue)>) -> Feature {
+        Feature {
+            geometry,
+            properties: properties
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+
+    fn writer() -> FlatGeobufWriter {
+        FlatGeobufWriter::new(
+            "roads",
+            vec![
+                column("open", ExportColumnType::Bool),
+                column("lanes", ExportColumnType::Long),
+                column("width", ExportColumnType::Double),
+                column("name", ExportColumnType::String),
+            ],
+        )
+    }
+
+    #[test]
+    fn header_describes_the_columns() {
+        let data = writer().header();
+        let reader = FgbReader::open(data.as_slice()).unwrap();
+        let header = reader.header();
+
+        assert_eq!(header.name(), Some("roads"));
+        assert_eq!(header.index_node_size(), 0);
+        assert_eq!(header.crs().unwrap().code(), 4326);
+        let columns: Vec<(&str, ColumnType)> = header
+            .columns()
+            .unwrap()
+            .iter()
+            .map(|column| (column.name(), column.type_()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("open", ColumnType::Bool),
+                ("lanes", ColumnType::Long),
+                ("width", ColumnType::Double),
+                ("name", ColumnType::String),
+            ]
+        );
+    }
+
+    #[test]
+    fn features_round_trip() {
+        let writer = writer();
+        let square =
+            polygon![(x: 0.0, y: 0.0), (x: 1.0, y: 0.0), (x: 1.0, y: 1.0), (x: 0.0, y: 0.0)];
+        let mut data = writer.header();
+        data.extend(writer.write_feature(&feature(
+            Geometry::Point(point!(x: 1.5, y: 2.5)),
+            vec![
+                ("open", PropertyValue::Bool(true)),
+                ("lanes", PropertyValue::Int(2)),
+                ("width", PropertyValue::Float(3.5)),
+                ("name", PropertyValue::String("main".to_string())),
+            ],
+        )));
+        data.extend(writer.write_feature(&feature(
+            Geometry::MultiPolygon(MultiPolygon(vec![square.clone(), square])),
+            vec![],
+        )));
+
+        let mut features = FgbReader::open(data.as_slice())
+            .unwrap()
+            .select_all_seq()
+            .unwrap();
+        let point = features.next().unwrap().unwrap();
+        assert!(point.property::<bool>("open").unwrap());
+        assert_eq!(point.property::<i64>("lanes").unwrap(), 2);
+        assert_eq!(point.property::<f64>("width").unwrap(), 3.5);
+        assert_eq!(point.property::<String>("name").unwrap(), "main");
+        let geometry = point.geometry().unwrap();
+        assert_eq!(geometry.type_(), GeometryType::Point);
+        assert_eq!(
+            geometry.xy().unwrap().iter().collect::<Vec<_>>(),
+            vec![1.5, 2.5]
+        );
+
+        let multi_polygon = features.next().unwrap().unwrap();
+        let geometry = multi_polygon.geometry().unwrap();
+        assert_eq!(geometry.type_(), GeometryType::MultiPolygon);
+        assert_eq!(geometry.parts().unwrap().len(), 2);
+        assert!(multi_polygon.property::<i64>("lanes").is_err());
+        assert!(features.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn values_a_column_cannot_hold_are_left_null() {
+        let writer = writer();
+        let mut data = writer.header();
+        data.extend(writer.write_feature(&feature(
+            Geometry::Point(point!(x: 0.0, y: 0.0)),
+            vec![
+                ("open", PropertyValue::Int(1)),
+                ("lanes", PropertyValue::UInt(u64::MAX)),
+                ("width", PropertyValue::Bool(true)),
+                ("name", PropertyValue::Int(7)),
+            ],
+        )));
+
+        let mut features = FgbReader::open(data.as_slice())
+            .unwrap()
+            .select_all_seq()
+            .unwrap();
+        let feature = features.next().unwrap().unwrap();
+        assert!(feature.property::<bool>("open").is_err());
+        assert!(feature.property::<i64>("lanes").is_err());
+        assert!(feature.property::<f64>("width").is_err());
+        assert_eq!(feature.property::<String>("name").unwrap(), "7");
+    }
+}
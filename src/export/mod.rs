@@ -0,0 +1,6 @@
+pub mod export_column;
+pub mod export_format;
+pub mod export_query_constructor;
+pub mod export_service;
+mod export_writer;
+mod flatgeobuf_writer;
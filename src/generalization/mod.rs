@@ -0,0 +1,2 @@
+pub mod property_quantizer;
+pub mod small_feature_filter;
@@ -0,0 +1,127 @@
+use crate::mvt::property_value::{Properties, PropertyType, PropertyValue};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FloatEncoding {
+    #[default]
+    Double,
+    Float,
+}
+
+/// Rounds floating point properties so equal values share a slot in the layer's value table
+pub struct PropertyQuantizer<'a> {
+    precision: Option<&'a HashMap<String, u32>>,
+    float_encoding: FloatEncoding,
+    integer_floats: bool,
+}
+
+impl<'a> PropertyQuantizer<'a> {
+    /// Returns `None` when the layer leaves floating point properties untouched
+    pub fn new(
+        precision: Option<&'a HashMap<String, u32>>,
+        float_encoding: Option<FloatEncoding>,
+        integer_floats: bool,
+    ) -> Option<Self> {
+        if precision.is_none() && float_encoding.is_none() && !integer_floats {
+            return None;
+        }
+
+        Some(Self {
+            precision,
+            float_encoding: float_encoding.unwrap_or_default(),
+            integer_floats,
+        })
+    }
+
+    /// Type of a column after its values are quantized. Integer floats still land in floating
+    /// point columns, which hold them.
+    pub fn column_type(&self, property_type: PropertyType) -> PropertyType {
+        match (property_type, self.float_encoding) {
+            (PropertyType::Float | PropertyType::Double, FloatEncoding::Float) => {
+                PropertyType::Float
+            }
+            (PropertyType::Float | PropertyType::Double, FloatEncoding::Double) => {
+                PropertyType::Double
+            }
+            (property_type, _) => property_type,
+        }
+    }
+
+    pub fn quantize(&self, properties: &mut Properties) {
+        for (name, property) in properties.iter_mut() {
+            let value = match property {
+                PropertyValue::Float(value) => *value as f64,
+                PropertyValue::Double(value) => *value,
+                _ => continue,
+            };
+
+            let value = match self.precision.and_then(|precision| precision.get(name)) {
+                Some(decimals) => {
+                    let scale = 10f64.powi(*decimals as i32);
+                    (value * scale).round() / scale
+                }
+                None => value,
+            };
+
+            // i64::MAX rounds up to 2^63 as a float, which is past the range of integers
+            *property = if self.integer_floats
+                && value.fract() == 0.0
+                && value >= i64::MIN as f64
+                && value < i64::MAX as f64
+            {
+                PropertyValue::SInt(value as i64)
+            } else {
+                match self.float_encoding {
+                    FloatEncoding::Float => PropertyValue::Float(value as f32),
+                    FloatEncoding::Double => PropertyValue::Double(value),
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantize(quantizer: &PropertyQuantizer, value: PropertyValue) -> PropertyValue {
+        let mut properties = Properties::from([("value".to_string(), value)]);
+        quantizer.quantize(&mut properties);
+        properties.remove("value").unwrap()
+    }
+
+    #[test]
+    fn rounds_to_the_precision() {
+        let precision = HashMap::from([("value".to_string(), 2)]);
+        let quantizer = PropertyQuantizer::new(Some(&precision), None, false).unwrap();
+
+        let value = quantize(&quantizer, PropertyValue::Double(1.23456));
+        assert!(matches!(value, PropertyValue::Double(value) if value == 1.23));
+        let value = quantize(&quantizer, PropertyValue::String("1.23456".to_string()));
+        assert!(matches!(value, PropertyValue::String(_)));
+    }
+
+    #[test]
+    fn whole_floats_become_integers_within_range() {
+        let quantizer = PropertyQuantizer::new(None, Some(FloatEncoding::Float), true).unwrap();
+
+        assert!(matches!(
+            quantize(&quantizer, PropertyValue::Double(-42.0)),
+            PropertyValue::SInt(-42)
+        ));
+        assert!(matches!(
+            quantize(&quantizer, PropertyValue::Double(i64::MIN as f64)),
+            PropertyValue::SInt(i64::MIN)
+        ));
+        assert!(matches!(
+            quantize(&quantizer, PropertyValue::Double(i64::MAX as f64)),
+            PropertyValue::Float(_)
+        ));
+        assert!(matches!(
+            quantize(&quantizer, PropertyValue::Double(0.5)),
+            PropertyValue::Float(0.5)
+        ));
+    }
+}
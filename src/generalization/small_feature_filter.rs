@@ -0,0 +1,310 @@
+use crate::mvt::constants::{DEFAULT_EXTENT, DEFAULT_TILE_SIZE};
+use crate::mvt::mapbox_vector_tile::Feature;
+use geo_types::{Coord, Geometry, LineString, MultiLineString, MultiPolygon, Point, Polygon};
+
+fn ring_area(ring: &LineString) -> f64 {
+    ring.lines()
+        .map(|line| line.start.x * line.end.y - line.end.x * line.start.y)
+        .sum::<f64>()
+        / 2.0
+}
+
+fn polygon_area(polygon: &Polygon) -> f64 {
+    let holes: f64 = polygon
+        .interiors()
+        .iter()
+        .map(|ring| ring_area(ring).abs())
+        .sum();
+    ring_area(polygon.exterior()).abs() - holes
+}
+
+fn line_length(line_string: &LineString) -> f64 {
+    line_string
+        .lines()
+        .map(|line| (line.dx() * line.dx() + line.dy() * line.dy()).sqrt())
+        .sum()
+}
+
+fn ring_centroid(ring: &LineString) -> Option<Coord> {
+    let area = ring_area(ring);
+    if area == 0.0 {
+        let size = ring.0.len() as f64;
+        if size == 0.0 {
+            return None;
+        }
+        let (x, y) = ring
+            .0
+            .iter()
+            .fold((0.0, 0.0), |(x, y), coord| (x + coord.x, y + coord.y));
+        return Some(Coord {
+            x: x / size,
+            y: y / size,
+        });
+    }
+
+    let (x, y) = ring.lines().fold((0.0, 0.0), |(x, y), line| {
+        let cross = line.start.x * line.end.y - line.end.x * line.start.y;
+        (
+            x + (line.start.x + line.end.x) * cross,
+            y + (line.start.y + line.end.y) * cross,
+        )
+    });
+    Some(Coord {
+        x: x / (6.0 * area),
+        y: y / (6.0 * area),
+    })
+}
+
+fn line_midpoint(line_string: &LineString) -> Option<Coord> {
+    let mut remaining = line_length(line_string) / 2.0;
+    for line in line_string.lines() {
+        let length = (line.dx() * line.dx() + line.dy() * line.dy()).sqrt();
+        if length >= remaining && length > 0.0 {
+            let ratio = remaining / length;
+            return Some(Coord {
+                x: line.start.x + line.dx() * ratio,
+                y: line.start.y + line.dy() * ratio,
+            });
+        }
+        remaining -= length;
+    }
+    line_string.0.first().copied()
+}
+
+/// Drops polygons and lines too small to be visible in tile pixel space
+pub struct SmallFeatureFilter {
+    min_area: f64,
+    min_length: f64,
+    replace_with_point: bool,
+}
+
+impl SmallFeatureFilter {
+    /// Thresholds are given in pixels of a tile and returns `None` when neither is set
+    pub fn new(
+        min_polygon_area: Option<f64>,
+        min_line_length: Option<f64>,
+        replace_with_point: bool,
+    ) -> Option<Self> {
+        if min_polygon_area.is_none() && min_line_length.is_none() {
+            return None;
+        }
+
+        let pixel_size = DEFAULT_EXTENT as f64 / DEFAULT_TILE_SIZE as f64;
+        Some(Self {
+            min_area: min_polygon_area.unwrap_or(0.0) * pixel_size * pixel_size,
+            min_length: min_line_length.unwrap_or(0.0) * pixel_size,
+            replace_with_point,
+        })
+    }
+
+    pub fn filter_features(&self, features: Vec<Feature>) -> Vec<Feature> {
+        features
+            .into_iter()
+            .filter_map(|feature| self.filter_feature(feature))
+            .collect()
+    }
+
+    pub fn filter_feature(&self, mut feature: Feature) -> Option<Feature> {
+        feature.geometry = self.filter_geometry(&feature.geometry)?;
+        Some(feature)
+    }
+
+    fn is_small_polygon(&self, polygon: &Polygon) -> bool {
+        polygon_area(polygon) < self.min_area
+    }
+
+    fn is_small_line(&self, line_string: &LineString) -> bool {
+        line_length(line_string) < self.min_length
+    }
+
+    fn replacement(&self, representative: Option<Coord>) -> Option<Geometry> {
+        if self.replace_with_point {
+            return representative.map(|coord| Geometry::Point(Point(coord)));
+        }
+        None
+    }
+
+    fn filter_geometry(&self, geometry: &Geometry) -> Option<Geometry> {
+        match geometry {
+            Geometry::Polygon(polygon) => {
+                if self.is_small_polygon(polygon) {
+                    return self.replacement(ring_centroid(polygon.exterior()));
+                }
+                Some(geometry.clone())
+            }
+            Geometry::MultiPolygon(multi_polygon) => {
+                let polygons: Vec<Polygon> = multi_polygon
+                    .iter()
+                    .filter(|polygon| !self.is_small_polygon(polygon))
+                    .cloned()
+                    .collect();
+                if polygons.is_empty() {
+                    let largest = multi_polygon
+                        .iter()
+                        .max_by(|a, b| polygon_area(a).total_cmp(&polygon_area(b)))?;
+                    return self.replacement(ring_centroid(largest.exterior()));
+                }
+                Some(Geometry::MultiPolygon(MultiPolygon(polygons)))
+            }
+            Geometry::LineString(line_string) => {
+                if self.is_small_line(line_string) {
+                    return self.replacement(line_midpoint(line_string));
+                }
+                Some(geometry.clone())
+            }
+            Geometry::MultiLineString(multi_line_string) => {
+                let line_strings: Vec<LineString> = multi_line_string
+                    .iter()
+                    .filter(|line_string| !self.is_small_line(line_string))
+                    .cloned()
+                    .collect();
+                if line_strings.is_empty() {
+                    let longest = multi_line_string
+                        .iter()
+                        .max_by(|a, b| line_length(a).total_cmp(&line_length(b)))?;
+                    return self.replacement(line_midpoint(longest));
+                }
+                Some(Geometry::MultiLineString(MultiLineString(line_strings)))
+            }
+            _ => Some(geometry.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::{coord, line_string, polygon};
+
+    /// Tile units per pixel
+    const PIXEL: f64 = (DEFAULT_EXTENT / DEFAULT_TILE_SIZE) as f64;
+
+    /// A square of `size` pixels with its lower left corner at `x` pixels
+    fn square(x: f64, size: f64) -> Polygon {
+        let (min, max) = (x * PIXEL, (x + size) * PIXEL);
+        polygon![
+            (x: min, y: min),
+            (x: max, y: min),
+            (x: max, y: max),
+            (x: min, y: max),
+            (x: min, y: min),
+        ]
+    }
+
+    /// A horizontal line of `length` pixels starting at `x` pixels
+    fn line(x: f64, length: f64) -> LineString {
+        line_string![(x: x * PIXEL, y: 0.0), (x: (x + length) * PIXEL, y: 0.0)]
+    }
+
+    fn filter(replace_with_point: bool) -> SmallFeatureFilter {
+        SmallFeatureFilter::new(Some(4.0), Some(3.0), replace_with_point).unwrap()
+    }
+
+    #[test]
+    fn needs_a_threshold() {
+        assert!(SmallFeatureFilter::new(None, None, true).is_none());
+    }
+
+    #[test]
+    fn drops_polygons_below_the_pixel_area() {
+        let filter = filter(false);
+        assert_eq!(
+            filter.filter_geometry(&Geometry::Polygon(square(0.0, 1.9))),
+            None
+        );
+        let kept = Geometry::Polygon(square(0.0, 2.1));
+        assert_eq!(filter.filter_geometry(&kept), Some(kept));
+    }
+
+    #[test]
+    fn holes_count_against_the_area() {
+        let hole = square(0.25, 2.5).exterior().clone();
+        let polygon = Polygon::new(square(0.0, 3.0).exterior().clone(), vec![hole]);
+        assert_eq!(
+            filter(false).filter_geometry(&Geometry::Polygon(polygon)),
+            None
+        );
+    }
+
+    #[test]
+    fn drops_lines_below_the_length() {
+        let filter = filter(false);
+        assert_eq!(
+            filter.filter_geometry(&Geometry::LineString(line(0.0, 2.9))),
+            None
+        );
+        let kept = Geometry::LineString(line(0.0, 3.1));
+        assert_eq!(filter.filter_geometry(&kept), Some(kept));
+    }
+
+    #[test]
+    fn leaves_points_and_unset_thresholds_alone() {
+        let point = Geometry::Point(Point::new(1.0, 1.0));
+        assert_eq!(filter(true).filter_geometry(&point), Some(point));
+
+        let lines_only = SmallFeatureFilter::new(None, Some(3.0), false).unwrap();
+        let tiny = Geometry::Polygon(square(0.0, 0.1));
+        assert_eq!(lines_only.filter_geometry(&tiny), Some(tiny));
+    }
+
+    #[test]
+    fn drops_only_the_small_parts_of_multi_geometries() {
+        let filter = filter(true);
+        let multi_polygon = MultiPolygon(vec![square(0.0, 1.0), square(10.0, 3.0)]);
+        assert_eq!(
+            filter.filter_geometry(&Geometry::MultiPolygon(multi_polygon)),
+            Some(Geometry::MultiPolygon(MultiPolygon(vec![square(
+                10.0, 3.0
+            )])))
+        );
+
+        let multi_line_string = MultiLineString(vec![line(0.0, 5.0), line(10.0, 1.0)]);
+        assert_eq!(
+            filter.filter_geometry(&Geometry::MultiLineString(multi_line_string)),
+            Some(Geometry::MultiLineString(MultiLineString(vec![line(
+                0.0, 5.0
+            )])))
+        );
+    }
+
+    #[test]
+    fn replaces_polygons_with_their_centroid() {
+        let filter = filter(true);
+        assert_eq!(
+            filter.filter_geometry(&Geometry::Polygon(square(0.0, 1.0))),
+            Some(Geometry::Point(Point::new(0.5 * PIXEL, 0.5 * PIXEL)))
+        );
+
+        // a multipolygon without any part left is replaced by the centroid of its largest part
+        let multi_polygon = MultiPolygon(vec![square(0.0, 0.5), square(10.0, 1.0)]);
+        assert_eq!(
+            filter.filter_geometry(&Geometry::MultiPolygon(multi_polygon)),
+            Some(Geometry::Point(Point::new(10.5 * PIXEL, 10.5 * PIXEL)))
+        );
+    }
+
+    #[test]
+    fn replaces_lines_with_their_midpoint() {
+        let filter = filter(true);
+        let bent = line_string![(x: 0.0, y: 0.0), (x: 8.0, y: 0.0), (x: 8.0, y: 8.0)];
+        assert_eq!(
+            filter.filter_geometry(&Geometry::LineString(bent)),
+            Some(Geometry::Point(Point(coord! { x: 8.0, y: 0.0 })))
+        );
+
+        let multi_line_string = MultiLineString(vec![line(0.0, 1.0), line(10.0, 2.0)]);
+        assert_eq!(
+            filter.filter_geometry(&Geometry::MultiLineString(multi_line_string)),
+            Some(Geometry::Point(Point::new(11.0 * PIXEL, 0.0)))
+        );
+    }
+
+    #[test]
+    fn degenerate_rings_fall_back_to_the_average_vertex() {
+        let flat = polygon![(x: 0.0, y: 0.0), (x: 6.0, y: 0.0), (x: 0.0, y: 0.0)];
+        assert_eq!(
+            filter(true).filter_geometry(&Geometry::Polygon(flat)),
+            Some(Geometry::Point(Point::new(2.0, 0.0)))
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use geo_types::{
+    Coord, Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Point, Polygon,
+};
+
+/// Inside test, whether the edge is vertical and the edge position
+type ClipEdge = (fn(&Coord, f64) -> bool, bool, f64);
+
+/// Axis aligned box geometries are clipped against, usually the tile extent
+pub struct ClipBox {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ClipBox {
+    fn contains(&self, coord: &Coord) -> bool {
+        coord.x >= self.min && coord.x <= self.max && coord.y >= self.min && coord.y <= self.max
+    }
+
+    /// Liang-Barsky clipping of a single segment
+    fn clip_segment(&self, start: Coord, end: Coord) -> Option<(Coord, Coord)> {
+        let dx = end.x - start.x;
+        let dy = end.y - start.y;
+        let mut t0: f64 = 0.0;
+        let mut t1: f64 = 1.0;
+
+        for (p, q) in [
+            (-dx, start.x - self.min),
+            (dx, self.max - start.x),
+            (-dy, start.y - self.min),
+            (dy, self.max - start.y),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+                continue;
+            }
+
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+
+        Some((
+            Coord {
+                x: start.x + t0 * dx,
+                y: start.y + t0 * dy,
+            },
+            Coord {
+                x: start.x + t1 * dx,
+                y: start.y + t1 * dy,
+            },
+        ))
+    }
+
+    fn clip_line_string(&self, line_string: &LineString) -> Vec<LineString> {
+        let mut clipped: Vec<LineString> = vec![];
+        let mut current: Vec<Coord> = vec![];
+
+        for line in line_string.lines() {
+            let Some((start, end)) = self.clip_segment(line.start, line.end) else {
+                continue;
+            };
+
+            if current.last() != Some(&start) {
+                if current.len() > 1 {
+                    clipped.push(LineString(std::mem::take(&mut current)));
+                }
+                current = vec![start];
+            }
+            current.push(end);
+        }
+
+        if current.len() > 1 {
+            clipped.push(LineString(current));
+        }
+        clipped
+    }
+
+    /// Sutherland-Hodgman clipping of a ring against each edge of the box in turn
+    fn clip_ring(&self, ring: &LineString) -> Option<LineString> {
+        let mut coords: Vec<Coord> = ring.0.clone();
+        if ring.is_closed() {
+            coords.pop();
+        }
+
+        let edges: [ClipEdge; 4] = [
+            (|coord, bound| coord.x >= bound, true, self.min),
+            (|coord, bound| coord.x <= bound, true, self.max),
+            (|coord, bound| coord.y >= bound, false, self.min),
+            (|coord, bound| coord.y <= bound, false, self.max),
+        ];
+
+        for (inside, vertical, bound) in edges {
+            if coords.is_empty() {
+                return None;
+            }
+
+            let intersect = |a: Coord, b: Coord| {
+                if vertical {
+                    let t = (bound - a.x) / (b.x - a.x);
+                    Coord {
+                        x: bound,
+                        y: a.y + t * (b.y - a.y),
+                    }
+                } else {
+                    let t = (bound - a.y) / (b.y - a.y);
+                    Coord {
+                        x: a.x + t * (b.x - a.x),
+                        y: bound,
+                    }
+                }
+            };
+
+            let mut output: Vec<Coord> = vec![];
+            let mut previous = *coords.last().unwrap();
+            for coord in coords.iter() {
+                let coord_inside = inside(coord, bound);
+                let previous_inside = inside(&previous, bound);
+                if coord_inside {
+                    if !previous_inside {
+                        output.push(intersect(previous, *coord));
+                    }
+                    output.push(*coord);
+                } else if previous_inside {
+                    output.push(intersect(previous, *coord));
+                }
+                previous = *coord;
+            }
+            coords = output;
+        }
+
+        if coords.len() < 3 {
+            return None;
+        }
+        coords.push(coords[0]);
+        Some(LineString(coords))
+    }
+
+    fn clip_polygon(&self, polygon: &Polygon) -> Option<Polygon> {
+        let exterior = self.clip_ring(polygon.exterior())?;
+        let interiors = polygon
+            .interiors()
+            .iter()
+            .filter_map(|interior| self.clip_ring(interior))
+            .collect();
+        Some(Polygon::new(exterior, interiors))
+    }
+
+    /// Clips a geometry to the box, `None` when nothing of it is left
+    pub fn clip_geometry(&self, geometry: &Geometry) -> Option<Geometry> {
+        match geometry {
+            Geometry::Point(point) => {
+                if self.contains(&point.0) {
+                    return Some(geometry.clone());
+                }
+                None
+            }
+            Geometry::MultiPoint(multi_point) => {
+                let points: Vec<Point> = multi_point
+                    .iter()
+                    .filter(|point| self.contains(&point.0))
+                    .copied()
+                    .collect();
+                if points.is_empty() {
+                    return None;
+                }
+                Some(Geometry::MultiPoint(MultiPoint(points)))
+            }
+            Geometry::LineString(line_string) => {
+                let mut line_strings = self.clip_line_string(line_string);
+                match line_strings.len() {
+                    0 => None,
+                    1 => line_strings.pop().map(Geometry::LineString),
+                    _ => Some(Geometry::MultiLineString(MultiLineString(line_strings))),
+                }
+            }
+            Geometry::MultiLineString(multi_line_string) => {
+                let line_strings: Vec<LineString> = multi_line_string
+                    .iter()
+                    .flat_map(|line_string| self.clip_line_string(line_string))
+                    .collect();
+                if line_strings.is_empty() {
+                    return None;
+                }
+                Some(Geometry::MultiLineString(MultiLineString(line_strings)))
+            }
+            Geometry::Polygon(polygon) => self.clip_polygon(polygon).map(Geometry::Polygon),
+            Geometry::MultiPolygon(multi_polygon) => {
+                let polygons: Vec<Polygon> = multi_polygon
+                    .iter()
+                    .filter_map(|polygon| self.clip_polygon(polygon))
+                    .collect();
+                if polygons.is_empty() {
+                    return None;
+                }
+                Some(Geometry::MultiPolygon(MultiPolygon(polygons)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::{coord, line_string, point, polygon};
+
+    const BOX: ClipBox = ClipBox {
+        min: 0.0,
+        max: 10.0,
+    };
+
+    #[test]
+    fn clips_segments_to_the_box() {
+        assert_eq!(
+            BOX.clip_segment(coord! { x: -5.0, y: 5.0 }, coord! { x: 15.0, y: 5.0 }),
+            Some((coord! { x: 0.0, y: 5.0 }, coord! { x: 10.0, y: 5.0 }))
+        );
+        assert_eq!(
+            BOX.clip_segment(coord! { x: 2.0, y: 2.0 }, coord! { x: 4.0, y: 4.0 }),
+            Some((coord! { x: 2.0, y: 2.0 }, coord! { x: 4.0, y: 4.0 }))
+        );
+        assert_eq!(
+            BOX.clip_segment(coord! { x: -5.0, y: 5.0 }, coord! { x: 5.0, y: 15.0 }),
+            Some((coord! { x: 0.0, y: 10.0 }, coord! { x: 0.0, y: 10.0 }))
+        );
+        assert_eq!(
+            BOX.clip_segment(coord! { x: -5.0, y: 5.0 }, coord! { x: -1.0, y: 15.0 }),
+            None
+        );
+        assert_eq!(
+            BOX.clip_segment(coord! { x: 11.0, y: 0.0 }, coord! { x: 11.0, y: 10.0 }),
+            None
+        );
+    }
+
+    #[test]
+    fn splits_lines_leaving_and_entering_the_box() {
+        let line = line_string![
+            (x: 2.0, y: 2.0),
+            (x: 2.0, y: 12.0),
+            (x: 8.0, y: 12.0),
+            (x: 8.0, y: 2.0),
+        ];
+        let clipped = BOX.clip_geometry(&Geometry::LineString(line));
+        assert_eq!(
+            clipped,
+            Some(Geometry::MultiLineString(MultiLineString(vec![
+                line_string![(x: 2.0, y: 2.0), (x: 2.0, y: 10.0)],
+                line_string![(x: 8.0, y: 10.0), (x: 8.0, y: 2.0)],
+            ])))
+        );
+    }
+
+    #[test]
+    fn keeps_points_inside_the_box() {
+        assert!(BOX
+            .clip_geometry(&Geometry::Point(point!(x: 10.0, y: 0.0)))
+            .is_some());
+        assert!(BOX
+            .clip_geometry(&Geometry::Point(point!(x: 10.1, y: 0.0)))
+            .is_none());
+        assert_eq!(
+            BOX.clip_geometry(&Geometry::MultiPoint(MultiPoint(vec![
+                point!(x: -1.0, y: 5.0),
+                point!(x: 5.0, y: 5.0),
+            ]))),
+            Some(Geometry::MultiPoint(MultiPoint(vec![
+                point!(x: 5.0, y: 5.0)
+            ])))
+        );
+    }
+
+    #[test]
+    fn clips_polygons_to_the_box() {
+        let polygon = polygon![
+            (x: -5.0, y: -5.0),
+            (x: 5.0, y: -5.0),
+            (x: 5.0, y: 5.0),
+            (x: -5.0, y: 5.0),
+            (x: -5.0, y: -5.0),
+        ];
+        let Some(Geometry::Polygon(clipped)) = BOX.clip_geometry(&Geometry::Polygon(polygon))
+        else {
+            panic!("the polygon overlaps the box");
+        };
+
+        let exterior = clipped.exterior();
+        assert!(exterior.is_closed());
+        assert!(exterior.coords().all(|coord| BOX.contains(coord)));
+        let mut corners: Vec<(f64, f64)> = exterior.coords().skip(1).map(|c| c.x_y()).collect();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(corners, [(0.0, 0.0), (0.0, 5.0), (5.0, 0.0), (5.0, 5.0)]);
+    }
+
+    #[test]
+    fn drops_holes_and_polygons_outside_the_box() {
+        let polygon = Polygon::new(
+            line_string![(x: 1.0, y: 1.0), (x: 20.0, y: 1.0), (x: 20.0, y: 9.0), (x: 1.0, y: 1.0)],
+            vec![line_string![
+                (x: 15.0, y: 2.0),
+                (x: 18.0, y: 2.0),
+                (x: 18.0, y: 4.0),
+                (x: 15.0, y: 2.0),
+            ]],
+        );
+        let Some(Geometry::Polygon(clipped)) = BOX.clip_geometry(&Geometry::Polygon(polygon))
+        else {
+            panic!("the polygon overlaps the box");
+        };
+        assert!(clipped.interiors().is_empty());
+
+        let outside = polygon![(x: 11.0, y: 11.0), (x: 12.0, y: 11.0), (x: 12.0, y: 12.0)];
+        assert!(BOX
+            .clip_geometry(&Geometry::MultiPolygon(MultiPolygon(vec![outside])))
+            .is_none());
+    }
+}
@@ -1,6 +1,13 @@
-use geo_types::{Coord, Point};
+use crate::mvt::constants::DEFAULT_TILE_SIZE;
+use geo_types::{
+    Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon,
+    Point, Polygon,
+};
 use h3o::Resolution;
 
+/// Latitude limit of Web Mercator
+pub const MAX_LATITUDE: f64 = 85.051_128_78;
+
 pub fn translate_zoom_to_h3_resolution(z: u32) -> u32 {
     if z >= 15 {
         return Resolution::Fifteen as u32;
@@ -33,6 +40,21 @@ pub fn mercator_to_tile(longitude: f64, latitude: f64, zoom_level: u32) -> Point
     Point::new(x, y)
 }
 
+/// Inclusive `(min_x, min_y, max_x, max_y)` XYZ tile range covering a longitude/latitude
+/// `[min_x, min_y, max_x, max_y]` bounding box at a zoom
+pub fn get_tile_range(bbox: &[f64; 4], z: u32) -> (u32, u32, u32, u32) {
+    let max_tile = (1u32 << z) - 1;
+    let to_tile = |value: f64| (value.floor().max(0.0) as u32).min(max_tile);
+    let top_left = mercator_to_tile(bbox[0], bbox[3].min(MAX_LATITUDE), z);
+    let bottom_right = mercator_to_tile(bbox[2], bbox[1].max(-MAX_LATITUDE), z);
+    (
+        to_tile(top_left.x()),
+        to_tile(top_left.y()),
+        to_tile(bottom_right.x()),
+        to_tile(bottom_right.y()),
+    )
+}
+
 pub fn to_point(x: f64, y: f64, z: u32) -> (f64, f64) {
     let max_tiles = get_max_tiles_from_zoom(z);
 
@@ -44,14 +66,15 @@ pub fn to_point(x: f64, y: f64, z: u32) -> (f64, f64) {
     (longitude, latitude)
 }
 
-pub fn get_bounding_box_from_tile(x: u32, y: u32, z: u32) -> BBox {
+/// Longitude/latitude bounds of a tile grown by `buffer` tiles on every side
+pub fn get_bounding_box_from_tile(x: u32, y: u32, z: u32, buffer: f64) -> BBox {
     let x = x as f64;
     let y = y as f64;
 
-    let min_x = x;
-    let min_y = y.max(0.0);
-    let max_x = x + 1.0;
-    let max_y = (y + 1.0).min(get_max_tiles_from_zoom(z));
+    let min_x = x - buffer;
+    let min_y = (y - buffer).max(0.0);
+    let max_x = x + 1.0 + buffer;
+    let max_y = (y + 1.0 + buffer).min(get_max_tiles_from_zoom(z));
 
     let (min_longitude, max_latitude) = to_point(min_x, min_y, z);
     let (max_longitude, min_latitude) = to_point(max_x, max_y, z);
@@ -67,3 +90,60 @@ pub fn get_bounding_box_from_tile(x: u32, y: u32, z: u32) -> BBox {
         }),
     }
 }
+
+fn map_line_string(line_string: &LineString, f: &impl Fn(Coord) -> Coord) -> LineString {
+    LineString(line_string.0.iter().map(|coord| f(*coord)).collect())
+}
+
+fn map_polygon(polygon: &Polygon, f: &impl Fn(Coord) -> Coord) -> Polygon {
+    Polygon::new(
+        map_line_string(polygon.exterior(), f),
+        polygon
+            .interiors()
+            .iter()
+            .map(|interior| map_line_string(interior, f))
+            .collect(),
+    )
+}
+
+pub fn map_coords(geometry: &Geometry, f: &impl Fn(Coord) -> Coord) -> Geometry {
+    match geometry {
+        Geometry::Point(point) => Geometry::Point(Point(f(point.0))),
+        Geometry::Line(line) => {
+            Geometry::LineString(map_line_string(&LineString(vec![line.start, line.end]), f))
+        }
+        Geometry::LineString(line_string) => Geometry::LineString(map_line_string(line_string, f)),
+        Geometry::Polygon(polygon) => Geometry::Polygon(map_polygon(polygon, f)),
+        Geometry::MultiPoint(multi_point) => Geometry::MultiPoint(MultiPoint(
+            multi_point.iter().map(|point| Point(f(point.0))).collect(),
+        )),
+        Geometry::MultiLineString(multi_line_string) => Geometry::MultiLineString(MultiLineString(
+            multi_line_string
+                .iter()
+                .map(|line_string| map_line_string(line_string, f))
+                .collect(),
+        )),
+        Geometry::MultiPolygon(multi_polygon) => Geometry::MultiPolygon(MultiPolygon(
+            multi_polygon
+                .iter()
+                .map(|polygon| map_polygon(polygon, f))
+                .collect(),
+        )),
+        Geometry::GeometryCollection(collection) => {
+            Geometry::GeometryCollection(GeometryCollection(
+                collection
+                    .iter()
+                    .map(|geometry| map_coords(geometry, f))
+                    .collect(),
+            ))
+        }
+        Geometry::Rect(rect) => Geometry::Polygon(map_polygon(&rect.to_polygon(), f)),
+        Geometry::Triangle(triangle) => Geometry::Polygon(map_polygon(&triangle.to_polygon(), f)),
+    }
+}
+
+/// Number of decimals that resolve a pixel of a tile at the zoom when written in degrees
+pub fn get_coordinate_precision(zoom: u32) -> u32 {
+    let pixels_per_degree = get_max_tiles_from_zoom(zoom) * DEFAULT_TILE_SIZE as f64 / 360.0;
+    (pixels_per_degree.log10().ceil().max(0.0) as u32 + 1).min(9)
+}
@@ -0,0 +1,437 @@
+use crate::geo::clip::ClipBox;
+use crate::geo::geo_utils::{get_coordinate_precision, map_coords, to_point};
+use crate::mvt::constants::DEFAULT_EXTENT;
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature};
+use crate::mvt::property_value::{Properties, PropertyColumns, PropertyType, PropertyValue};
+use arrow_array::builder::{
+    BinaryBuilder, BooleanBuilder, FixedSizeListBuilder, Float32Builder, Float64Builder,
+    Int64Builder, ListBuilder, StringBuilder, UInt64Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use geo_types::{Coord, Geometry, LineString, Polygon};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+const GEOMETRY_COLUMN: &str = "geometry";
+const CRS_METADATA: &str = r#"{"crs":"OGC:CRS84","crs_type":"authority_code"}"#;
+
+type CoordBuilder = FixedSizeListBuilder<Float64Builder>;
+
+/// GeoArrow encoding of the geometry column, native when every feature fits one geometry family
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GeometryEncoding {
+    Point,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+    Wkb,
+}
+
+impl GeometryEncoding {
+    fn of(geometry: &Geometry) -> Option<Self> {
+        match geometry {
+            Geometry::Point(_) => Some(GeometryEncoding::Point),
+            Geometry::LineString(_) => Some(GeometryEncoding::LineString),
+            Geometry::Polygon(_) => Some(GeometryEncoding::Polygon),
+            Geometry::MultiPoint(_) => Some(GeometryEncoding::MultiPoint),
+            Geometry::MultiLineString(_) => Some(GeometryEncoding::MultiLineString),
+            Geometry::MultiPolygon(_) => Some(GeometryEncoding::MultiPolygon),
+            _ => None,
+        }
+    }
+
+    /// Single and multi geometries of a family share the multi encoding, mixed families fall back to WKB
+    fn merge(self, other: GeometryEncoding) -> Self {
+        use GeometryEncoding::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Point | MultiPoint, Point | MultiPoint) => MultiPoint,
+            (LineString | MultiLineString, LineString | MultiLineString) => MultiLineString,
+            (Polygon | MultiPolygon, Polygon | MultiPolygon) => MultiPolygon,
+            _ => Wkb,
+        }
+    }
+
+    fn extension_name(&self) -> &'static str {
+        match self {
+            GeometryEncoding::Point => "geoarrow.point",
+            GeometryEncoding::LineString => "geoarrow.linestring",
+            GeometryEncoding::Polygon => "geoarrow.polygon",
+            GeometryEncoding::MultiPoint => "geoarrow.multipoint",
+            GeometryEncoding::MultiLineString => "geoarrow.multilinestring",
+            GeometryEncoding::MultiPolygon => "geoarrow.multipolygon",
+            GeometryEncoding::Wkb => "geoarrow.wkb",
+        }
+    }
+}
+
+fn coord_builder() -> CoordBuilder {
+    FixedSizeListBuilder::new(Float64Builder::new(), 2).with_field(Field::new(
+        "xy",
+        DataType::Float64,
+        false,
+    ))
+}
+
+fn append_coord(builder: &mut CoordBuilder, coord: &Coord) {
+    builder.values().append_value(coord.x);
+    builder.values().append_value(coord.y);
+    builder.append(true);
+}
+
+fn append_coords<'a>(
+    builder: &mut ListBuilder<CoordBuilder>,
+    coords: impl Iterator<Item = &'a Coord>,
+) {
+    for coord in coords {
+        append_coord(builder.values(), coord);
+    }
+    builder.append(true);
+}
+
+fn append_lines<'a>(
+    builder: &mut ListBuilder<ListBuilder<CoordBuilder>>,
+    lines: impl Iterator<Item = &'a LineString>,
+) {
+    for line in lines {
+        append_coords(builder.values(), line.0.iter());
+    }
+    builder.append(true);
+}
+
+fn polygon_rings(polygon: &Polygon) -> impl Iterator<Item = &LineString> {
+    [polygon.exterior()].into_iter().chain(polygon.interiors())
+}
+
+fn append_polygons<'a>(
+    builder: &mut ListBuilder<ListBuilder<ListBuilder<CoordBuilder>>>,
+    polygons: impl Iterator<Item = &'a Polygon>,
+) {
+    for polygon in polygons {
+        append_lines(builder.values(), polygon_rings(polygon));
+    }
+    builder.append(true);
+}
+
+fn geometry_array(encoding: GeometryEncoding, geometries: &[Geometry]) -> ArrayRef {
+    match encoding {
+        GeometryEncoding::Point => {
+            let mut builder = coord_builder();
+            for geometry in geometries {
+                if let Geometry::Point(point) = geometry {
+                    append_coord(&mut builder, &point.0);
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        GeometryEncoding::LineString | GeometryEncoding::MultiPoint => {
+            let mut builder = ListBuilder::new(coord_builder());
+            for geometry in geometries {
+                match geometry {
+                    Geometry::Point(point) => append_coords(&mut builder, [point.0].iter()),
+                    Geometry::MultiPoint(multi_point) => {
+                        append_coords(&mut builder, multi_point.iter().map(|point| &point.0))
+                    }
+                    Geometry::LineString(line_string) => {
+                        append_coords(&mut builder, line_string.0.iter())
+                    }
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        GeometryEncoding::Polygon | GeometryEncoding::MultiLineString => {
+            let mut builder = ListBuilder::new(ListBuilder::new(coord_builder()));
+            for geometry in geometries {
+                match geometry {
+                    Geometry::LineString(line_string) => {
+                        append_lines(&mut builder, [line_string].into_iter())
+                    }
+                    Geometry::MultiLineString(multi_line_string) => {
+                        append_lines(&mut builder, multi_line_string.iter())
+                    }
+                    Geometry::Polygon(polygon) => {
+                        append_lines(&mut builder, polygon_rings(polygon))
+                    }
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        GeometryEncoding::MultiPolygon => {
+            let mut builder = ListBuilder::new(ListBuilder::new(ListBuilder::new(coord_builder())));
+            for geometry in geometries {
+                match geometry {
+                    Geometry::Polygon(polygon) => {
+                        append_polygons(&mut builder, [polygon].into_iter())
+                    }
+                    Geometry::MultiPolygon(multi_polygon) => {
+                        append_polygons(&mut builder, multi_polygon.iter())
+                    }
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        GeometryEncoding::Wkb => {
+            let mut builder = BinaryBuilder::new();
+            for geometry in geometries {
+                match wkb::geom_to_wkb(geometry) {
+                    Ok(data) => builder.append_value(data),
+                    Err(_) => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+fn property_array(property_type: PropertyType, name: &str, properties: &[Properties]) -> ArrayRef {
+    let values = properties.iter().map(|properties| properties.get(name));
+    match property_type {
+        PropertyType::Bool => {
+            let mut builder = BooleanBuilder::new();
+            for value in values {
+                builder.append_option(match value {
+                    Some(PropertyValue::Bool(value)) => Some(*value),
+                    _ => None,
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        PropertyType::Int => {
+            let mut builder = Int64Builder::new();
+            for value in values {
+                builder.append_option(value.and_then(PropertyValue::as_i64));
+            }
+            Arc::new(builder.finish())
+        }
+        PropertyType::UInt => {
+            let mut builder = UInt64Builder::new();
+            for value in values {
+                builder.append_option(value.and_then(PropertyValue::as_u64));
+            }
+            Arc::new(builder.finish())
+        }
+        PropertyType::Float => {
+            let mut builder = Float32Builder::new();
+            for value in values {
+                builder.append_option(
+                    value
+                        .and_then(PropertyValue::as_f64)
+                        .map(|value| value as f32),
+                );
+            }
+            Arc::new(builder.finish())
+        }
+        PropertyType::Double => {
+            let mut builder = Float64Builder::new();
+            for value in values {
+                builder.append_option(value.and_then(PropertyValue::as_f64));
+            }
+            Arc::new(builder.finish())
+        }
+        PropertyType::String => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                builder.append_option(value.map(PropertyValue::to_string));
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+/// Writes tile features as an Arrow IPC stream with a GeoArrow geometry column in longitude/latitude
+/// and a typed column per property
+pub struct GeoArrowLayer {
+    x: f64,
+    y: f64,
+    z: u32,
+    scale: f64,
+    clip_box: ClipBox,
+    encoding: Option<GeometryEncoding>,
+    geometries: Vec<Geometry>,
+    properties: Vec<Properties>,
+    columns: PropertyColumns,
+}
+
+impl GeoArrowLayer {
+    pub fn new(coordinates: &Coordinates) -> Self {
+        Self {
+            x: coordinates.x as f64,
+            y: coordinates.y as f64,
+            z: coordinates.z,
+            scale: 10f64.powi(get_coordinate_precision(coordinates.z) as i32),
+            clip_box: ClipBox {
+                min: 0.0,
+                max: DEFAULT_EXTENT as f64,
+            },
+            encoding: None,
+            geometries: vec![],
+            properties: vec![],
+            columns: PropertyColumns::default(),
+        }
+    }
+
+    /// Types property columns after the query's columns rather than the values of the tile
+    pub fn declare_columns(&mut self, columns: &BTreeMap<String, PropertyType>) {
+        self.columns.declare(columns);
+    }
+
+    fn to_lon_lat(&self, coord: Coord) -> Coord {
+        let extent = DEFAULT_EXTENT as f64;
+        let (longitude, latitude) =
+            to_point(self.x + coord.x / extent, self.y + coord.y / extent, self.z);
+        Coord {
+            x: (longitude * self.scale).round() / self.scale,
+            y: (latitude * self.scale).round() / self.scale,
+        }
+    }
+
+    /// Adds a feature whose geometry is projected into tile extent coordinates
+    pub fn add_feature(&mut self, feature: &Feature) {
+        let Some(geometry) = self.clip_box.clip_geometry(&feature.geometry) else {
+            return;
+        };
+        let Some(encoding) = GeometryEncoding::of(&geometry) else {
+            return;
+        };
+
+        self.encoding = Some(match self.encoding {
+            Some(current) => current.merge(encoding),
+            None => encoding,
+        });
+        self.columns.add(&feature.properties);
+        self.geometries
+            .push(map_coords(&geometry, &|coord| self.to_lon_lat(coord)));
+        self.properties.push(feature.properties.clone());
+    }
+
+    pub fn into_bytes(self) -> Result<Vec<u8>, ArrowError> {
+        let encoding = self.encoding.unwrap_or(GeometryEncoding::Wkb);
+        let geometry = geometry_array(encoding, &self.geometries);
+        let geometry_field = Field::new(GEOMETRY_COLUMN, geometry.data_type().clone(), true)
+            .with_metadata(HashMap::from([
+                (
+                    "ARROW:extension:name".to_string(),
+                    encoding.extension_name().to_string(),
+                ),
+                (
+                    "ARROW:extension:metadata".to_string(),
+                    CRS_METADATA.to_string(),
+                ),
+            ]));
+
+        let mut fields = vec![geometry_field];
+        let mut columns = vec![geometry];
+        for (name, property_type) in self.columns.iter() {
+            let column = property_array(*property_type, name, &self.properties);
+            fields.push(Field::new(name, column.data_type().clone(), true));
+            columns.push(column);
+        }
+
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new(schema.clone(), columns)?;
+        let mut writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        writer.write(&batch)?;
+        writer.finish()?;
+        writer.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, Float64Array, Int64Array, StringArray};
+    use arrow_ipc::reader::StreamReader;
+    use geo_types::Point;
+
+    fn feature(properties: Vec<(&str, PropertyValue)>) -> Feature {
+        Feature {
+            geometry: Geometry::Point(Point::new(2048.0, 2048.0)),
+            properties: properties
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+
+    fn read_batch(layer: GeoArrowLayer) -> RecordBatch {
+        let data = layer.into_bytes().unwrap();
+        let mut reader = StreamReader::try_new(data.as_slice(), None).unwrap();
+        reader.next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn schema_follows_declared_columns() {
+        let mut layer = GeoArrowLayer::new(&Coordinates { x: 0, y: 0, z: 0 });
+        layer.declare_columns(&BTreeMap::from([
+            ("id".to_string(), PropertyType::Int),
+            ("name".to_string(), PropertyType::String),
+            ("height".to_string(), PropertyType::Double),
+        ]));
+        layer.add_feature(&feature(vec![
+            ("id", PropertyValue::UInt(u64::MAX)),
+            ("height", PropertyValue::Int(12)),
+        ]));
+        layer.add_feature(&feature(vec![("id", PropertyValue::Int(-4))]));
+
+        let batch = read_batch(layer);
+        let schema = batch.schema();
+        let geometry = schema.field_with_name(GEOMETRY_COLUMN).unwrap();
+        assert_eq!(
+            geometry.metadata()["ARROW:extension:name"],
+            GeometryEncoding::Point.extension_name()
+        );
+        assert_eq!(
+            schema.field_with_name("id").unwrap().data_type(),
+            &DataType::Int64
+        );
+        assert_eq!(
+            schema.field_with_name("name").unwrap().data_type(),
+            &DataType::Utf8
+        );
+
+        let ids = batch
+            .column_by_name("id")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert!(ids.is_null(0));
+        assert_eq!(ids.value(1), -4);
+        let heights = batch
+            .column_by_name("height")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(heights.value(0), 12.0);
+        assert!(heights.is_null(1));
+        let names = batch
+            .column_by_name("name")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(names.null_count(), 2);
+    }
+
+    #[test]
+    fn undeclared_signed_and_unsigned_columns_become_doubles() {
+        let mut layer = GeoArrowLayer::new(&Coordinates { x: 0, y: 0, z: 0 });
+        layer.add_feature(&feature(vec![("count", PropertyValue::UInt(3))]));
+        layer.add_feature(&feature(vec![("count", PropertyValue::Int(-3))]));
+
+        let batch = read_batch(layer);
+        assert_eq!(
+            batch.schema().field_with_name("count").unwrap().data_type(),
+            &DataType::Float64
+        );
+        assert_eq!(batch.num_rows(), 2);
+    }
+}
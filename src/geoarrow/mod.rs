@@ -0,0 +1 @@
+pub mod geoarrow_layer;
@@ -0,0 +1,140 @@
+use crate::geo::clip::ClipBox;
+use crate::geo::geo_utils::{get_coordinate_precision, to_point};
+use crate::mvt::constants::DEFAULT_EXTENT;
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature};
+use geo_types::{Coord, Geometry, LineString, Polygon};
+use serde_json::{json, Map, Value};
+
+const FEATURE_COLLECTION_START: &[u8] = br#"{"type":"FeatureCollection","features":["#;
+const FEATURE_COLLECTION_END: &[u8] = b"]}";
+
+/// Writes tile features as a GeoJSON FeatureCollection clipped to the tile in longitude/latitude
+pub struct GeoJsonLayer {
+    x: f64,
+    y: f64,
+    z: u32,
+    scale: f64,
+    clip_box: ClipBox,
+    data: Vec<u8>,
+    feature_count: usize,
+}
+
+impl GeoJsonLayer {
+    pub fn new(coordinates: &Coordinates) -> Self {
+        Self {
+            x: coordinates.x as f64,
+            y: coordinates.y as f64,
+            z: coordinates.z,
+            scale: 10f64.powi(get_coordinate_precision(coordinates.z) as i32),
+            clip_box: ClipBox {
+                min: 0.0,
+                max: DEFAULT_EXTENT as f64,
+            },
+            data: FEATURE_COLLECTION_START.to_vec(),
+            feature_count: 0,
+        }
+    }
+
+    fn position(&self, coord: &Coord) -> Value {
+        let extent = DEFAULT_EXTENT as f64;
+        let (longitude, latitude) =
+            to_point(self.x + coord.x / extent, self.y + coord.y / extent, self.z);
+        json!([
+            (longitude * self.scale).round() / self.scale,
+            (latitude * self.scale).round() / self.scale
+        ])
+    }
+
+    fn line_string(&self, line_string: &LineString) -> Value {
+        Value::Array(
+            line_string
+                .0
+                .iter()
+                .map(|coord| self.position(coord))
+                .collect(),
+        )
+    }
+
+    fn polygon(&self, polygon: &Polygon) -> Value {
+        Value::Array(
+            [polygon.exterior()]
+                .into_iter()
+                .chain(polygon.interiors())
+                .map(|ring| self.line_string(ring))
+                .collect(),
+        )
+    }
+
+    fn geometry(&self, geometry: &Geometry) -> Option<Value> {
+        let (geometry_type, coordinates) = match geometry {
+            Geometry::Point(point) => ("Point", self.position(&point.0)),
+            Geometry::MultiPoint(multi_point) => (
+                "MultiPoint",
+                Value::Array(
+                    multi_point
+                        .iter()
+                        .map(|point| self.position(&point.0))
+                        .collect(),
+                ),
+            ),
+            Geometry::LineString(line_string) => ("LineString", self.line_string(line_string)),
+            Geometry::MultiLineString(multi_line_string) => (
+                "MultiLineString",
+                Value::Array(
+                    multi_line_string
+                        .iter()
+                        .map(|line_string| self.line_string(line_string))
+                        .collect(),
+                ),
+            ),
+            Geometry::Polygon(polygon) => ("Polygon", self.polygon(polygon)),
+            Geometry::MultiPolygon(multi_polygon) => (
+                "MultiPolygon",
+                Value::Array(
+                    multi_polygon
+                        .iter()
+                        .map(|polygon| self.polygon(polygon))
+                        .collect(),
+                ),
+            ),
+            _ => return None,
+        };
+
+        Some(json!({ "type": geometry_type, "coordinates": coordinates }))
+    }
+
+    /// Adds a feature whose geometry is projected into tile extent coordinates
+    pub fn add_feature(&mut self, feature: &Feature) {
+        let Some(geometry) = self
+            .clip_box
+            .clip_geometry(&feature.geometry)
+            .and_then(|geometry| self.geometry(&geometry))
+        else {
+            return;
+        };
+
+        let properties: Map<String, Value> = feature
+            .properties
+            .iter()
+            .map(|(key, value)| (key.clone(), value.to_json()))
+            .collect();
+
+        let geojson_feature = json!({
+            "type": "Feature",
+            "geometry": geometry,
+            "properties": properties,
+        });
+
+        if self.feature_count > 0 {
+            self.data.push(b',');
+        }
+        self.data
+            .extend_from_slice(geojson_feature.to_string().as_bytes());
+        self.feature_count += 1;
+    }
+
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.data.extend_from_slice(FEATURE_COLLECTION_END);
+        self.data
+    }
+}
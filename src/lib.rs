@@ -1,10 +1,19 @@
 pub mod cache;
+pub mod clustering;
 pub mod config;
 pub mod db;
-pub mod default_header_layer;
 pub mod dep;
+pub mod export;
+pub mod generalization;
 mod geo;
+mod geoarrow;
+mod geojson;
+pub mod mbtiles;
+mod mlt;
 mod mvt;
+pub mod pmtiles;
 mod protos;
 pub mod routes;
+pub mod simplification;
+pub mod tile_expiry;
 pub mod tiling;
@@ -1,21 +1,105 @@
-use axum::http::header::CONTENT_ENCODING;
-use axum::http::{HeaderMap, HeaderValue, Method};
-use axum::{routing::get, Router};
+use axum::http::{HeaderValue, Method};
+use axum::routing::{delete, get};
+use axum::Router;
+use clap::{Args, Parser, Subcommand};
 use rs_dynamic_mvt::cache::cache_provider::CacheProvider;
+use rs_dynamic_mvt::cache::hot_tiles::HotTiles;
+use rs_dynamic_mvt::cache::single_flight::SingleFlight;
 use rs_dynamic_mvt::config::Config;
-use rs_dynamic_mvt::default_header_layer::DefaultHeaderLayer;
 use rs_dynamic_mvt::dep::AppState;
+use rs_dynamic_mvt::dep::StaticLayers;
+use rs_dynamic_mvt::mbtiles::mbtiles_writer::MbtilesWriter;
+use rs_dynamic_mvt::pmtiles::pmtiles_writer::PmtilesWriter;
+use rs_dynamic_mvt::routes::admin_handler::purge_cache;
+use rs_dynamic_mvt::routes::export_handler::export_layer;
 use rs_dynamic_mvt::routes::mvt_handler::get_tile;
+use rs_dynamic_mvt::routes::pmtiles_handler::get_archive;
+use rs_dynamic_mvt::routes::tile_json_handler::get_tile_json;
+use rs_dynamic_mvt::tile_expiry::expire_on_notify;
+use rs_dynamic_mvt::tiling::tile_pyramid::{
+    render_pyramid, PyramidError, PyramidSummary, TilePyramid, MAX_PYRAMID_ZOOM,
+};
+use rs_dynamic_mvt::tiling::tile_source::TileSource;
 use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::collections::HashMap;
 use std::env;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpListener;
 use tower_http::compression::CompressionLayer;
 use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Serve tiles over HTTP (default)
+    Serve,
+    /// Render a layer's tiles for a bounding box and zoom range into an MBTiles file
+    ExportMbtiles(ExportPyramidArgs),
+    /// Render a layer's tiles for a bounding box and zoom range into a PMTiles archive
+    ExportPmtiles(ExportPyramidArgs),
+}
+
+#[derive(Args)]
+struct ExportPyramidArgs {
+    /// File to write, an existing MBTiles file is resumed
+    output: PathBuf,
+    /// Layer whose configuration is used
+    #[arg(long, default_value = "default")]
+    layer: String,
+    /// min_x,min_y,max_x,max_y in longitude/latitude
+    #[arg(
+        long,
+        value_delimiter = ',',
+        num_args = 4,
+        allow_hyphen_values = true,
+        default_values_t = [-180.0, -85.051_128_78, 180.0, 85.051_128_78]
+    )]
+    bbox: Vec<f64>,
+    #[arg(long, default_value_t = 0, value_parser = zoom_parser())]
+    min_zoom: u32,
+    #[arg(long, value_parser = zoom_parser())]
+    max_zoom: u32,
+    /// Tiles rendered at the same time
+    #[arg(long, default_value_t = 4)]
+    concurrency: usize,
+    /// SQL query, defaults to the layer's QUERY
+    #[arg(long)]
+    query: Option<String>,
+    /// Geometry column, defaults to the layer's GEO_COL
+    #[arg(long)]
+    geo_col: Option<String>,
+    /// SRID of the geometry column, defaults to the layer's SRID or 4326
+    #[arg(long)]
+    srid: Option<String>,
+}
+
+fn zoom_parser() -> clap::builder::RangedI64ValueParser<u32> {
+    clap::value_parser!(u32).range(0..=MAX_PYRAMID_ZOOM as i64)
+}
+
+async fn connect(config: &Config, max_connections: u32) -> PgPool {
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .acquire_timeout(Duration::from_secs(3))
+        .connect(&config.database_url)
+        .await
+        .expect("can't connect to database")
+}
+
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
     let config = Config::from_env().unwrap();
 
     tracing_subscriber::registry()
@@ -26,6 +110,96 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
+    match cli.command {
+        Some(Command::ExportMbtiles(args)) => {
+            let output = args.output.clone();
+            run_export(config, args, |pool, pyramid| async move {
+                let writer = MbtilesWriter::open(&output, &pyramid).await?;
+                render_pyramid(pool, pyramid, writer).await
+            })
+            .await
+        }
+        Some(Command::ExportPmtiles(args)) => {
+            let output = args.output.clone();
+            run_export(config, args, |pool, pyramid| async move {
+                let writer = PmtilesWriter::create(&output, &pyramid)?;
+                render_pyramid(pool, pyramid, writer).await
+            })
+            .await
+        }
+        Some(Command::Serve) | None => serve(config).await,
+    }
+}
+
+async fn run_export<F, Fut>(config: Config, args: ExportPyramidArgs, export: F)
+where
+    F: FnOnce(PgPool, TilePyramid) -> Fut,
+    Fut: std::future::Future<Output = Result<PyramidSummary, PyramidError>>,
+{
+    let layer_config = config.get_layer_config(&args.layer);
+    let query = args
+        .query
+        .or(layer_config.query.clone())
+        .expect("the layer has no query, set --query or LAYERS__{LAYER}__QUERY");
+    let geo_col = args
+        .geo_col
+        .or(layer_config.geo_col.clone())
+        .expect("the layer has no geometry column, set --geo-col or LAYERS__{LAYER}__GEO_COL");
+    let srid = args
+        .srid
+        .or(layer_config.srid.clone())
+        .unwrap_or("4326".to_string());
+
+    let pyramid = TilePyramid {
+        layer: args.layer,
+        config: layer_config,
+        query,
+        geo_col,
+        srid,
+        bbox: [args.bbox[0], args.bbox[1], args.bbox[2], args.bbox[3]],
+        min_zoom: args.min_zoom,
+        max_zoom: args.max_zoom,
+        concurrency: args.concurrency,
+    };
+    if let Err(error) = pyramid.validate() {
+        tracing::error!("{}", error);
+        std::process::exit(2);
+    }
+
+    let pool = connect(&config, args.concurrency.max(1) as u32).await;
+    match export(pool, pyramid).await {
+        Ok(summary) => tracing::info!(
+            "rendered {} tiles ({} empty), skipped {} already rendered",
+            summary.rendered,
+            summary.empty,
+            summary.skipped
+        ),
+        Err(error) => {
+            tracing::error!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Opens the PMTiles archive or MBTiles file of every layer configured with one, skipping sources
+/// that can't be read
+async fn open_static_layers(config: &Config) -> StaticLayers {
+    let mut static_layers = HashMap::new();
+    for (name, layer_config) in config.layers.iter().flatten() {
+        match TileSource::open(layer_config).await {
+            Some(Ok(source)) => {
+                static_layers.insert(name.to_lowercase(), Arc::new(source));
+            }
+            Some(Err(error)) => {
+                tracing::warn!("can't open the source of layer {}: {}", name, error)
+            }
+            None => {}
+        }
+    }
+    Arc::new(static_layers)
+}
+
+async fn serve(config: Config) {
     let mut cors = CorsLayer::new()
         .allow_methods([Method::GET])
         .allow_headers(Any)
@@ -40,36 +214,47 @@ async fn main() {
         cors = cors.allow_origin(Any);
     };
 
-    let cache_provider = CacheProvider::new(config.cache_url.clone());
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .acquire_timeout(Duration::from_secs(3))
-        .connect(&config.database_url)
-        .await
-        .expect("can't connect to database");
+    let cache_provider = CacheProvider::from_config(&config);
+    let pool = connect(&config, 10).await;
+    let static_layers = open_static_layers(&config).await;
 
     let mvt_route = Router::new().route("/:x/:y/:z", get(get_tile));
 
+    let state = AppState {
+        pool,
+        cache: cache_provider,
+        config: config.clone(),
+        static_layers,
+        flights: Arc::new(SingleFlight::default()),
+        hot_tiles: config
+            .expiry_channel
+            .as_ref()
+            .and(config.expiry_refresh_tiles)
+            .and_then(NonZeroUsize::new)
+            .map(|capacity| Arc::new(HotTiles::new(capacity))),
+    };
+    if let Some(channel) = &config.expiry_channel {
+        tokio::spawn(expire_on_notify(state.clone(), channel.clone()));
+    }
     let mut app = Router::new()
-        .nest("/mvt", mvt_route)
-        .layer(cors)
-        .with_state(AppState {
-            pool,
-            cache: cache_provider,
-            config: config.clone(),
-        });
+        .route("/export/:layer", get(export_layer))
+        .route("/tilejson", get(get_tile_json))
+        .route("/admin/cache", delete(purge_cache))
+        .with_state(state.clone());
 
     let disabled_gzip = config.disable_gzip.unwrap_or(false);
     if !disabled_gzip {
-        let mut default_headers = HeaderMap::new();
-        default_headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
-
-        let compression_layer = CompressionLayer::new().gzip(true);
-        app = app
-            .layer(compression_layer)
-            .layer(DefaultHeaderLayer::new(default_headers));
+        app = app.layer(CompressionLayer::new().gzip(true));
     }
 
+    // tiles are sent precompressed with a strong ETag per encoding and archives byte for byte so
+    // ranges stay valid, so both are served outside the compression layer
+    let precompressed_routes = Router::new()
+        .nest("/mvt", mvt_route)
+        .route("/pmtiles/:layer", get(get_archive))
+        .with_state(state);
+    let app = app.merge(precompressed_routes).layer(cors);
+
     let listener = TcpListener::bind("127.0.0.1:8095").await.unwrap();
     tracing::debug!("listening on {}", listener.local_addr().unwrap());
     axum::serve(listener, app).await.unwrap();
@@ -0,0 +1,120 @@
+use crate::mbtiles::mbtiles_writer::tile_row;
+use flate2::read::GzDecoder;
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+fn parse_numbers(value: &str) -> Option<Vec<f64>> {
+    value
+        .split(',')
+        .map(|number| number.trim().parse().ok())
+        .collect()
+}
+
+/// Reads tiles from a local MBTiles file, opened read-only
+pub struct MbtilesReader {
+    path: PathBuf,
+    pool: SqlitePool,
+    metadata: HashMap<String, String>,
+}
+
+impl MbtilesReader {
+    /// Opens the file and reads its `metadata` table
+    pub async fn open(path: &Path) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::new().filename(path).read_only(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await?;
+
+        let metadata = sqlx::query("SELECT name, value FROM metadata")
+            .fetch_all(&pool)
+            .await?
+            .iter()
+            .filter_map(|row| Some((row.try_get(0).ok()?, row.try_get(1).ok()?)))
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            pool,
+            metadata,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_mvt(&self) -> bool {
+        self.metadata.get("format").map(String::as_str) == Some("pbf")
+    }
+
+    /// TileJSON from the `metadata` table, without `tiles`
+    pub fn tile_json(&self) -> Value {
+        let mut tile_json = json!({ "tilejson": "3.0.0" });
+        for (name, value) in self.metadata.iter() {
+            match name.as_str() {
+                "name" | "description" | "attribution" | "version" => {
+                    tile_json[name] = json!(value);
+                }
+                "minzoom" | "maxzoom" => {
+                    if let Ok(zoom) = value.parse::<u32>() {
+                        tile_json[name] = json!(zoom);
+                    }
+                }
+                "bounds" => {
+                    if let Some(bounds) = parse_numbers(value).filter(|bounds| bounds.len() == 4) {
+                        tile_json[name] = json!(bounds);
+                    }
+                }
+                "center" => {
+                    if let Some(center) = parse_numbers(value).filter(|center| center.len() == 3) {
+                        tile_json[name] = json!([center[0], center[1], center[2] as u32]);
+                    }
+                }
+                "json" => {
+                    if let Ok(Value::Object(json)) = serde_json::from_str(value) {
+                        if let Some(vector_layers) = json.get("vector_layers") {
+                            tile_json["vector_layers"] = vector_layers.clone();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        tile_json
+    }
+
+    /// Returns the tile, gunzipped if stored gzipped, `None` when the file does not hold it
+    pub async fn get_tile(&self, x: u32, y: u32, z: u32) -> Result<Option<Vec<u8>>, sqlx::Error> {
+        if z > 31 || x >= 1 << z || y >= 1 << z {
+            return Ok(None);
+        }
+
+        let row = sqlx::query(
+            "SELECT tile_data FROM tiles WHERE zoom_level = ? AND tile_column = ? AND tile_row = ?",
+        )
+        .bind(z)
+        .bind(x)
+        .bind(tile_row(y, z))
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(data) = row.map(|row| row.try_get::<Vec<u8>, _>(0)).transpose()? else {
+            return Ok(None);
+        };
+
+        if !data.starts_with(&GZIP_MAGIC) {
+            return Ok(Some(data));
+        }
+        let mut tile = Vec::new();
+        GzDecoder::new(data.as_slice())
+            .read_to_end(&mut tile)
+            .map_err(|error| sqlx::Error::Decode(error.into()))?;
+        Ok(Some(tile))
+    }
+}
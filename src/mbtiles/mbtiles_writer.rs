@@ -0,0 +1,152 @@
+use crate::tiling::tile_pyramid::{PyramidError, PyramidTile, TileArchiveWriter, TilePyramid};
+use async_trait::async_trait;
+use indoc::indoc;
+use serde_json::json;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::collections::HashSet;
+use std::path::Path;
+
+const SCHEMA: &str = indoc! {r#"
+    CREATE TABLE IF NOT EXISTS metadata (name TEXT NOT NULL, value TEXT, UNIQUE (name));
+    CREATE TABLE IF NOT EXISTS tiles (
+        zoom_level INTEGER NOT NULL,
+        tile_column INTEGER NOT NULL,
+        tile_row INTEGER NOT NULL,
+        tile_data BLOB,
+        UNIQUE (zoom_level, tile_column, tile_row)
+    );
+    CREATE TABLE IF NOT EXISTS rendered_tiles (
+        zoom_level INTEGER NOT NULL,
+        tile_column INTEGER NOT NULL,
+        tile_row INTEGER NOT NULL,
+        UNIQUE (zoom_level, tile_column, tile_row)
+    );
+"#};
+
+/// MBTiles rows count from the bottom of the map (TMS)
+pub(crate) fn tile_row(y: u32, z: u32) -> i64 {
+    (1i64 << z) - 1 - y as i64
+}
+
+fn metadata(pyramid: &TilePyramid) -> Vec<(&'static str, String)> {
+    let [min_x, min_y, max_x, max_y] = pyramid.bbox;
+    let (center_x, center_y, center_zoom) = pyramid.center();
+    let json = json!({ "vector_layers": pyramid.vector_layers() });
+
+    vec![
+        ("name", pyramid.layer.clone()),
+        ("format", "pbf".to_string()),
+        ("type", "overlay".to_string()),
+        ("bounds", format!("{},{},{},{}", min_x, min_y, max_x, max_y)),
+        (
+            "center",
+            format!("{},{},{}", center_x, center_y, center_zoom),
+        ),
+        ("minzoom", pyramid.min_zoom.to_string()),
+        ("maxzoom", pyramid.max_zoom.to_string()),
+        ("json", json.to_string()),
+    ]
+}
+
+/// Writes tiles into an MBTiles file. Every rendered tile, including empty tiles that are not
+/// stored, is recorded in `rendered_tiles` so an interrupted export can skip it when resumed.
+pub struct MbtilesWriter {
+    pool: SqlitePool,
+}
+
+impl MbtilesWriter {
+    /// Opens or creates the file and writes the pyramid's metadata
+    pub async fn open(path: &Path, pyramid: &TilePyramid) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+        sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+
+        let writer = Self { pool };
+        writer.write_metadata(&metadata(pyramid)).await?;
+        Ok(writer)
+    }
+
+    async fn write_metadata(&self, metadata: &[(&str, String)]) -> Result<(), sqlx::Error> {
+        let mut transaction = self.pool.begin().await?;
+        for (name, value) in metadata {
+            sqlx::query("INSERT OR REPLACE INTO metadata (name, value) VALUES (?, ?)")
+                .bind(name)
+                .bind(value)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await
+    }
+}
+
+#[async_trait]
+impl TileArchiveWriter for MbtilesWriter {
+    async fn rendered_tiles(&self, z: u32) -> Result<HashSet<(u32, u32)>, PyramidError> {
+        let rows = sqlx::query(
+            "SELECT tile_column, tile_row FROM rendered_tiles WHERE zoom_level = ?
+             UNION SELECT tile_column, tile_row FROM tiles WHERE zoom_level = ?",
+        )
+        .bind(z)
+        .bind(z)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let tiles = rows
+            .iter()
+            .map(|row| {
+                let x: i64 = row.try_get(0)?;
+                let row: i64 = row.try_get(1)?;
+                Ok((x as u32, tile_row(row as u32, z) as u32))
+            })
+            .collect::<Result<_, sqlx::Error>>()?;
+        Ok(tiles)
+    }
+
+    async fn write_tiles(&mut self, tiles: &[PyramidTile]) -> Result<(), PyramidError> {
+        let mut transaction = self.pool.begin().await?;
+        for tile in tiles {
+            if let Some(data) = &tile.data {
+                sqlx::query(
+                    "INSERT OR REPLACE INTO tiles (zoom_level, tile_column, tile_row, tile_data)
+                     VALUES (?, ?, ?, ?)",
+                )
+                .bind(tile.z)
+                .bind(tile.x)
+                .bind(tile_row(tile.y, tile.z))
+                .bind(data)
+                .execute(&mut *transaction)
+                .await?;
+            }
+            sqlx::query(
+                "INSERT OR IGNORE INTO rendered_tiles (zoom_level, tile_column, tile_row)
+                 VALUES (?, ?, ?)",
+            )
+            .bind(tile.z)
+            .bind(tile.x)
+            .bind(tile_row(tile.y, tile.z))
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Drops the resume bookkeeping once every tile has been written
+    async fn finish(self) -> Result<(), PyramidError> {
+        sqlx::query("DROP TABLE IF EXISTS rendered_tiles")
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("PRAGMA journal_mode = DELETE")
+            .execute(&self.pool)
+            .await?;
+        self.pool.close().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,2 @@
+pub mod mbtiles_reader;
+pub mod mbtiles_writer;
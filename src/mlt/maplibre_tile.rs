@@ -0,0 +1,471 @@
+use crate::mlt::stream_encoder::{
+    boolean_rle, write_present_stream, write_stream, write_string, write_varint,
+    write_varint_stream, zigzag, StreamMetadata, DICTIONARY_NONE, DICTIONARY_VERTEX,
+    LENGTH_GEOMETRIES, LENGTH_PARTS, LENGTH_RINGS, LENGTH_VAR_BINARY, LOGICAL_COMPONENTWISE_DELTA,
+    LOGICAL_NONE, PHYSICAL_NONE, PHYSICAL_STREAM_DATA, PHYSICAL_STREAM_LENGTH, PHYSICAL_VARINT,
+};
+use crate::mvt::constants::DEFAULT_EXTENT;
+use crate::mvt::mapbox_vector_tile::Feature;
+use crate::mvt::property_value::{Properties, PropertyColumns, PropertyType, PropertyValue};
+use geo_types::{Coord, Geometry, LineString, Polygon};
+use std::collections::BTreeMap;
+
+/// Tag of a layer holding a feature table with embedded metadata
+const FEATURE_TABLE_TAG: u64 = 1;
+
+const COLUMN_KIND_SCALAR: u64 = 0;
+const COLUMN_KIND_COMPLEX: u64 = 1;
+const COMPLEX_TYPE_GEOMETRY: u64 = 0;
+
+const GEOMETRY_POINT: u64 = 0;
+const GEOMETRY_LINESTRING: u64 = 1;
+const GEOMETRY_POLYGON: u64 = 2;
+const GEOMETRY_MULTIPOINT: u64 = 3;
+const GEOMETRY_MULTILINESTRING: u64 = 4;
+const GEOMETRY_MULTIPOLYGON: u64 = 5;
+
+/// Scalar column type numbers of the MLT metadata schema
+fn scalar_type_code(property_type: PropertyType) -> u64 {
+    match property_type {
+        PropertyType::Bool => 0,
+        PropertyType::Int => 5,
+        PropertyType::UInt => 6,
+        PropertyType::Float => 7,
+        PropertyType::Double => 8,
+        PropertyType::String => 9,
+    }
+}
+
+struct MltGeometry {
+    geometry_type: u64,
+    polygons: Vec<Vec<Vec<Coord<i64>>>>,
+    lines: Vec<Vec<Coord<i64>>>,
+    points: Vec<Coord<i64>>,
+}
+
+fn to_vertex(coord: &Coord) -> Coord<i64> {
+    Coord {
+        x: coord.x.floor() as i64,
+        y: coord.y.floor() as i64,
+    }
+}
+
+fn to_line(line_string: &LineString) -> Vec<Coord<i64>> {
+    line_string.0.iter().map(to_vertex).collect()
+}
+
+/// Rings are stored without their closing vertex
+fn to_rings(polygon: &Polygon) -> Vec<Vec<Coord<i64>>> {
+    [polygon.exterior()]
+        .into_iter()
+        .chain(polygon.interiors())
+        .map(|ring| {
+            let mut vertices = to_line(ring);
+            if vertices.len() > 1 && vertices.first() == vertices.last() {
+                vertices.pop();
+            }
+            vertices
+        })
+        .collect()
+}
+
+impl MltGeometry {
+    fn from_geometry(geometry: &Geometry) -> Option<Self> {
+        let mut mlt_geometry = MltGeometry {
+            geometry_type: 0,
+            polygons: vec![],
+            lines: vec![],
+            points: vec![],
+        };
+        match geometry {
+            Geometry::Point(point) => {
+                mlt_geometry.geometry_type = GEOMETRY_POINT;
+                mlt_geometry.points.push(to_vertex(&point.0));
+            }
+            Geometry::MultiPoint(multi_point) if !multi_point.0.is_empty() => {
+                mlt_geometry.geometry_type = GEOMETRY_MULTIPOINT;
+                mlt_geometry.points = multi_point
+                    .iter()
+                    .map(|point| to_vertex(&point.0))
+                    .collect();
+            }
+            Geometry::LineString(line_string) => {
+                mlt_geometry.geometry_type = GEOMETRY_LINESTRING;
+                mlt_geometry.lines.push(to_line(line_string));
+            }
+            Geometry::MultiLineString(multi_line_string) if !multi_line_string.0.is_empty() => {
+                mlt_geometry.geometry_type = GEOMETRY_MULTILINESTRING;
+                mlt_geometry.lines = multi_line_string.iter().map(to_line).collect();
+            }
+            Geometry::Polygon(polygon) => {
+                mlt_geometry.geometry_type = GEOMETRY_POLYGON;
+                mlt_geometry.polygons.push(to_rings(polygon));
+            }
+            Geometry::MultiPolygon(multi_polygon) if !multi_polygon.0.is_empty() => {
+                mlt_geometry.geometry_type = GEOMETRY_MULTIPOLYGON;
+                mlt_geometry.polygons = multi_polygon.iter().map(to_rings).collect();
+            }
+            _ => return None,
+        }
+        Some(mlt_geometry)
+    }
+
+    fn is_multi(&self) -> bool {
+        self.geometry_type >= GEOMETRY_MULTIPOINT
+    }
+
+    fn num_geometries(&self) -> usize {
+        match self.geometry_type {
+            GEOMETRY_POINT | GEOMETRY_MULTIPOINT => self.points.len(),
+            GEOMETRY_LINESTRING | GEOMETRY_MULTILINESTRING => self.lines.len(),
+            _ => self.polygons.len(),
+        }
+    }
+}
+
+/// Writes tile features as a MapLibre Tile (MLT) feature table with a geometry column and one
+/// typed column per property
+pub struct MapLibreLayer {
+    name: String,
+    geometries: Vec<MltGeometry>,
+    properties: Vec<Properties>,
+    columns: PropertyColumns,
+}
+
+impl MapLibreLayer {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            geometries: vec![],
+            properties: vec![],
+            columns: PropertyColumns::default(),
+        }
+    }
+
+    /// Types property columns after the query's columns rather than the values of the tile
+    pub fn declare_columns(&mut self, columns: &BTreeMap<String, PropertyType>) {
+        self.columns.declare(columns);
+    }
+
+    /// Adds a feature whose geometry is projected into tile extent coordinates
+    pub fn push_feature(&mut self, feature: &Feature) {
+        let Some(geometry) = MltGeometry::from_geometry(&feature.geometry) else {
+            return;
+        };
+
+        self.columns.add(&feature.properties);
+        self.geometries.push(geometry);
+        self.properties.push(feature.properties.clone());
+    }
+
+    fn write_metadata(&self, out: &mut Vec<u8>) {
+        write_string(out, &self.name);
+        write_varint(out, DEFAULT_EXTENT as u64);
+        write_varint(out, self.geometries.len() as u64);
+        write_varint(out, self.columns.len() as u64 + 1);
+
+        write_varint(out, COLUMN_KIND_COMPLEX);
+        write_varint(out, COMPLEX_TYPE_GEOMETRY);
+        out.push(0);
+        write_string(out, "geometry");
+
+        for (name, property_type) in self.columns.iter() {
+            write_varint(out, COLUMN_KIND_SCALAR);
+            write_varint(out, scalar_type_code(*property_type));
+            out.push(1);
+            write_string(out, name);
+        }
+    }
+
+    /// Geometry column: geometry types, topology length streams and a delta encoded vertex buffer
+    fn write_geometry_column(&self, out: &mut Vec<u8>) {
+        let contains_multi = self.geometries.iter().any(MltGeometry::is_multi);
+        let contains_polygons = self
+            .geometries
+            .iter()
+            .any(|geometry| !geometry.polygons.is_empty());
+
+        let mut geometry_types = Vec::with_capacity(self.geometries.len());
+        let mut num_geometries = vec![];
+        let mut num_parts = vec![];
+        let mut num_rings = vec![];
+        let mut vertices: Vec<Coord<i64>> = vec![];
+
+        for geometry in self.geometries.iter() {
+            geometry_types.push(geometry.geometry_type);
+            if contains_multi {
+                num_geometries.push(geometry.num_geometries() as u64);
+            }
+            vertices.extend(geometry.points.iter());
+            for line in geometry.lines.iter() {
+                // line vertex counts share the ring stream when the column also holds polygons
+                if contains_polygons {
+                    num_rings.push(line.len() as u64);
+                } else {
+                    num_parts.push(line.len() as u64);
+                }
+                vertices.extend(line.iter());
+            }
+            for polygon in geometry.polygons.iter() {
+                num_parts.push(polygon.len() as u64);
+                for ring in polygon.iter() {
+                    num_rings.push(ring.len() as u64);
+                    vertices.extend(ring.iter());
+                }
+            }
+        }
+
+        let topology_streams = [
+            (LENGTH_GEOMETRIES, num_geometries),
+            (LENGTH_PARTS, num_parts),
+            (LENGTH_RINGS, num_rings),
+        ];
+        let stream_count = 2 + topology_streams
+            .iter()
+            .filter(|(_, lengths)| !lengths.is_empty())
+            .count();
+        write_varint(out, stream_count as u64);
+
+        write_varint_stream(out, PHYSICAL_STREAM_DATA, DICTIONARY_NONE, &geometry_types);
+        for (length_type, lengths) in topology_streams.iter() {
+            if !lengths.is_empty() {
+                write_varint_stream(out, PHYSICAL_STREAM_LENGTH, *length_type, lengths);
+            }
+        }
+
+        let mut data = Vec::with_capacity(vertices.len() * 2);
+        let mut previous = Coord { x: 0, y: 0 };
+        for vertex in vertices.iter() {
+            write_varint(&mut data, zigzag(vertex.x - previous.x));
+            write_varint(&mut data, zigzag(vertex.y - previous.y));
+            previous = *vertex;
+        }
+        write_stream(
+            out,
+            &StreamMetadata {
+                physical_stream_type: PHYSICAL_STREAM_DATA,
+                logical_stream_type: DICTIONARY_VERTEX,
+                logical_technique: LOGICAL_COMPONENTWISE_DELTA,
+                physical_technique: PHYSICAL_VARINT,
+                num_values: vertices.len() * 2,
+            },
+            &data,
+        );
+    }
+
+    /// Property column: a present stream followed by the values of the features that have one the
+    /// column's type holds
+    fn write_property_column(&self, out: &mut Vec<u8>, name: &str, property_type: PropertyType) {
+        let values: Vec<Option<&PropertyValue>> = self
+            .properties
+            .iter()
+            .map(|properties| {
+                properties
+                    .get(name)
+                    .filter(|value| property_type.holds(value))
+            })
+            .collect();
+        let present: Vec<bool> = values.iter().map(Option::is_some).collect();
+        let values: Vec<&PropertyValue> = values.into_iter().flatten().collect();
+
+        if property_type == PropertyType::String {
+            write_varint(out, 3);
+        }
+        write_present_stream(out, &present);
+
+        match property_type {
+            PropertyType::Bool => {
+                let booleans: Vec<bool> = values
+                    .iter()
+                    .map(|value| matches!(value, PropertyValue::Bool(true)))
+                    .collect();
+                write_stream(
+                    out,
+                    &StreamMetadata {
+                        physical_stream_type: PHYSICAL_STREAM_DATA,
+                        logical_stream_type: DICTIONARY_NONE,
+                        logical_technique: LOGICAL_NONE,
+                        physical_technique: PHYSICAL_NONE,
+                        num_values: booleans.len(),
+                    },
+                    &boolean_rle(&booleans),
+                );
+            }
+            PropertyType::Int => {
+                let integers: Vec<u64> = values
+                    .iter()
+                    .filter_map(|value| value.as_i64())
+                    .map(zigzag)
+                    .collect();
+                write_varint_stream(out, PHYSICAL_STREAM_DATA, DICTIONARY_NONE, &integers);
+            }
+            PropertyType::UInt => {
+                let integers: Vec<u64> = values.iter().filter_map(|value| value.as_u64()).collect();
+                write_varint_stream(out, PHYSICAL_STREAM_DATA, DICTIONARY_NONE, &integers);
+            }
+            PropertyType::Float | PropertyType::Double => {
+                let mut data = vec![];
+                for number in values.iter().filter_map(|value| value.as_f64()) {
+                    if property_type == PropertyType::Float {
+                        data.extend_from_slice(&(number as f32).to_le_bytes());
+                    } else {
+                        data.extend_from_slice(&number.to_le_bytes());
+                    }
+                }
+                write_stream(
+                    out,
+                    &StreamMetadata {
+                        physical_stream_type: PHYSICAL_STREAM_DATA,
+                        logical_stream_type: DICTIONARY_NONE,
+                        logical_technique: LOGICAL_NONE,
+                        physical_technique: PHYSICAL_NONE,
+                        num_values: values.len(),
+                    },
+                    &data,
+                );
+            }
+            PropertyType::String => {
+                let strings: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                let lengths: Vec<u64> = strings.iter().map(|value| value.len() as u64).collect();
+                write_varint_stream(out, PHYSICAL_STREAM_LENGTH, LENGTH_VAR_BINARY, &lengths);
+                write_stream(
+                    out,
+                    &StreamMetadata {
+                        physical_stream_type: PHYSICAL_STREAM_DATA,
+                        logical_stream_type: DICTIONARY_NONE,
+                        logical_technique: LOGICAL_NONE,
+                        physical_technique: PHYSICAL_NONE,
+                        num_values: strings.len(),
+                    },
+                    strings.concat().as_bytes(),
+                );
+            }
+        }
+    }
+
+    /// Encodes the layer as a length prefixed feature table
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut table = vec![];
+        write_varint(&mut table, FEATURE_TABLE_TAG);
+        self.write_metadata(&mut table);
+        self.write_geometry_column(&mut table);
+        for (name, property_type) in self.columns.iter() {
+            self.write_property_column(&mut table, name, *property_type);
+        }
+
+        let mut out = Vec::with_capacity(table.len() + 5);
+        write_varint(&mut out, table.len() as u64);
+        out.extend_from_slice(&table);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::Point;
+
+    fn read_varint(data: &[u8], offset: &mut usize) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = data[*offset];
+            *offset += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_string(data: &[u8], offset: &mut usize) -> String {
+        let length = read_varint(data, offset) as usize;
+        let value = String::from_utf8(data[*offset..*offset + length].to_vec()).unwrap();
+        *offset += length;
+        value
+    }
+
+    fn feature(properties: Vec<(&str, PropertyValue)>) -> Feature {
+        Feature {
+            geometry: Geometry::Point(Point::new(10.0, 20.0)),
+            properties: properties
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+
+    /// Names and scalar type codes of the property columns in the layer metadata
+    fn property_columns(data: &[u8]) -> Vec<(String, u64)> {
+        let mut offset = 0;
+        let length = read_varint(data, &mut offset) as usize;
+        assert_eq!(data.len(), offset + length);
+        assert_eq!(read_varint(data, &mut offset), FEATURE_TABLE_TAG);
+        read_string(data, &mut offset);
+        assert_eq!(read_varint(data, &mut offset), DEFAULT_EXTENT as u64);
+        read_varint(data, &mut offset);
+        let column_count = read_varint(data, &mut offset);
+
+        let mut columns = vec![];
+        for _ in 0..column_count {
+            let kind = read_varint(data, &mut offset);
+            let type_code = read_varint(data, &mut offset);
+            offset += 1;
+            let name = read_string(data, &mut offset);
+            if kind == COLUMN_KIND_SCALAR {
+                columns.push((name, type_code));
+            }
+        }
+        columns
+    }
+
+    #[test]
+    fn declared_columns_keep_their_type() {
+        let mut layer = MapLibreLayer::new("roads".to_string());
+        layer.declare_columns(&BTreeMap::from([
+            ("id".to_string(), PropertyType::Int),
+            ("oid".to_string(), PropertyType::UInt),
+        ]));
+        layer.push_feature(&feature(vec![
+            ("id", PropertyValue::UInt(u64::MAX)),
+            ("oid", PropertyValue::Int(-1)),
+            ("point_count", PropertyValue::UInt(2)),
+        ]));
+        layer.push_feature(&feature(vec![("point_count", PropertyValue::Int(-2))]));
+
+        assert_eq!(
+            property_columns(&layer.into_bytes()),
+            vec![
+                ("id".to_string(), scalar_type_code(PropertyType::Int)),
+                ("oid".to_string(), scalar_type_code(PropertyType::UInt)),
+                (
+                    "point_count".to_string(),
+                    scalar_type_code(PropertyType::Double)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn values_a_column_cannot_hold_are_not_present() {
+        let mut layer = MapLibreLayer::new("roads".to_string());
+        layer.declare_columns(&BTreeMap::from([("id".to_string(), PropertyType::Int)]));
+        layer.push_feature(&feature(vec![("id", PropertyValue::UInt(u64::MAX))]));
+        layer.push_feature(&feature(vec![("id", PropertyValue::Int(-3))]));
+
+        let mut column = vec![];
+        layer.write_property_column(&mut column, "id", PropertyType::Int);
+
+        let mut present = vec![];
+        write_present_stream(&mut present, &[false, true]);
+        let mut values = vec![];
+        write_varint_stream(
+            &mut values,
+            PHYSICAL_STREAM_DATA,
+            DICTIONARY_NONE,
+            &[zigzag(-3)],
+        );
+        assert_eq!(column, [present, values].concat());
+    }
+}
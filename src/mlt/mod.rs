@@ -0,0 +1,2 @@
+pub mod maplibre_tile;
+mod stream_encoder;
@@ -0,0 +1,229 @@
+//! Stream level encodings of the MapLibre Tile format
+
+pub const PHYSICAL_STREAM_PRESENT: u8 = 0;
+pub const PHYSICAL_STREAM_DATA: u8 = 1;
+pub const PHYSICAL_STREAM_LENGTH: u8 = 3;
+
+pub const DICTIONARY_NONE: u8 = 0;
+pub const DICTIONARY_VERTEX: u8 = 3;
+
+pub const LENGTH_VAR_BINARY: u8 = 0;
+pub const LENGTH_GEOMETRIES: u8 = 1;
+pub const LENGTH_PARTS: u8 = 2;
+pub const LENGTH_RINGS: u8 = 3;
+
+pub const LOGICAL_NONE: u8 = 0;
+pub const LOGICAL_COMPONENTWISE_DELTA: u8 = 2;
+
+pub const PHYSICAL_NONE: u8 = 0;
+pub const PHYSICAL_VARINT: u8 = 2;
+
+pub fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub fn write_string(out: &mut Vec<u8>, value: &str) {
+    write_varint(out, value.len() as u64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// ORC byte run length encoding, written as literal groups of at most 128 bytes
+pub fn byte_rle(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + bytes.len() / 128 + 1);
+    for chunk in bytes.chunks(128) {
+        out.push((256 - chunk.len()) as u8);
+        out.extend_from_slice(chunk);
+    }
+    out
+}
+
+/// Packs booleans least significant bit first and byte RLE encodes them
+pub fn boolean_rle(values: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; values.len().div_ceil(8)];
+    for (index, value) in values.iter().enumerate() {
+        if *value {
+            bytes[index / 8] |= 1 << (index % 8);
+        }
+    }
+    byte_rle(&bytes)
+}
+
+pub struct StreamMetadata {
+    pub physical_stream_type: u8,
+    pub logical_stream_type: u8,
+    pub logical_technique: u8,
+    pub physical_technique: u8,
+    pub num_values: usize,
+}
+
+/// Writes the stream header followed by its already encoded data
+pub fn write_stream(out: &mut Vec<u8>, metadata: &StreamMetadata, data: &[u8]) {
+    out.push((metadata.physical_stream_type << 4) | (metadata.logical_stream_type & 0x0f));
+    out.push(((metadata.logical_technique & 0x07) << 5) | (metadata.physical_technique & 0x03));
+    write_varint(out, metadata.num_values as u64);
+    write_varint(out, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+pub fn write_present_stream(out: &mut Vec<u8>, present: &[bool]) {
+    write_stream(
+        out,
+        &StreamMetadata {
+            physical_stream_type: PHYSICAL_STREAM_PRESENT,
+            logical_stream_type: 0,
+            logical_technique: LOGICAL_NONE,
+            physical_technique: PHYSICAL_NONE,
+            num_values: present.len(),
+        },
+        &boolean_rle(present),
+    );
+}
+
+pub fn write_varint_stream(
+    out: &mut Vec<u8>,
+    physical_stream_type: u8,
+    logical_stream_type: u8,
+    values: &[u64],
+) {
+    let mut data = Vec::with_capacity(values.len());
+    for value in values {
+        write_varint(&mut data, *value);
+    }
+    write_stream(
+        out,
+        &StreamMetadata {
+            physical_stream_type,
+            logical_stream_type,
+            logical_technique: LOGICAL_NONE,
+            physical_technique: PHYSICAL_VARINT,
+            num_values: values.len(),
+        },
+        &data,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_varint(data: &[u8], position: &mut usize) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = data[*position];
+            *position += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+            shift += 7;
+        }
+    }
+
+    fn unzigzag(value: u64) -> i64 {
+        (value >> 1) as i64 ^ -((value & 1) as i64)
+    }
+
+    /// ORC byte RLE decoding, with runs as well as the literal groups the encoder writes
+    fn decode_byte_rle(data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut position = 0;
+        while position < data.len() {
+            let header = data[position];
+            if header < 128 {
+                bytes.extend(std::iter::repeat_n(data[position + 1], header as usize + 3));
+                position += 2;
+            } else {
+                let length = 256 - header as usize;
+                bytes.extend_from_slice(&data[position + 1..position + 1 + length]);
+                position += 1 + length;
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        let values = [0, 1, 127, 128, 16_383, 16_384, u64::MAX];
+        let mut data = vec![];
+        for value in values {
+            write_varint(&mut data, value);
+        }
+
+        let mut position = 0;
+        for value in values {
+            assert_eq!(read_varint(&data, &mut position), value);
+        }
+        assert_eq!(position, data.len());
+    }
+
+    #[test]
+    fn zigzag_interleaves_signs() {
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(zigzag(-2), 3);
+        for value in [i64::MIN, -12_345, 12_345, i64::MAX] {
+            assert_eq!(unzigzag(zigzag(value)), value);
+        }
+    }
+
+    #[test]
+    fn byte_rle_round_trips() {
+        assert!(byte_rle(&[]).is_empty());
+        assert_eq!(byte_rle(&[7, 8]), [254, 7, 8]);
+
+        let bytes: Vec<u8> = (0..300).map(|value| value as u8).collect();
+        let encoded = byte_rle(&bytes);
+        assert_eq!(encoded.len(), bytes.len() + 3);
+        assert_eq!(decode_byte_rle(&encoded), bytes);
+    }
+
+    #[test]
+    fn boolean_rle_packs_least_significant_bit_first() {
+        let values = [
+            true, false, true, true, false, false, false, false, false, true,
+        ];
+        let encoded = boolean_rle(&values);
+        assert_eq!(encoded, [254, 0b0000_1101, 0b0000_0010]);
+
+        let bytes = decode_byte_rle(&encoded);
+        let decoded: Vec<bool> = (0..values.len())
+            .map(|index| bytes[index / 8] & (1 << (index % 8)) != 0)
+            .collect();
+        assert_eq!(decoded, values);
+    }
+
+    #[test]
+    fn streams_start_with_their_metadata() {
+        let mut out = vec![];
+        write_varint_stream(&mut out, PHYSICAL_STREAM_LENGTH, LENGTH_PARTS, &[1, 300]);
+        assert_eq!(
+            out,
+            [
+                (PHYSICAL_STREAM_LENGTH << 4) | LENGTH_PARTS,
+                PHYSICAL_VARINT,
+                2,
+                3,
+                1,
+                0xac,
+                0x02
+            ]
+        );
+
+        let mut out = vec![];
+        write_present_stream(&mut out, &[true, true]);
+        assert_eq!(
+            out,
+            [PHYSICAL_STREAM_PRESENT << 4, PHYSICAL_NONE, 2, 2, 255, 3]
+        );
+    }
+}
@@ -1 +1,2 @@
 pub const DEFAULT_EXTENT: u32 = 4096;
+pub const DEFAULT_TILE_SIZE: u32 = 512;
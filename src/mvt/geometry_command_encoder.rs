@@ -1,4 +1,4 @@
-use crate::geo::geo_utils::mercator_to_tile;
+use crate::geo::geo_utils::{map_coords, mercator_to_tile};
 use crate::mvt::mapbox_vector_tile::Coordinates;
 use crate::protos::vector_tile::tile::GeomType;
 use geo_types::{Coord, Geometry, LineString, Point, Polygon};
@@ -30,32 +30,33 @@ impl TileProjection {
         }
     }
 
-    pub fn project_point(&self, point: &Point) -> Point {
-        let point = mercator_to_tile(point.0.x, point.0.y, self.zoom_level);
-        Point(Coord {
-            x: f64::floor(point.0.x - self.min_point.0.x),
-            y: f64::floor(point.0.y - self.min_point.0.y),
-        })
+    pub fn project_coord(&self, coord: Coord) -> Coord {
+        let point = mercator_to_tile(coord.x, coord.y, self.zoom_level);
+        Coord {
+            x: point.0.x - self.min_point.0.x,
+            y: point.0.y - self.min_point.0.y,
+        }
+    }
+
+    /// Projects a longitude/latitude geometry into unrounded tile extent coordinates
+    pub fn project_geometry(&self, geometry: &Geometry) -> Geometry {
+        map_coords(geometry, &|coord| self.project_coord(coord))
     }
 }
 
-pub struct GeometryCommandEncoder<'a> {
-    point_projection: &'a TileProjection,
+pub struct GeometryCommandEncoder {
     prev_point: EncoderPoint,
     pub data: Vec<u32>,
 }
 
 pub trait FromGeometry {
-    fn from_geometry_with_projection(
-        geom: &Geometry,
-        point_projection: &TileProjection,
-    ) -> Result<GeometryData, String>;
+    /// Encodes a geometry already projected into tile extent coordinates
+    fn from_geometry(geom: &Geometry) -> Result<GeometryData, String>;
 }
 
-impl<'a> GeometryCommandEncoder<'a> {
-    fn new(point_projection: &'a TileProjection) -> GeometryCommandEncoder<'a> {
+impl GeometryCommandEncoder {
+    fn new() -> GeometryCommandEncoder {
         GeometryCommandEncoder {
-            point_projection,
             prev_point: EncoderPoint { x: 0, y: 0 },
             data: vec![],
         }
@@ -79,13 +80,14 @@ impl<'a> GeometryCommandEncoder<'a> {
 
     fn push_points(&mut self, points: &[Point]) {
         for point in points.iter() {
-            let point = self.point_projection.project_point(point);
+            let point_x = point.0.x.floor() as i32;
+            let point_y = point.0.y.floor() as i32;
 
-            let x = point.0.x as i32 - self.prev_point.x;
-            let y = point.0.y as i32 - self.prev_point.y;
+            let x = point_x - self.prev_point.x;
+            let y = point_y - self.prev_point.y;
 
-            self.prev_point.x = point.0.x as i32;
-            self.prev_point.y = point.0.y as i32;
+            self.prev_point.x = point_x;
+            self.prev_point.y = point_y;
 
             self.data.push(((x << 1) ^ (x >> 31)) as u32);
             self.data.push(((y << 1) ^ (y >> 31)) as u32);
@@ -98,11 +100,8 @@ pub struct GeometryData {
     pub geometry: Vec<u32>,
 }
 
-impl<'a> FromGeometry for GeometryCommandEncoder<'a> {
-    fn from_geometry_with_projection(
-        geom: &Geometry,
-        point_projection: &TileProjection,
-    ) -> Result<GeometryData, String> {
+impl FromGeometry for GeometryCommandEncoder {
+    fn from_geometry(geom: &Geometry) -> Result<GeometryData, String> {
         let add_line = |encoder: &mut GeometryCommandEncoder, line_string: &LineString| {
             let points = line_string.points().collect::<Vec<Point>>();
             let point = points.first().unwrap();
@@ -131,7 +130,7 @@ impl<'a> FromGeometry for GeometryCommandEncoder<'a> {
 
         match geom {
             Geometry::Point(point) => {
-                let mut encoder = GeometryCommandEncoder::new(point_projection);
+                let mut encoder = GeometryCommandEncoder::new();
                 let points = [*point];
                 encoder.move_to(&points);
 
@@ -141,7 +140,7 @@ impl<'a> FromGeometry for GeometryCommandEncoder<'a> {
                 })
             }
             Geometry::LineString(line_string) => {
-                let mut encoder = GeometryCommandEncoder::new(point_projection);
+                let mut encoder = GeometryCommandEncoder::new();
                 let points = line_string.points().collect::<Vec<Point>>();
                 encoder.move_to(&[points[0]]);
                 encoder.line_to(&points[1..]);
@@ -152,7 +151,7 @@ impl<'a> FromGeometry for GeometryCommandEncoder<'a> {
                 })
             }
             Geometry::MultiLineString(multi_line_string) => {
-                let mut encoder = GeometryCommandEncoder::new(point_projection);
+                let mut encoder = GeometryCommandEncoder::new();
                 for line_string in multi_line_string.iter() {
                     let points = line_string.points().collect::<Vec<Point>>();
                     encoder.move_to(&[points[0]]);
@@ -164,7 +163,7 @@ impl<'a> FromGeometry for GeometryCommandEncoder<'a> {
                 })
             }
             Geometry::Polygon(polygon) => {
-                let mut encoder = GeometryCommandEncoder::new(point_projection);
+                let mut encoder = GeometryCommandEncoder::new();
                 add_polygon(&mut encoder, polygon);
 
                 Ok(GeometryData {
@@ -173,7 +172,7 @@ impl<'a> FromGeometry for GeometryCommandEncoder<'a> {
                 })
             }
             Geometry::MultiPolygon(multi_polygon) => {
-                let mut encoder = GeometryCommandEncoder::new(point_projection);
+                let mut encoder = GeometryCommandEncoder::new();
 
                 for polygon in multi_polygon.iter() {
                     add_polygon(&mut encoder, polygon);
@@ -185,7 +184,7 @@ impl<'a> FromGeometry for GeometryCommandEncoder<'a> {
                 })
             }
             Geometry::MultiPoint(multi_point) => {
-                let mut encoder = GeometryCommandEncoder::new(point_projection);
+                let mut encoder = GeometryCommandEncoder::new();
                 encoder.move_to(&multi_point.0);
                 Ok(GeometryData {
                     geometry_type: GeomType::POINT,
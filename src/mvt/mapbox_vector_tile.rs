@@ -1,21 +1,20 @@
 use crate::mvt::constants::DEFAULT_EXTENT;
-use crate::mvt::geometry_command_encoder::{FromGeometry, GeometryCommandEncoder, TileProjection};
+use crate::mvt::geometry_command_encoder::{FromGeometry, GeometryCommandEncoder};
 use crate::mvt::mvt_error::BinaryTileError;
+use crate::mvt::property_value::{Properties, PropertyValue};
 use crate::protos::vector_tile::tile::{Feature as ProtoFeature, Layer as ProtoLayer, Value};
 use crate::protos::vector_tile::Tile;
-use futures::stream::FuturesUnordered;
-use futures::StreamExt;
 use geo_types::Geometry;
 use protobuf::{CodedOutputStream, Message};
-use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Feature {
     pub geometry: Geometry,
-    pub properties: serde_json::Value,
+    pub properties: Properties,
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct Coordinates {
     pub x: u32,
     pub y: u32,
@@ -26,7 +25,6 @@ pub struct MapboxLayer {
     keys: Arc<RwLock<Vec<String>>>,
     values: Arc<RwLock<Vec<Value>>>,
     layer: ProtoLayer,
-    tile_projector: Arc<TileProjection>,
 }
 
 pub fn get_key_index(keys: Arc<RwLock<Vec<String>>>, key: &str) -> usize {
@@ -67,18 +65,16 @@ fn add_properties(
     keys: Arc<RwLock<Vec<String>>>,
     values: Arc<RwLock<Vec<Value>>>,
     feature: &mut ProtoFeature,
-    properties: &serde_json::Value,
+    properties: &Properties,
 ) {
-    if let serde_json::Value::Object(properties) = properties {
-        for (key, property) in properties {
-            add_property(
-                keys.clone(),
-                values.clone(),
-                feature,
-                key.as_str(),
-                property,
-            );
-        }
+    for (key, property) in properties {
+        add_property(
+            keys.clone(),
+            values.clone(),
+            feature,
+            key.as_str(),
+            property,
+        );
     }
 }
 
@@ -87,27 +83,31 @@ fn add_property(
     values: Arc<RwLock<Vec<Value>>>,
     feature: &mut ProtoFeature,
     key: &str,
-    property: &serde_json::Value,
+    property: &PropertyValue,
 ) {
     let mut value = Value::new();
     match property {
-        serde_json::Value::Null => {}
-        serde_json::Value::Bool(val) => {
+        PropertyValue::Bool(val) => {
             value.set_bool_value(*val);
         }
-        serde_json::Value::Number(val) => {
-            if val.is_i64() {
-                value.set_int_value(val.as_i64().unwrap());
-            } else if val.is_f64() {
-                value.set_double_value(val.as_f64().unwrap());
-            } else if val.is_u64() {
-                value.set_uint_value(val.as_u64().unwrap());
-            }
+        PropertyValue::Int(val) => {
+            value.set_int_value(*val);
+        }
+        PropertyValue::SInt(val) => {
+            value.set_sint_value(*val);
         }
-        serde_json::Value::String(val) => {
+        PropertyValue::UInt(val) => {
+            value.set_uint_value(*val);
+        }
+        PropertyValue::Float(val) => {
+            value.set_float_value(*val);
+        }
+        PropertyValue::Double(val) => {
+            value.set_double_value(*val);
+        }
+        PropertyValue::String(val) => {
             value.set_string_value(val.to_string());
         }
-        _ => {}
     }
 
     let key_index = get_key_index(keys, key);
@@ -116,8 +116,7 @@ fn add_property(
     feature.tags.push(value_index as u32);
 }
 
-pub async fn add_feature(
-    tile_projector: Arc<TileProjection>,
+pub fn encode_feature(
     keys: Arc<RwLock<Vec<String>>>,
     values: Arc<RwLock<Vec<Value>>>,
     feature: &Feature,
@@ -128,8 +127,7 @@ pub async fn add_feature(
         return None;
     }
 
-    let result =
-        GeometryCommandEncoder::from_geometry_with_projection(&feature.geometry, &tile_projector);
+    let result = GeometryCommandEncoder::from_geometry(&feature.geometry);
 
     match result {
         Ok(geometry_data) => {
@@ -151,48 +149,39 @@ pub async fn add_feature(
 }
 
 impl MapboxLayer {
-    pub fn new(name: String, tile_projector: TileProjection) -> MapboxLayer {
+    pub fn new(name: String) -> MapboxLayer {
         let mut layer = ProtoLayer::new();
         layer.name = Some(name);
         layer.version = Some(1);
         layer.extent = Some(DEFAULT_EXTENT);
         Self {
             layer,
-            tile_projector: Arc::new(tile_projector),
             keys: Arc::new(RwLock::new(vec![])),
             values: Arc::new(RwLock::new(vec![])),
         }
     }
 
-    pub async fn add_features(&mut self, features: &[Feature]) {
-        let mut proto_features: Vec<ProtoFeature> = Vec::new();
-
-        let mut tasks = FuturesUnordered::new();
+    /// Encodes a single feature into the layer so rows can be added as they arrive
+    pub fn push_feature(&mut self, feature: &Feature) {
+        let keys = self.keys.clone();
+        let values = self.values.clone();
 
-        for feature in features.iter() {
-            let tile_projector = self.tile_projector.clone();
-            let keys = self.keys.clone();
-            let values = self.values.clone();
-
-            tasks.push(add_feature(tile_projector, keys, values, feature));
-        }
-
-        while let Some(Some(proto_feature)) = tasks.next().await {
-            proto_features.push(proto_feature);
+        if let Some(proto_feature) = encode_feature(keys, values, feature) {
+            self.layer.features.push(proto_feature);
         }
+    }
 
-        self.layer.features = proto_features;
+    pub fn get_layer(&self) -> ProtoLayer {
+        let mut layer = self.layer.clone();
 
         let keys = self.keys.read().unwrap();
         let values = self.values.read().unwrap();
-        self.layer.keys = keys.clone();
-        self.layer.values = values.clone();
+        layer.keys = keys.clone();
+        layer.values = values.clone();
         drop(keys);
         drop(values);
-    }
 
-    pub fn get_layer(&self) -> ProtoLayer {
-        self.layer.clone()
+        layer
     }
 }
 
@@ -201,18 +190,9 @@ pub struct MapboxVectorTile {
 }
 
 impl MapboxVectorTile {
-    pub async fn new(
-        coordinates: &Coordinates,
-        layers: &HashMap<String, Vec<Feature>>,
-    ) -> MapboxVectorTile {
+    pub fn from_layers(layers: &[MapboxLayer]) -> MapboxVectorTile {
         let mut tile = Tile::new();
-
-        for (name, features) in layers.iter() {
-            let mut mapbox_layer = MapboxLayer::new(
-                name.to_string(),
-                TileProjection::new(coordinates, DEFAULT_EXTENT),
-            );
-            mapbox_layer.add_features(features).await;
+        for mapbox_layer in layers.iter() {
             tile.layers.push(mapbox_layer.get_layer());
         }
 
@@ -1,4 +1,5 @@
 pub mod constants;
-mod geometry_command_encoder;
+pub mod geometry_command_encoder;
 pub mod mapbox_vector_tile;
 mod mvt_error;
+pub mod property_value;
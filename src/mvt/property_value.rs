@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+pub type Properties = BTreeMap<String, PropertyValue>;
+
+/// Property columns of a columnar layer: the types declared from the query's columns, and types
+/// widened from the values of properties the query doesn't declare, such as cluster counts
+#[derive(Clone, Debug, Default)]
+pub struct PropertyColumns {
+    types: BTreeMap<String, PropertyType>,
+    declared: BTreeSet<String>,
+}
+
+impl PropertyColumns {
+    pub fn declare(&mut self, columns: &BTreeMap<String, PropertyType>) {
+        for (name, property_type) in columns.iter() {
+            self.types.insert(name.clone(), *property_type);
+            self.declared.insert(name.clone());
+        }
+    }
+
+    /// Adds the property types of a feature to the undeclared column types seen so far
+    pub fn add(&mut self, properties: &Properties) {
+        for (key, value) in properties.iter() {
+            if self.declared.contains(key) {
+                continue;
+            }
+            let property_type = value.property_type();
+            self.types
+                .entry(key.clone())
+                .and_modify(|column_type| *column_type = column_type.merge(property_type))
+                .or_insert(property_type);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &PropertyType)> {
+        self.types.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+}
+
+/// A feature property typed the way it is written into an MVT layer value
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    Float(f32),
+    Double(f64),
+    Int(i64),
+    UInt(u64),
+    SInt(i64),
+    Bool(bool),
+}
+
+/// Type of a columnar property, wide enough for the values of every feature in the column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PropertyType {
+    Bool,
+    Int,
+    UInt,
+    Float,
+    Double,
+    String,
+}
+
+impl PropertyType {
+    /// Widens the type so both it and `other` fit, falling back to strings. Signed and unsigned
+    /// integers only fit together in doubles.
+    pub fn merge(self, other: PropertyType) -> Self {
+        use PropertyType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Float | Double | Int | UInt, Float | Double | Int | UInt) => Double,
+            _ => String,
+        }
+    }
+
+    /// Whether a value can be written into a column of the type, other values are left null
+    pub fn holds(self, value: &PropertyValue) -> bool {
+        match self {
+            PropertyType::Bool => matches!(value, PropertyValue::Bool(_)),
+            PropertyType::Int => value.as_i64().is_some(),
+            PropertyType::UInt => value.as_u64().is_some(),
+            PropertyType::Float | PropertyType::Double => value.as_f64().is_some(),
+            PropertyType::String => true,
+        }
+    }
+}
+
+impl PropertyValue {
+    pub fn property_type(&self) -> PropertyType {
+        match self {
+            PropertyValue::Bool(_) => PropertyType::Bool,
+            PropertyValue::Int(_) | PropertyValue::SInt(_) => PropertyType::Int,
+            PropertyValue::UInt(_) => PropertyType::UInt,
+            PropertyValue::Float(_) => PropertyType::Float,
+            PropertyValue::Double(_) => PropertyType::Double,
+            PropertyValue::String(_) => PropertyType::String,
+        }
+    }
+
+    /// Integers in the range of `i64`
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            PropertyValue::Int(value) | PropertyValue::SInt(value) => Some(*value),
+            PropertyValue::UInt(value) => i64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    /// Integers in the range of `u64`
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            PropertyValue::Int(value) | PropertyValue::SInt(value) => u64::try_from(*value).ok(),
+            PropertyValue::UInt(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PropertyValue::Float(value) => Some(*value as f64),
+            PropertyValue::Double(value) => Some(*value),
+            PropertyValue::Int(value) | PropertyValue::SInt(value) => Some(*value as f64),
+            PropertyValue::UInt(value) => Some(*value as f64),
+            PropertyValue::String(value) => value.parse().ok(),
+            PropertyValue::Bool(_) => None,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            PropertyValue::String(value) => serde_json::Value::String(value.clone()),
+            PropertyValue::Float(value) => serde_json::Number::from_f64(*value as f64)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            PropertyValue::Double(value) => serde_json::Number::from_f64(*value)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            PropertyValue::Int(value) | PropertyValue::SInt(value) => {
+                serde_json::Value::Number((*value).into())
+            }
+            PropertyValue::UInt(value) => serde_json::Value::Number((*value).into()),
+            PropertyValue::Bool(value) => serde_json::Value::Bool(*value),
+        }
+    }
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::String(value) => write!(f, "{}", value),
+            PropertyValue::Float(value) => write!(f, "{}", value),
+            PropertyValue::Double(value) => write!(f, "{}", value),
+            PropertyValue::Int(value) | PropertyValue::SInt(value) => write!(f, "{}", value),
+            PropertyValue::UInt(value) => write!(f, "{}", value),
+            PropertyValue::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_and_unsigned_integers_merge_into_doubles() {
+        assert_eq!(
+            PropertyType::Int.merge(PropertyType::UInt),
+            PropertyType::Double
+        );
+        assert_eq!(
+            PropertyType::UInt.merge(PropertyType::Int),
+            PropertyType::Double
+        );
+        assert_eq!(
+            PropertyType::Int.merge(PropertyType::Int),
+            PropertyType::Int
+        );
+        assert_eq!(
+            PropertyType::Float.merge(PropertyType::Int),
+            PropertyType::Double
+        );
+        assert_eq!(
+            PropertyType::Bool.merge(PropertyType::Int),
+            PropertyType::String
+        );
+    }
+
+    #[test]
+    fn integers_convert_only_within_range() {
+        assert_eq!(PropertyValue::UInt(u64::MAX).as_i64(), None);
+        assert_eq!(PropertyValue::UInt(7).as_i64(), Some(7));
+        assert_eq!(PropertyValue::Int(-1).as_u64(), None);
+        assert_eq!(PropertyValue::SInt(7).as_u64(), Some(7));
+        assert!(!PropertyType::Int.holds(&PropertyValue::UInt(u64::MAX)));
+        assert!(!PropertyType::Bool.holds(&PropertyValue::Int(1)));
+        assert!(PropertyType::String.holds(&PropertyValue::Bool(true)));
+    }
+
+    #[test]
+    fn declared_columns_keep_their_type() {
+        let mut columns = PropertyColumns::default();
+        columns.declare(&BTreeMap::from([("id".to_string(), PropertyType::Int)]));
+        columns.add(&Properties::from([
+            ("id".to_string(), PropertyValue::String("a".to_string())),
+            ("point_count".to_string(), PropertyValue::UInt(3)),
+        ]));
+        columns.add(&Properties::from([(
+            "point_count".to_string(),
+            PropertyValue::Int(-3),
+        )]));
+
+        let types: Vec<(&String, &PropertyType)> = columns.iter().collect();
+        assert_eq!(
+            types,
+            vec![
+                (&"id".to_string(), &PropertyType::Int),
+                (&"point_count".to_string(), &PropertyType::Double),
+            ]
+        );
+    }
+}
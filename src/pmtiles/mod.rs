@@ -0,0 +1,5 @@
+mod pmtiles_directory;
+mod pmtiles_header;
+pub mod pmtiles_reader;
+pub mod pmtiles_writer;
+mod tile_id;
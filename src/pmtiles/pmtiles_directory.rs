@@ -0,0 +1,242 @@
+use crate::pmtiles::pmtiles_header::{COMPRESSION_GZIP, COMPRESSION_NONE};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, Read, Write};
+
+/// A directory entry. With a run length of 0 it points at a leaf directory, otherwise at tile
+/// data shared by `run_length` consecutive tile ids.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+    pub tile_id: u64,
+    pub offset: u64,
+    pub length: u32,
+    pub run_length: u32,
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], position: &mut usize) -> io::Result<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data
+            .get(*position)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated directory"))?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+        if shift > 63 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "varint too long",
+            ));
+        }
+    }
+}
+
+pub fn compress(data: &[u8], compression: u8) -> io::Result<Vec<u8>> {
+    match compression {
+        COMPRESSION_NONE => Ok(data.to_vec()),
+        COMPRESSION_GZIP => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unsupported compression",
+        )),
+    }
+}
+
+pub fn decompress(data: &[u8], compression: u8) -> io::Result<Vec<u8>> {
+    match compression {
+        COMPRESSION_NONE => Ok(data.to_vec()),
+        COMPRESSION_GZIP => {
+            let mut decoded = vec![];
+            GzDecoder::new(data).read_to_end(&mut decoded)?;
+            Ok(decoded)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unsupported compression",
+        )),
+    }
+}
+
+/// Column wise varint encoding of the entries: delta tile ids, run lengths, lengths and offsets,
+/// where an offset directly following the previous entry's data is written as 0
+pub fn serialize_directory(entries: &[Entry]) -> Vec<u8> {
+    let mut data = vec![];
+    write_varint(&mut data, entries.len() as u64);
+
+    let mut last_id = 0;
+    for entry in entries {
+        write_varint(&mut data, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        write_varint(&mut data, entry.run_length as u64);
+    }
+    for entry in entries {
+        write_varint(&mut data, entry.length as u64);
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        let follows_previous = index > 0
+            && entry.offset == entries[index - 1].offset + entries[index - 1].length as u64;
+        write_varint(
+            &mut data,
+            if follows_previous {
+                0
+            } else {
+                entry.offset + 1
+            },
+        );
+    }
+    data
+}
+
+pub fn deserialize_directory(data: &[u8]) -> io::Result<Vec<Entry>> {
+    let mut position = 0;
+    let count = read_varint(data, &mut position)? as usize;
+    let mut entries = vec![
+        Entry {
+            tile_id: 0,
+            offset: 0,
+            length: 0,
+            run_length: 0,
+        };
+        count.min(data.len())
+    ];
+    if entries.len() != count {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid directory",
+        ));
+    }
+
+    let mut last_id = 0;
+    for entry in entries.iter_mut() {
+        last_id += read_varint(data, &mut position)?;
+        entry.tile_id = last_id;
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = read_varint(data, &mut position)? as u32;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = read_varint(data, &mut position)? as u32;
+    }
+    for index in 0..count {
+        let value = read_varint(data, &mut position)?;
+        entries[index].offset = if value == 0 && index > 0 {
+            entries[index - 1].offset + entries[index - 1].length as u64
+        } else {
+            value.saturating_sub(1)
+        };
+    }
+    Ok(entries)
+}
+
+/// Finds the entry holding a tile id: the last entry starting at or before it, if it is a leaf
+/// directory or its run covers the id
+pub fn find_entry(entries: &[Entry], tile_id: u64) -> Option<Entry> {
+    let index = entries
+        .partition_point(|entry| entry.tile_id <= tile_id)
+        .checked_sub(1)?;
+    let entry = entries[index];
+    if entry.run_length == 0 || tile_id < entry.tile_id + entry.run_length as u64 {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(tile_id: u64, offset: u64, length: u32, run_length: u32) -> Entry {
+        Entry {
+            tile_id,
+            offset,
+            length,
+            run_length,
+        }
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut data = vec![];
+            write_varint(&mut data, value);
+            let mut position = 0;
+            assert_eq!(read_varint(&data, &mut position).unwrap(), value);
+            assert_eq!(position, data.len());
+        }
+
+        let mut data = vec![];
+        write_varint(&mut data, 300);
+        assert_eq!(data, [0xac, 0x02]);
+    }
+
+    #[test]
+    fn rejects_truncated_and_overlong_varints() {
+        assert!(read_varint(&[0x80], &mut 0).is_err());
+        assert!(read_varint(&[0xff; 11], &mut 0).is_err());
+    }
+
+    #[test]
+    fn directories_round_trip() {
+        let entries = vec![
+            entry(0, 0, 100, 1),
+            entry(1, 100, 50, 3),
+            entry(5, 0, 100, 1),
+            entry(40, 150, 20, 0),
+        ];
+        let data = serialize_directory(&entries);
+        assert_eq!(deserialize_directory(&data).unwrap(), entries);
+
+        let compressed = compress(&data, COMPRESSION_GZIP).unwrap();
+        let decompressed = decompress(&compressed, COMPRESSION_GZIP).unwrap();
+        assert_eq!(deserialize_directory(&decompressed).unwrap(), entries);
+    }
+
+    #[test]
+    fn writes_contiguous_offsets_as_zero() {
+        let data = serialize_directory(&[entry(0, 0, 10, 1), entry(1, 10, 10, 1)]);
+        assert_eq!(data, [2, 0, 1, 1, 1, 10, 10, 1, 0]);
+    }
+
+    #[test]
+    fn rejects_directories_longer_than_their_data() {
+        assert!(deserialize_directory(&[]).is_err());
+        assert!(deserialize_directory(&[100, 0]).is_err());
+        assert!(deserialize_directory(&[1, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn finds_the_entry_covering_a_tile() {
+        let entries = [
+            entry(0, 0, 10, 1),
+            entry(4, 10, 10, 3),
+            entry(10, 20, 10, 0),
+        ];
+
+        assert_eq!(find_entry(&entries, 0), Some(entries[0]));
+        assert_eq!(find_entry(&entries, 1), None);
+        assert_eq!(find_entry(&entries, 6), Some(entries[1]));
+        assert_eq!(find_entry(&entries, 7), None);
+        assert_eq!(find_entry(&entries, 1000), Some(entries[2]));
+        assert_eq!(find_entry(&entries[1..], 0), None);
+    }
+}
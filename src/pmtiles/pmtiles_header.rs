@@ -0,0 +1,176 @@
+pub const HEADER_SIZE: usize = 127;
+
+const MAGIC: &[u8; 7] = b"PMTiles";
+const VERSION: u8 = 3;
+
+pub const COMPRESSION_NONE: u8 = 1;
+pub const COMPRESSION_GZIP: u8 = 2;
+
+pub const TILE_TYPE_MVT: u8 = 1;
+
+/// Fixed size header at the start of a PMTiles v3 archive
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PmtilesHeader {
+    pub root_directory_offset: u64,
+    pub root_directory_length: u64,
+    pub metadata_offset: u64,
+    pub metadata_length: u64,
+    pub leaf_directories_offset: u64,
+    pub leaf_directories_length: u64,
+    pub tile_data_offset: u64,
+    pub tile_data_length: u64,
+    pub addressed_tiles_count: u64,
+    pub tile_entries_count: u64,
+    pub tile_contents_count: u64,
+    pub clustered: bool,
+    pub internal_compression: u8,
+    pub tile_compression: u8,
+    pub tile_type: u8,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    pub min_lon_e7: i32,
+    pub min_lat_e7: i32,
+    pub max_lon_e7: i32,
+    pub max_lat_e7: i32,
+    pub center_zoom: u8,
+    pub center_lon_e7: i32,
+    pub center_lat_e7: i32,
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_i32(data: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+impl PmtilesHeader {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_SIZE);
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+        for value in [
+            self.root_directory_offset,
+            self.root_directory_length,
+            self.metadata_offset,
+            self.metadata_length,
+            self.leaf_directories_offset,
+            self.leaf_directories_length,
+            self.tile_data_offset,
+            self.tile_data_length,
+            self.addressed_tiles_count,
+            self.tile_entries_count,
+            self.tile_contents_count,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&[
+            self.clustered as u8,
+            self.internal_compression,
+            self.tile_compression,
+            self.tile_type,
+            self.min_zoom,
+            self.max_zoom,
+        ]);
+        for value in [
+            self.min_lon_e7,
+            self.min_lat_e7,
+            self.max_lon_e7,
+            self.max_lat_e7,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.push(self.center_zoom);
+        data.extend_from_slice(&self.center_lon_e7.to_le_bytes());
+        data.extend_from_slice(&self.center_lat_e7.to_le_bytes());
+        data
+    }
+
+    /// Parses a header, `None` when the data is not a PMTiles v3 archive
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < HEADER_SIZE || &data[0..7] != MAGIC || data[7] != VERSION {
+            return None;
+        }
+
+        Some(Self {
+            root_directory_offset: read_u64(data, 8),
+            root_directory_length: read_u64(data, 16),
+            metadata_offset: read_u64(data, 24),
+            metadata_length: read_u64(data, 32),
+            leaf_directories_offset: read_u64(data, 40),
+            leaf_directories_length: read_u64(data, 48),
+            tile_data_offset: read_u64(data, 56),
+            tile_data_length: read_u64(data, 64),
+            addressed_tiles_count: read_u64(data, 72),
+            tile_entries_count: read_u64(data, 80),
+            tile_contents_count: read_u64(data, 88),
+            clustered: data[96] == 1,
+            internal_compression: data[97],
+            tile_compression: data[98],
+            tile_type: data[99],
+            min_zoom: data[100],
+            max_zoom: data[101],
+            min_lon_e7: read_i32(data, 102),
+            min_lat_e7: read_i32(data, 106),
+            max_lon_e7: read_i32(data, 110),
+            max_lat_e7: read_i32(data, 114),
+            center_zoom: data[118],
+            center_lon_e7: read_i32(data, 119),
+            center_lat_e7: read_i32(data, 123),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_round_trip() {
+        let header = PmtilesHeader {
+            root_directory_offset: HEADER_SIZE as u64,
+            root_directory_length: 10,
+            metadata_offset: 137,
+            metadata_length: 20,
+            tile_data_offset: 157,
+            tile_data_length: u64::MAX,
+            addressed_tiles_count: 3,
+            tile_entries_count: 2,
+            tile_contents_count: 1,
+            clustered: true,
+            internal_compression: COMPRESSION_GZIP,
+            tile_compression: COMPRESSION_NONE,
+            tile_type: TILE_TYPE_MVT,
+            min_zoom: 0,
+            max_zoom: 14,
+            min_lon_e7: -1_800_000_000,
+            min_lat_e7: -850_511_287,
+            max_lon_e7: 1_800_000_000,
+            max_lat_e7: 850_511_287,
+            center_zoom: 7,
+            center_lon_e7: -1,
+            center_lat_e7: 1,
+            ..Default::default()
+        };
+
+        let data = header.to_bytes();
+        assert_eq!(data.len(), HEADER_SIZE);
+        assert_eq!(&data[..8], b"PMTiles\x03");
+        assert_eq!(PmtilesHeader::from_bytes(&data), Some(header));
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        let data = PmtilesHeader::default().to_bytes();
+        assert!(PmtilesHeader::from_bytes(&data[..HEADER_SIZE - 1]).is_none());
+
+        let mut version_2 = data.clone();
+        version_2[7] = 2;
+        assert!(PmtilesHeader::from_bytes(&version_2).is_none());
+
+        let mut other = data;
+        other[0] = b'X';
+        assert!(PmtilesHeader::from_bytes(&other).is_none());
+    }
+}
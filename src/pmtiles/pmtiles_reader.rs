@@ -0,0 +1,159 @@
+use crate::pmtiles::pmtiles_directory::{decompress, deserialize_directory, find_entry, Entry};
+use crate::pmtiles::pmtiles_header::{PmtilesHeader, HEADER_SIZE, TILE_TYPE_MVT};
+use crate::pmtiles::tile_id::zxy_to_tile_id;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// Root plus up to three levels of leaf directories
+const MAX_DIRECTORY_DEPTH: usize = 4;
+
+/// Leaf directories kept in memory before the cache is cleared
+const MAX_CACHED_LEAVES: usize = 64;
+
+/// Reads tiles from a local PMTiles v3 archive. The header and root directory are read once,
+/// leaf directories on demand.
+pub struct PmtilesReader {
+    path: PathBuf,
+    header: PmtilesHeader,
+    metadata: Value,
+    root: Arc<Vec<Entry>>,
+    leaves: Mutex<HashMap<u64, Arc<Vec<Entry>>>>,
+}
+
+async fn read_range(path: &Path, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut data = vec![0; length as usize];
+    file.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+impl PmtilesReader {
+    pub async fn open(path: &Path) -> io::Result<Self> {
+        let header = read_range(path, 0, HEADER_SIZE as u64).await?;
+        let header = PmtilesHeader::from_bytes(&header).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "not a PMTiles v3 archive")
+        })?;
+
+        let root = read_range(
+            path,
+            header.root_directory_offset,
+            header.root_directory_length,
+        )
+        .await?;
+        let root = deserialize_directory(&decompress(&root, header.internal_compression)?)?;
+
+        let metadata = read_range(path, header.metadata_offset, header.metadata_length).await?;
+        let metadata = decompress(&metadata, header.internal_compression)?;
+        let metadata = serde_json::from_slice(&metadata).unwrap_or(Value::Null);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            header,
+            metadata,
+            root: Arc::new(root),
+            leaves: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_mvt(&self) -> bool {
+        self.header.tile_type == TILE_TYPE_MVT
+    }
+
+    /// TileJSON from the header's bounds and zooms and the archive's metadata, without `tiles`
+    pub fn tile_json(&self) -> Value {
+        let header = &self.header;
+        let degrees = |e7: i32| e7 as f64 / 10_000_000.0;
+        let mut tile_json = json!({
+            "tilejson": "3.0.0",
+            "bounds": [
+                degrees(header.min_lon_e7),
+                degrees(header.min_lat_e7),
+                degrees(header.max_lon_e7),
+                degrees(header.max_lat_e7),
+            ],
+            "center": [
+                degrees(header.center_lon_e7),
+                degrees(header.center_lat_e7),
+                header.center_zoom,
+            ],
+            "minzoom": header.min_zoom,
+            "maxzoom": header.max_zoom,
+        });
+        for key in [
+            "name",
+            "description",
+            "attribution",
+            "version",
+            "vector_layers",
+        ] {
+            if let Some(value) = self.metadata.get(key) {
+                tile_json[key] = value.clone();
+            }
+        }
+        tile_json
+    }
+
+    async fn leaf(&self, entry: &Entry) -> io::Result<Arc<Vec<Entry>>> {
+        if let Some(leaf) = self.leaves.lock().unwrap().get(&entry.offset) {
+            return Ok(leaf.clone());
+        }
+
+        let data = read_range(
+            &self.path,
+            self.header.leaf_directories_offset + entry.offset,
+            entry.length as u64,
+        )
+        .await?;
+        let leaf = Arc::new(deserialize_directory(&decompress(
+            &data,
+            self.header.internal_compression,
+        )?)?);
+
+        let mut leaves = self.leaves.lock().unwrap();
+        if leaves.len() >= MAX_CACHED_LEAVES {
+            leaves.clear();
+        }
+        leaves.insert(entry.offset, leaf.clone());
+        Ok(leaf)
+    }
+
+    /// Returns the decompressed tile, `None` when the archive does not hold it
+    pub async fn get_tile(&self, x: u32, y: u32, z: u32) -> io::Result<Option<Vec<u8>>> {
+        if z < self.header.min_zoom as u32 || z > self.header.max_zoom as u32 || z > 31 {
+            return Ok(None);
+        }
+        if x >= 1 << z || y >= 1 << z {
+            return Ok(None);
+        }
+
+        let tile_id = zxy_to_tile_id(z, x, y);
+        let mut directory = self.root.clone();
+        for _ in 0..MAX_DIRECTORY_DEPTH {
+            let Some(entry) = find_entry(&directory, tile_id) else {
+                return Ok(None);
+            };
+
+            if entry.run_length > 0 {
+                let data = read_range(
+                    &self.path,
+                    self.header.tile_data_offset + entry.offset,
+                    entry.length as u64,
+                )
+                .await?;
+                return decompress(&data, self.header.tile_compression).map(Some);
+            }
+            directory = self.leaf(&entry).await?;
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,237 @@
+use crate::pmtiles::pmtiles_directory::{compress, serialize_directory, Entry};
+use crate::pmtiles::pmtiles_header::{PmtilesHeader, COMPRESSION_GZIP, HEADER_SIZE, TILE_TYPE_MVT};
+use crate::pmtiles::tile_id::zxy_to_tile_id;
+use crate::tiling::tile_pyramid::{PyramidError, PyramidTile, TileArchiveWriter, TilePyramid};
+use async_trait::async_trait;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Header and root directory must fit in the first 16 KiB so readers can fetch both at once
+const MAX_ROOT_SIZE: usize = 16_384 - HEADER_SIZE;
+
+const INITIAL_LEAF_SIZE: usize = 4096;
+
+fn to_e7(degrees: f64) -> i32 {
+    (degrees * 10_000_000.0) as i32
+}
+
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// Fits all entries in the root directory, or splits them into leaf directories, doubling the
+/// leaf size until the root fits
+fn build_directories(entries: &[Entry]) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    let root = compress(&serialize_directory(entries), COMPRESSION_GZIP)?;
+    if root.len() <= MAX_ROOT_SIZE {
+        return Ok((root, vec![]));
+    }
+
+    let mut leaf_size = INITIAL_LEAF_SIZE;
+    loop {
+        let mut leaves = vec![];
+        let mut root_entries = vec![];
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = compress(&serialize_directory(chunk), COMPRESSION_GZIP)?;
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend(leaf);
+        }
+
+        let root = compress(&serialize_directory(&root_entries), COMPRESSION_GZIP)?;
+        if root.len() <= MAX_ROOT_SIZE {
+            return Ok((root, leaves));
+        }
+        leaf_size *= 2;
+    }
+}
+
+struct TileLocation {
+    tile_id: u64,
+    offset: u64,
+    length: u32,
+}
+
+/// Writes a tile pyramid into a clustered PMTiles v3 archive. Tiles are spooled to a temporary
+/// file with identical contents stored once, then written in tile id order when finished.
+pub struct PmtilesWriter {
+    path: PathBuf,
+    spool_path: PathBuf,
+    spool: BufWriter<File>,
+    spool_length: u64,
+    contents: HashMap<[u8; 32], (u64, u32)>,
+    tiles: Vec<TileLocation>,
+    header: PmtilesHeader,
+    metadata: Vec<u8>,
+}
+
+impl PmtilesWriter {
+    pub fn create(path: &Path, pyramid: &TilePyramid) -> io::Result<Self> {
+        let spool_path = with_extension(path, ".tiles.tmp");
+        let spool = BufWriter::new(File::create(&spool_path)?);
+
+        let [min_x, min_y, max_x, max_y] = pyramid.bbox;
+        let (center_x, center_y, center_zoom) = pyramid.center();
+        let header = PmtilesHeader {
+            internal_compression: COMPRESSION_GZIP,
+            tile_compression: COMPRESSION_GZIP,
+            tile_type: TILE_TYPE_MVT,
+            clustered: true,
+            min_zoom: pyramid.min_zoom as u8,
+            max_zoom: pyramid.max_zoom as u8,
+            min_lon_e7: to_e7(min_x),
+            min_lat_e7: to_e7(min_y),
+            max_lon_e7: to_e7(max_x),
+            max_lat_e7: to_e7(max_y),
+            center_zoom: center_zoom as u8,
+            center_lon_e7: to_e7(center_x),
+            center_lat_e7: to_e7(center_y),
+            ..Default::default()
+        };
+        let metadata = json!({
+            "name": pyramid.layer,
+            "format": "pbf",
+            "type": "overlay",
+            "vector_layers": pyramid.vector_layers(),
+        });
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            spool_path,
+            spool,
+            spool_length: 0,
+            contents: HashMap::new(),
+            tiles: vec![],
+            header,
+            metadata: compress(metadata.to_string().as_bytes(), COMPRESSION_GZIP)?,
+        })
+    }
+
+    fn add_tile(&mut self, tile_id: u64, data: &[u8]) -> io::Result<()> {
+        let hash: [u8; 32] = Sha256::digest(data).into();
+        let (offset, length) = match self.contents.get(&hash) {
+            Some(location) => *location,
+            None => {
+                self.spool.write_all(data)?;
+                let location = (self.spool_length, data.len() as u32);
+                self.spool_length += data.len() as u64;
+                self.contents.insert(hash, location);
+                location
+            }
+        };
+        self.tiles.push(TileLocation {
+            tile_id,
+            offset,
+            length,
+        });
+        Ok(())
+    }
+
+    /// Copies the spooled contents into tile id order, merging consecutive tiles with the same
+    /// contents into runs
+    fn cluster(&mut self, data_path: &Path) -> io::Result<Vec<Entry>> {
+        self.tiles.sort_by_key(|tile| tile.tile_id);
+        self.spool.flush()?;
+
+        let mut spool = File::open(&self.spool_path)?;
+        let mut data = BufWriter::new(File::create(data_path)?);
+        let mut data_length = 0u64;
+        let mut clustered_offsets: HashMap<u64, u64> = HashMap::new();
+        let mut entries: Vec<Entry> = vec![];
+        let mut buffer = vec![];
+
+        for tile in self.tiles.iter() {
+            let offset = match clustered_offsets.get(&tile.offset) {
+                Some(offset) => *offset,
+                None => {
+                    buffer.resize(tile.length as usize, 0);
+                    spool.seek(SeekFrom::Start(tile.offset))?;
+                    spool.read_exact(&mut buffer)?;
+                    data.write_all(&buffer)?;
+                    clustered_offsets.insert(tile.offset, data_length);
+                    data_length += tile.length as u64;
+                    data_length - tile.length as u64
+                }
+            };
+
+            if let Some(last) = entries.last_mut() {
+                if last.offset == offset && last.tile_id + last.run_length as u64 == tile.tile_id {
+                    last.run_length += 1;
+                    continue;
+                }
+            }
+            entries.push(Entry {
+                tile_id: tile.tile_id,
+                offset,
+                length: tile.length,
+                run_length: 1,
+            });
+        }
+        data.flush()?;
+
+        self.header.tile_data_length = data_length;
+        self.header.addressed_tiles_count = self.tiles.len() as u64;
+        self.header.tile_entries_count = entries.len() as u64;
+        self.header.tile_contents_count = self.contents.len() as u64;
+        Ok(entries)
+    }
+
+    fn write_archive(mut self) -> io::Result<()> {
+        let data_path = with_extension(&self.path, ".data.tmp");
+        let entries = self.cluster(&data_path)?;
+        let (root, leaves) = build_directories(&entries)?;
+
+        let header = &mut self.header;
+        header.root_directory_offset = HEADER_SIZE as u64;
+        header.root_directory_length = root.len() as u64;
+        header.metadata_offset = header.root_directory_offset + header.root_directory_length;
+        header.metadata_length = self.metadata.len() as u64;
+        header.leaf_directories_offset = header.metadata_offset + header.metadata_length;
+        header.leaf_directories_length = leaves.len() as u64;
+        header.tile_data_offset = header.leaf_directories_offset + header.leaf_directories_length;
+
+        let mut archive = BufWriter::new(File::create(&self.path)?);
+        archive.write_all(&header.to_bytes())?;
+        archive.write_all(&root)?;
+        archive.write_all(&self.metadata)?;
+        archive.write_all(&leaves)?;
+        io::copy(&mut File::open(&data_path)?, &mut archive)?;
+        archive.flush()?;
+
+        fs::remove_file(&data_path)?;
+        fs::remove_file(&self.spool_path)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TileArchiveWriter for PmtilesWriter {
+    /// Archives are written in one go, so an export always starts from scratch
+    async fn rendered_tiles(&self, _z: u32) -> Result<HashSet<(u32, u32)>, PyramidError> {
+        Ok(HashSet::new())
+    }
+
+    async fn write_tiles(&mut self, tiles: &[PyramidTile]) -> Result<(), PyramidError> {
+        for tile in tiles {
+            if let Some(data) = &tile.data {
+                self.add_tile(zxy_to_tile_id(tile.z, tile.x, tile.y), data)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn finish(self) -> Result<(), PyramidError> {
+        self.write_archive()?;
+        Ok(())
+    }
+}
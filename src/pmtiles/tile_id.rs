@@ -0,0 +1,64 @@
+fn rotate(n: u32, x: u32, y: u32, rx: u32, ry: u32) -> (u32, u32) {
+    if ry == 0 {
+        if rx != 0 {
+            return (
+                n.wrapping_sub(1).wrapping_sub(y),
+                n.wrapping_sub(1).wrapping_sub(x),
+            );
+        }
+        return (y, x);
+    }
+    (x, y)
+}
+
+/// PMTiles tile id: tiles of lower zooms first, then the position along a Hilbert curve
+pub fn zxy_to_tile_id(z: u32, x: u32, y: u32) -> u64 {
+    let mut id = ((1u64 << (z * 2)) - 1) / 3;
+    if z == 0 {
+        return id;
+    }
+
+    let (mut x, mut y) = (x, y);
+    let mut s = 1u32 << (z - 1);
+    while s > 0 {
+        let rx = s & x;
+        let ry = s & y;
+        id += ((3 * rx as u64) ^ ry as u64) * s as u64;
+        (x, y) = rotate(s, x, y, rx, ry);
+        s >>= 1;
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn follows_the_hilbert_curve() {
+        assert_eq!(zxy_to_tile_id(0, 0, 0), 0);
+        assert_eq!(zxy_to_tile_id(1, 0, 0), 1);
+        assert_eq!(zxy_to_tile_id(1, 0, 1), 2);
+        assert_eq!(zxy_to_tile_id(1, 1, 1), 3);
+        assert_eq!(zxy_to_tile_id(1, 1, 0), 4);
+        assert_eq!(zxy_to_tile_id(2, 0, 0), 5);
+        assert_eq!(zxy_to_tile_id(3, 0, 0), 21);
+        assert_eq!(zxy_to_tile_id(12, 3423, 1763), 19078479);
+    }
+
+    #[test]
+    fn ids_of_a_zoom_are_consecutive() {
+        let ids: BTreeSet<u64> = (0..8)
+            .flat_map(|x| (0..8).map(move |y| zxy_to_tile_id(3, x, y)))
+            .collect();
+        assert_eq!(ids, (21..85).collect());
+    }
+
+    #[test]
+    fn handles_the_deepest_zoom() {
+        let last = (1u32 << 31) - 1;
+        assert_eq!(zxy_to_tile_id(31, 0, 0), ((1u64 << 62) - 1) / 3);
+        assert!(zxy_to_tile_id(31, last, 0) < u64::MAX / 3);
+    }
+}
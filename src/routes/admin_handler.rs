@@ -0,0 +1,95 @@
+use crate::cache::cache_key::CacheKeyFilter;
+use crate::dep::AppState;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::json;
+
+const DEFAULT_MAX_ZOOM: u32 = 24;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PurgeQuery {
+    layer: Option<String>,
+    /// `min_x,min_y,max_x,max_y` in longitude/latitude
+    bbox: Option<String>,
+    #[serde(default)]
+    min_zoom: u32,
+    max_zoom: Option<u32>,
+    x: Option<u32>,
+    y: Option<u32>,
+    z: Option<u32>,
+}
+
+fn parse_bbox(bbox: &str) -> Option<[f64; 4]> {
+    let values: Vec<f64> = bbox
+        .split(',')
+        .map(|value| value.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    let bbox: [f64; 4] = values.try_into().ok()?;
+    bbox.iter().all(|value| value.is_finite()).then_some(bbox)
+}
+
+/// Compares the whole token regardless of where it differs, so timing doesn't reveal it
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+/// Checks the `Authorization: Bearer` token against `ADMIN_TOKEN`. Without a configured token
+/// the admin endpoints don't exist.
+fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(expected) = &state.config.admin_token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if token_matches(expected, given) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Purges cached tiles of a layer: a single tile, the tiles covering a bbox across a zoom range,
+/// or all of them. Without a layer the tile or bbox is purged from every layer.
+pub async fn purge_cache(
+    State(state): State<AppState>,
+    Query(query): Query<PurgeQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(status) = authorize(&state, &headers) {
+        return status.into_response();
+    }
+
+    let layer = query.layer.as_deref();
+    let filter = match (query.x, query.y, query.z, &query.bbox) {
+        (Some(x), Some(y), Some(z), _) => CacheKeyFilter::tile(layer, x, y, z),
+        (None, None, None, Some(bbox)) => {
+            let Some(bbox) = parse_bbox(bbox) else {
+                return (StatusCode::BAD_REQUEST, "invalid bbox").into_response();
+            };
+            let max_zoom = query.max_zoom.unwrap_or(DEFAULT_MAX_ZOOM);
+            CacheKeyFilter::bbox(layer, &bbox, query.min_zoom, max_zoom)
+        }
+        (None, None, None, None) if layer.is_some() => CacheKeyFilter::layer(layer),
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "expected a layer, a bbox or x, y and z",
+            )
+                .into_response()
+        }
+    };
+
+    let purged = state.cache.purge(&filter).await;
+    tracing::info!("purged {} cached tiles", purged);
+    Json(json!({ "purged": purged })).into_response()
+}
@@ -0,0 +1,80 @@
+use crate::dep::AppState;
+use crate::export::export_format::ExportFormat;
+use crate::export::export_query_constructor::{bbox_to_wkt, polygon_to_wkt};
+use crate::export::export_service::{ExportLayer, ExportService};
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExportQuery {
+    bbox: Option<String>,
+    polygon: Option<String>,
+    #[serde(default)]
+    format: ExportFormat,
+    query: Option<String>,
+    #[serde(alias = "geoCol")]
+    geo_col: Option<String>,
+    srid: Option<String>,
+}
+
+fn bad_request(message: &str) -> Response {
+    (StatusCode::BAD_REQUEST, message.to_string()).into_response()
+}
+
+pub async fn export_layer(
+    State(state): State<AppState>,
+    Path(layer): Path<String>,
+    Query(params): Query<ExportQuery>,
+) -> impl IntoResponse {
+    let area = match (params.polygon, params.bbox.as_deref()) {
+        (Some(polygon), _) => match polygon_to_wkt(&polygon) {
+            Some(area) => area,
+            None => return bad_request("polygon must be a WKT polygon or multipolygon"),
+        },
+        (None, Some(bbox)) => match bbox_to_wkt(bbox) {
+            Some(area) => area,
+            None => return bad_request("bbox must be min_x,min_y,max_x,max_y"),
+        },
+        (None, None) => return bad_request("a bbox or polygon is required"),
+    };
+
+    let layer_config = state.config.get_layer_config(&layer);
+    let (Some(query), Some(geo_col)) = (
+        params.query.or(layer_config.query.clone()),
+        params.geo_col.or(layer_config.geo_col.clone()),
+    ) else {
+        return bad_request("the layer has no query and geometry column");
+    };
+    let srid = params
+        .srid
+        .or(layer_config.srid.clone())
+        .unwrap_or("4326".to_string());
+
+    let format = params.format;
+    let filename: String = layer
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    let filename = format!("{}.{}", filename, format.extension());
+    let export_layer = ExportLayer {
+        name: layer,
+        config: layer_config,
+        query,
+        geo_col,
+        srid,
+    };
+    let stream = ExportService::new(state.pool).export(export_layer, area, format);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .body(Body::from_stream(stream))
+        .unwrap()
+}
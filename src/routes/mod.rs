@@ -1 +1,5 @@
+pub mod admin_handler;
+pub mod export_handler;
 pub mod mvt_handler;
+pub mod pmtiles_handler;
+pub mod tile_json_handler;
@@ -1,12 +1,26 @@
+use crate::cache::cache_key::CacheKey;
+use crate::cache::cache_policy::{
+    cache_control, cache_encodings, cache_ttl, retention, stale_if_error, stale_while_revalidate,
+    tile_ttl,
+};
+use crate::cache::cached_tile::CachedTile;
+use crate::cache::content_encoding::ContentEncoding;
+use crate::cache::redis_lock::Lock;
+use crate::config::{Config, LayerConfig};
 use crate::dep::AppState;
-use crate::tiling::tile_service::TileService;
+use crate::tiling::tile_budget::BudgetReport;
+use crate::tiling::tile_error::TileError;
+use crate::tiling::tile_format::TileFormat;
+use crate::tiling::tile_service::{empty_tile, TileLayer, TileService};
+use crate::tiling::tile_source::TileSource;
 use axum::body::Body;
 use axum::extract::{Path, Query, State};
-use axum::http::{header, StatusCode};
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::time::Duration;
 
 pub fn internal_error<E>(err: E) -> (StatusCode, String)
 where
@@ -19,6 +33,27 @@ fn default_srid() -> String {
     "4326".to_string()
 }
 
+fn default_layer() -> String {
+    "default".to_string()
+}
+
+/// `Warning` of a stale tile served while it is rendered again
+const STALE_WARNING: &str = "110 - \"Response is Stale\"";
+
+/// `Warning` of a stale tile served because rendering it again failed
+const REVALIDATION_FAILED_WARNING: &str = "111 - \"Revalidation Failed\"";
+
+/// Rendered tile shared with every request waiting for it
+pub type TileResult = Result<CachedTile, StatusCode>;
+
+/// What is needed to render a tile again when its data changes
+#[derive(Clone, Debug)]
+pub struct TileRequest {
+    params: MVTCoordinates,
+    query: MVTQuery,
+    format: TileFormat,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MVTCoordinates {
     x: u32,
@@ -26,32 +61,90 @@ pub struct MVTCoordinates {
     z: u32,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct MVTPath {
+    x: u32,
+    y: u32,
+    z: String,
+}
+
+impl MVTPath {
+    /// Splits an optional format extension such as `.geojson` off the zoom segment
+    fn parse(&self) -> Option<(MVTCoordinates, Option<TileFormat>)> {
+        let (z, extension) = match self.z.split_once('.') {
+            Some((z, extension)) => (z, Some(extension)),
+            None => (self.z.as_str(), None),
+        };
+
+        let format = match extension {
+            Some(extension) => Some(TileFormat::from_extension(extension)?),
+            None => None,
+        };
+
+        let coordinates = MVTCoordinates {
+            x: self.x,
+            y: self.y,
+            z: z.parse().ok()?,
+        };
+        Some((coordinates, format))
+    }
+}
+
 #[derive(Clone, Deserialize, PartialEq, Debug)]
 pub struct MVTQuery {
-    query: String,
+    query: Option<String>,
     #[serde(alias = "geoCol")]
-    geo_col: String,
-    #[serde(default = "default_srid")]
-    srid: String,
+    geo_col: Option<String>,
+    srid: Option<String>,
+    /// One layer or several comma separated layers combined into one tile
+    #[serde(default = "default_layer")]
+    layer: String,
+}
+
+impl MVTQuery {
+    pub fn layers(&self) -> Vec<&str> {
+        self.layer
+            .split(',')
+            .map(str::trim)
+            .filter(|layer| !layer.is_empty())
+            .collect()
+    }
 }
 
 struct MVTBody {
+    status: StatusCode,
     data: Bytes,
-    cache_header: Option<String>,
+    encoding: ContentEncoding,
+    etag: String,
+    format: TileFormat,
+    cache_header: String,
+    budget: Option<BudgetReport>,
+    warning: Option<&'static str>,
 }
 
 impl IntoResponse for MVTBody {
     fn into_response(self) -> Response {
-        Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, "application/protobuf")
-            .header(
-                header::CACHE_CONTROL,
-                self.cache_header
-                    .unwrap_or("private, max-age=300".to_string()),
-            )
-            .body(Body::from(self.data))
-            .unwrap()
+        let mut builder = Response::builder()
+            .status(self.status)
+            .header(header::CONTENT_TYPE, self.format.content_type())
+            .header(header::VARY, "Accept, Accept-Encoding")
+            .header(header::ETAG, self.etag)
+            .header(header::CACHE_CONTROL, self.cache_header);
+
+        if let Some(encoding) = self.encoding.header_value() {
+            builder = builder.header(header::CONTENT_ENCODING, encoding);
+        }
+        if let Some(warning) = self.warning {
+            builder = builder.header(header::WARNING, warning);
+        }
+        if let Some(budget) = self.budget.filter(BudgetReport::is_reduced) {
+            builder = builder
+                .header("X-Tile-Dropped-Features", budget.dropped_features)
+                .header("X-Tile-Simplify-Scale", budget.simplify_scale)
+                .header("X-Tile-Cluster-Scale", budget.cluster_scale);
+        }
+
+        builder.body(Body::from(self.data)).unwrap()
     }
 }
 
@@ -62,50 +155,490 @@ fn calculate_hash(t: &str) -> String {
     format!("{:x}", digest)
 }
 
-fn get_cache_key(coordinates: &MVTCoordinates, query: &MVTQuery) -> String {
-    let as_string = format!("{:?}{:?}", coordinates, query);
-    calculate_hash(&as_string)
+/// Combined layers are keyed by their parsed names, so spacing in the layer list does not split
+/// a tile into several entries or hide it from purges of one of its layers
+fn get_cache_key(coordinates: &MVTCoordinates, query: &MVTQuery, format: TileFormat) -> CacheKey {
+    let layer = query.layers().join(",");
+    let query = MVTQuery {
+        layer: layer.clone(),
+        ..query.clone()
+    };
+    let as_string = format!("{:?}{:?}", query, format);
+    CacheKey::new(
+        &layer,
+        coordinates.x,
+        coordinates.y,
+        coordinates.z,
+        calculate_hash(&as_string),
+    )
 }
 
 pub async fn get_tile(
-    State(mut state): State<AppState>,
-    Path(params): Path<MVTCoordinates>,
+    State(state): State<AppState>,
+    Path(path): Path<MVTPath>,
     Query(query): Query<MVTQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    let cache_key = get_cache_key(&params, &query);
+    let Some((params, extension_format)) = path.parse() else {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::empty())
+            .unwrap();
+    };
+
+    let format = extension_format
+        .or_else(|| {
+            headers
+                .get(header::ACCEPT)
+                .and_then(|accept| accept.to_str().ok())
+                .and_then(TileFormat::from_accept)
+        })
+        .unwrap_or_default();
 
-    if let Some(value) = state.cache.get_bytes(&cache_key) {
-        let bytes = Bytes::from(value);
-        return MVTBody {
-            data: bytes,
-            cache_header: state.config.cache_control_header,
+    let cache_key = get_cache_key(&params, &query, format);
+    let request = TileRequest {
+        params,
+        query,
+        format,
+    };
+    if let Some(hot_tiles) = &state.hot_tiles {
+        hot_tiles.touch(&cache_key, || request.clone());
+    }
+    let ttl = layers_cache_ttl(&state.config, &request.query, request.params.z);
+
+    let response = TileResponse {
+        format,
+        // tiles cached compressed before compression was disabled are sent uncompressed as well
+        accept_encoding: headers
+            .get(header::ACCEPT_ENCODING)
+            .filter(|_| !state.config.disable_gzip.unwrap_or(false))
+            .and_then(|accept_encoding| accept_encoding.to_str().ok()),
+        if_none_match: headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|if_none_match| if_none_match.to_str().ok()),
+        cache_control_header: state.config.cache_control_header.as_deref(),
+        empty_status: empty_tile_status(&state.config),
+    };
+
+    let cached = state.cache.get(&cache_key).await;
+    if let Some(tile) = &cached {
+        let age = tile.age();
+        let tile_ttl = tile_ttl(&state.config, tile, ttl);
+        if age < tile_ttl {
+            return response.fresh(tile, tile_ttl - age);
+        }
+        if age < tile_ttl + stale_while_revalidate(&state.config) {
+            let (state, cache_key) = (state.clone(), cache_key.clone());
+            tokio::spawn(async move { refresh_tile(&state, &cache_key, &request).await });
+            return response.stale(tile, STALE_WARNING);
         }
-        .into_response();
     }
 
-    let tile_service = TileService::new(&state.pool);
-    let result = tile_service
-        .get_tile(
-            params.x,
-            params.y,
-            params.z,
-            &query.query,
-            &query.geo_col,
-            &query.srid,
+    let result = state
+        .flights
+        .run(&cache_key, || {
+            render_and_cache(&state, &request, &cache_key, ttl)
+        })
+        .await;
+
+    match result {
+        Ok(tile) => {
+            let tile_ttl = tile_ttl(&state.config, &tile, ttl);
+            response.fresh(&tile, tile_ttl)
+        }
+        Err(status) => match cached {
+            Some(tile)
+                if status.is_server_error()
+                    && tile.age()
+                        < tile_ttl(&state.config, &tile, ttl) + stale_if_error(&state.config) =>
+            {
+                response.stale(&tile, REVALIDATION_FAILED_WARNING)
+            }
+            _ => Response::builder()
+                .status(status)
+                .body(Body::empty())
+                .unwrap(),
+        },
+    }
+}
+
+/// What a tile response depends on besides the tile
+struct TileResponse<'a> {
+    format: TileFormat,
+    accept_encoding: Option<&'a str>,
+    if_none_match: Option<&'a str>,
+    cache_control_header: Option<&'a str>,
+    /// Status of empty MVT and MLT tiles, which are sent without a body
+    empty_status: StatusCode,
+}
+
+impl TileResponse<'_> {
+    /// A tile clients and proxies may keep for the time it stays fresh in the cache
+    fn fresh(&self, tile: &CachedTile, fresh_for: Duration) -> Response {
+        self.body(
+            tile,
+            cache_control(self.cache_control_header, fresh_for),
+            None,
+        )
+    }
+
+    /// A tile past its TTL, which clients and proxies must not store
+    fn stale(&self, tile: &CachedTile, warning: &'static str) -> Response {
+        self.body(
+            tile,
+            cache_control(self.cache_control_header, Duration::ZERO),
+            Some(warning),
         )
+    }
+
+    fn body(
+        &self,
+        tile: &CachedTile,
+        cache_header: String,
+        warning: Option<&'static str>,
+    ) -> Response {
+        let encoding = tile.negotiate(self.accept_encoding);
+        let etag = tile.etag(encoding);
+        if self
+            .if_none_match
+            .is_some_and(|if_none_match| etag_matches(if_none_match, &etag))
+        {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(header::ETAG, etag)
+                .header(header::VARY, "Accept, Accept-Encoding")
+                .header(header::CACHE_CONTROL, cache_header)
+                .body(Body::empty())
+                .unwrap();
+        }
+
+        match tile.data(encoding) {
+            Ok(data) => {
+                let (status, data) = match tile.is_empty() {
+                    false => (StatusCode::OK, data),
+                    true => match empty_tile(self.format) {
+                        Some(empty) => (StatusCode::OK, Bytes::from(empty)),
+                        None => (self.empty_status, data),
+                    },
+                };
+                MVTBody {
+                    status,
+                    data,
+                    encoding,
+                    etag,
+                    format: self.format,
+                    cache_header,
+                    budget: tile.budget.clone(),
+                    warning,
+                }
+                .into_response()
+            }
+            Err(error) => {
+                tracing::warn!("failed to decompress a cached tile: {}", error);
+                Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Body::empty())
+                    .unwrap()
+            }
+        }
+    }
+}
+
+/// `EMPTY_TILE_STATUS` of 200 sends empty MVT and MLT tiles as zero-length tiles, otherwise they
+/// are 204s
+fn empty_tile_status(config: &Config) -> StatusCode {
+    match config.empty_tile_status {
+        Some(200) => StatusCode::OK,
+        _ => StatusCode::NO_CONTENT,
+    }
+}
+
+/// Whether an `If-None-Match` header lists the entity tag, comparing weakly as required for it
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Renders a tile again after it was purged or went stale, so the next request finds it fresh
+pub async fn refresh_tile(state: &AppState, cache_key: &CacheKey, request: &TileRequest) {
+    let ttl = layers_cache_ttl(&state.config, &request.query, request.params.z);
+    let result = state
+        .flights
+        .run(cache_key, || {
+            render_and_cache(state, request, cache_key, ttl)
+        })
         .await;
+    if let Err(status) = result {
+        tracing::debug!("failed to refresh {}: {}", cache_key, status);
+    }
+}
 
-    if let Ok(bytes) = result {
-        state.cache.set(&cache_key, &bytes);
-        MVTBody {
-            data: Bytes::from(bytes),
-            cache_header: state.config.cache_control_header,
+/// Renders a tile missing from the cache or stale and caches it. With `CACHE_LOCK` only one
+/// instance renders the tile while the others wait for it to be cached.
+async fn render_and_cache(
+    state: &AppState,
+    request: &TileRequest,
+    cache_key: &CacheKey,
+    ttl: Duration,
+) -> TileResult {
+    let lock = state.cache.lock(cache_key).await;
+    if let Lock::Held = lock {
+        if let Some(tile) = state
+            .cache
+            .wait_for(cache_key, |tile| tile_ttl(&state.config, tile, ttl))
+            .await
+        {
+            return Ok(tile);
         }
-        .into_response()
-    } else {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(Body::empty())
+    }
+
+    let result = get_layers_tile(state, &request.params, &request.query, request.format)
+        .await
+        .map(|(data, budget)| CachedTile::new(&data, &cache_encodings(&state.config), budget));
+    if let Ok(tile) = &result {
+        state
+            .cache
+            .set(
+                cache_key,
+                tile,
+                retention(&state.config, tile_ttl(&state.config, tile, ttl)),
+            )
+            .await;
+    }
+    state.cache.unlock(cache_key, lock).await;
+    result
+}
+
+/// A combined tile is cached as long as its shortest lived layer
+fn layers_cache_ttl(config: &Config, query: &MVTQuery, zoom: u32) -> Duration {
+    query
+        .layers()
+        .iter()
+        .map(|layer| cache_ttl(config, &config.get_layer_config(layer), zoom))
+        .min()
+        .unwrap_or_else(|| cache_ttl(config, &LayerConfig::default(), zoom))
+}
+
+/// Renders every requested layer and joins them into one tile. MVT layers are independent
+/// messages in the tile, so encoded layers can be concatenated; other formats take a single layer.
+/// The tile is empty when no layer has features in it.
+async fn get_layers_tile(
+    state: &AppState,
+    params: &MVTCoordinates,
+    query: &MVTQuery,
+    format: TileFormat,
+) -> Result<(Vec<u8>, Option<BudgetReport>), StatusCode> {
+    let layers = query.layers();
+    if layers.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if layers.len() > 1 && format != TileFormat::Mvt {
+        return Err(StatusCode::NOT_ACCEPTABLE);
+    }
+
+    let tiles = futures::future::join_all(
+        layers
+            .iter()
+            .map(|layer| get_layer_tile(state, params, query, layer, format)),
+    )
+    .await;
+
+    let mut data = Vec::new();
+    let mut budget: Option<BudgetReport> = None;
+    for tile in tiles {
+        let Some((layer_data, layer_budget)) = tile? else {
+            continue;
+        };
+        data.extend(layer_data);
+        if let Some(layer_budget) = layer_budget.filter(BudgetReport::is_reduced) {
+            match &mut budget {
+                Some(budget) => budget.merge(&layer_budget),
+                None => budget = Some(layer_budget),
+            }
+        }
+    }
+    Ok((data, budget))
+}
+
+/// Renders one layer from its static source or the database, `None` when the layer has no
+/// features in the tile or a static source does not hold it
+async fn get_layer_tile(
+    state: &AppState,
+    params: &MVTCoordinates,
+    query: &MVTQuery,
+    layer: &str,
+    format: TileFormat,
+) -> Result<Option<(Vec<u8>, Option<BudgetReport>)>, StatusCode> {
+    match state.static_layers.get(&layer.to_lowercase()) {
+        Some(source) => Ok(get_static_tile(source, params, format)
+            .await?
+            .map(|data| (data, None))),
+        None => get_dynamic_tile(state, params, query, layer, format).await,
+    }
+}
+
+/// Reads a tile from a layer's PMTiles archive or MBTiles file, which only serve MVT tiles
+async fn get_static_tile(
+    source: &TileSource,
+    params: &MVTCoordinates,
+    format: TileFormat,
+) -> Result<Option<Vec<u8>>, StatusCode> {
+    if format != TileFormat::Mvt || !source.is_mvt() {
+        return Err(StatusCode::NOT_ACCEPTABLE);
+    }
+
+    source
+        .get_tile(params.x, params.y, params.z)
+        .await
+        .map_err(|error| {
+            tracing::warn!("failed to read {}: {}", source.path().display(), error);
+            StatusCode::NOT_FOUND
+        })
+}
+
+async fn get_dynamic_tile(
+    state: &AppState,
+    params: &MVTCoordinates,
+    query: &MVTQuery,
+    layer: &str,
+    format: TileFormat,
+) -> Result<Option<(Vec<u8>, Option<BudgetReport>)>, StatusCode> {
+    let layer_config = state.config.get_layer_config(layer);
+    let (Some(sql), Some(geo_col)) = (
+        query.query.as_ref().or(layer_config.query.as_ref()),
+        query.geo_col.as_ref().or(layer_config.geo_col.as_ref()),
+    ) else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    let srid = query
+        .srid
+        .clone()
+        .or(layer_config.srid.clone())
+        .unwrap_or_else(default_srid);
+
+    let layer = TileLayer {
+        name: layer,
+        config: &layer_config,
+        query: sql,
+        geo_col,
+        srid: &srid,
+    };
+    let tile_service = TileService::new(&state.pool);
+    match tile_service
+        .get_tile(params.x, params.y, params.z, &layer, format)
+        .await
+    {
+        Ok(tile) if tile.is_empty() => Ok(None),
+        Ok(tile) => Ok(Some((tile.data, tile.budget))),
+        Err(TileError::DatabaseError(error)) => {
+            tracing::warn!("failed to render a tile of {}: {}", layer.name, error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(layer: &str) -> MVTQuery {
+        MVTQuery {
+            query: None,
+            geo_col: None,
+            srid: None,
+            layer: layer.to_string(),
+        }
+    }
+
+    #[test]
+    fn combined_layers_share_a_key_regardless_of_spacing() {
+        let coordinates = MVTCoordinates { x: 1, y: 2, z: 3 };
+        let key = get_cache_key(&coordinates, &query("roads,water"), TileFormat::Mvt);
+        let spaced = get_cache_key(&coordinates, &query(" roads , water"), TileFormat::Mvt);
+
+        assert_eq!(key.layer, "roads,water");
+        assert_eq!(spaced, key);
+    }
+
+    fn tile_response(format: TileFormat) -> TileResponse<'static> {
+        TileResponse {
+            format,
+            accept_encoding: None,
+            if_none_match: None,
+            cache_control_header: None,
+            empty_status: StatusCode::NO_CONTENT,
+        }
+    }
+
+    async fn body(response: Response) -> Bytes {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
             .unwrap()
     }
+
+    #[tokio::test]
+    async fn empty_binary_tiles_have_no_body() {
+        let tile = CachedTile::new(&[], &[], None);
+        for format in [TileFormat::Mvt, TileFormat::Mlt] {
+            let response = tile_response(format).fresh(&tile, Duration::from_secs(60));
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+            assert!(body(response).await.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_documents_are_sent_in_full() {
+        let tile = CachedTile::new(&[], &[], None);
+
+        let response = tile_response(TileFormat::GeoJson).fresh(&tile, Duration::from_secs(60));
+        assert_eq!(response.status(), StatusCode::OK);
+        let collection: serde_json::Value = serde_json::from_slice(&body(response).await).unwrap();
+        assert_eq!(collection["type"], "FeatureCollection");
+        assert_eq!(collection["features"], serde_json::json!([]));
+
+        let response = tile_response(TileFormat::Arrow).fresh(&tile, Duration::from_secs(60));
+        assert_eq!(response.status(), StatusCode::OK);
+        let data = body(response).await;
+        let reader = arrow_ipc::reader::StreamReader::try_new(data.as_ref(), None).unwrap();
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 0);
+    }
+
+    #[test]
+    fn etags_match_listed_or_weak_tags() {
+        let etag = "\"abc-gzip\"";
+        assert!(etag_matches("\"abc-gzip\"", etag));
+        assert!(etag_matches("\"other\", W/\"abc-gzip\"", etag));
+        assert!(etag_matches("*", etag));
+        assert!(!etag_matches("\"abc\"", etag));
+        assert!(!etag_matches("abc-gzip", etag));
+        assert!(!etag_matches("", etag));
+    }
+
+    #[tokio::test]
+    async fn matching_etags_are_not_modified() {
+        let tile = CachedTile::new(b"tile", &[ContentEncoding::Gzip], None);
+        let etag = tile.etag(ContentEncoding::Gzip);
+        let response = TileResponse {
+            accept_encoding: Some("gzip"),
+            if_none_match: Some(&etag),
+            ..tile_response(TileFormat::Mvt)
+        }
+        .fresh(&tile, Duration::from_secs(60));
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert!(body(response).await.is_empty());
+
+        let response = TileResponse {
+            if_none_match: Some(&etag),
+            ..tile_response(TileFormat::Mvt)
+        }
+        .fresh(&tile, Duration::from_secs(60));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await.as_ref(), b"tile");
+    }
 }
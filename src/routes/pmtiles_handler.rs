@@ -0,0 +1,33 @@
+use crate::dep::AppState;
+use crate::tiling::tile_source::TileSource;
+use axum::body::Body;
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+/// Serves a static layer's PMTiles archive as a file with HTTP range support, for clients that
+/// read archives directly
+pub async fn get_archive(
+    State(state): State<AppState>,
+    Path(layer): Path<String>,
+    request: Request,
+) -> Response {
+    let layer = layer
+        .strip_suffix(".pmtiles")
+        .unwrap_or(&layer)
+        .to_lowercase();
+    let Some(TileSource::Pmtiles(reader)) = state.static_layers.get(&layer).map(AsRef::as_ref)
+    else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap();
+    };
+
+    match ServeFile::new(reader.path()).oneshot(request).await {
+        Ok(response) => response.into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,101 @@
+use crate::dep::AppState;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+fn default_layer() -> String {
+    "default".to_string()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TileJsonQuery {
+    #[serde(default = "default_layer")]
+    layer: String,
+}
+
+/// `{x}/{y}/{z}` tile URL on the host the request was made to
+fn tiles_url(headers: &HeaderMap, layer: &str) -> Option<String> {
+    let host = headers.get(header::HOST)?.to_str().ok()?;
+    let scheme = headers
+        .get("X-Forwarded-Proto")
+        .and_then(|proto| proto.to_str().ok())
+        .unwrap_or("http");
+    Some(format!(
+        "{}://{}/mvt/{{x}}/{{y}}/{{z}}?layer={}",
+        scheme, host, layer
+    ))
+}
+
+/// A zoom of every description, `None` when a description leaves it open
+fn zooms(descriptions: &[Value], key: &str) -> Option<Vec<u64>> {
+    descriptions
+        .iter()
+        .map(|description| description[key].as_u64())
+        .collect()
+}
+
+/// TileJSON of one layer or several comma separated layers served together. Static layers are
+/// described by their MBTiles metadata or PMTiles header, dynamic layers by their name only.
+pub async fn get_tile_json(
+    State(state): State<AppState>,
+    Query(query): Query<TileJsonQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let layers: Vec<&str> = query
+        .layer
+        .split(',')
+        .map(str::trim)
+        .filter(|layer| !layer.is_empty())
+        .collect();
+    if layers.is_empty() {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let Some(url) = tiles_url(&headers, &layers.join(",")) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let mut descriptions = Vec::with_capacity(layers.len());
+    for layer in layers.iter() {
+        let description = match state.static_layers.get(&layer.to_lowercase()) {
+            Some(source) => source.tile_json(),
+            None if state.config.get_layer_config(layer).query.is_some() => json!({
+                "tilejson": "3.0.0",
+                "name": layer,
+                "minzoom": 0,
+                "vector_layers": [{ "id": layer, "fields": {} }],
+            }),
+            None => return StatusCode::NOT_FOUND.into_response(),
+        };
+        descriptions.push(description);
+    }
+
+    let mut tile_json = match descriptions.as_slice() {
+        [description] => description.clone(),
+        _ => {
+            let vector_layers: Vec<Value> = descriptions
+                .iter()
+                .filter_map(|description| description["vector_layers"].as_array())
+                .flatten()
+                .cloned()
+                .collect();
+            let mut tile_json = json!({
+                "tilejson": "3.0.0",
+                "name": layers.join(","),
+                "vector_layers": vector_layers,
+            });
+            if let Some(min_zoom) = zooms(&descriptions, "minzoom") {
+                tile_json["minzoom"] = json!(min_zoom.into_iter().min());
+            }
+            if let Some(max_zoom) = zooms(&descriptions, "maxzoom") {
+                tile_json["maxzoom"] = json!(max_zoom.into_iter().max());
+            }
+            tile_json
+        }
+    };
+    tile_json["tiles"] = json!([url]);
+
+    Json(tile_json).into_response()
+}
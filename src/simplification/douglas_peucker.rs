@@ -0,0 +1,114 @@
+use geo_types::Coord;
+
+fn segment_distance_squared(point: Coord, start: Coord, end: Coord) -> f64 {
+    let dx = end.x - start.x;
+    let dy = end.y - start.y;
+    let length_squared = dx * dx + dy * dy;
+
+    let (x, y) = if length_squared == 0.0 {
+        (start.x, start.y)
+    } else {
+        let t = (((point.x - start.x) * dx + (point.y - start.y) * dy) / length_squared)
+            .clamp(0.0, 1.0);
+        (start.x + t * dx, start.y + t * dy)
+    };
+
+    (point.x - x) * (point.x - x) + (point.y - y) * (point.y - y)
+}
+
+pub fn douglas_peucker(coords: &[Coord], tolerance: f64) -> Vec<Coord> {
+    if coords.len() < 3 {
+        return coords.to_vec();
+    }
+
+    let tolerance_squared = tolerance * tolerance;
+    let mut keep = vec![false; coords.len()];
+    keep[0] = true;
+    keep[coords.len() - 1] = true;
+
+    let mut stack = vec![(0, coords.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_distance = 0.0;
+        let mut max_index = first;
+        for (index, coord) in coords.iter().enumerate().take(last).skip(first + 1) {
+            let distance = segment_distance_squared(*coord, coords[first], coords[last]);
+            if distance > max_distance {
+                max_distance = distance;
+                max_index = index;
+            }
+        }
+
+        if max_distance > tolerance_squared {
+            keep[max_index] = true;
+            stack.push((first, max_index));
+            stack.push((max_index, last));
+        }
+    }
+
+    coords
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(coord, _)| *coord)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::coord;
+
+    fn coords(points: &[(f64, f64)]) -> Vec<Coord> {
+        points.iter().map(|&(x, y)| coord! { x: x, y: y }).collect()
+    }
+
+    #[test]
+    fn keeps_short_lines() {
+        let line = coords(&[(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(douglas_peucker(&line, 10.0), line);
+        assert!(douglas_peucker(&[], 10.0).is_empty());
+    }
+
+    #[test]
+    fn drops_vertices_within_the_tolerance() {
+        let line = coords(&[
+            (0.0, 0.0),
+            (5.0, 0.4),
+            (10.0, 0.0),
+            (15.0, 5.0),
+            (20.0, 0.0),
+        ]);
+        assert_eq!(
+            douglas_peucker(&line, 1.0),
+            coords(&[(0.0, 0.0), (10.0, 0.0), (15.0, 5.0), (20.0, 0.0)])
+        );
+        assert_eq!(douglas_peucker(&line, 0.1), line);
+        assert_eq!(
+            douglas_peucker(&line, 10.0),
+            coords(&[(0.0, 0.0), (20.0, 0.0)])
+        );
+    }
+
+    #[test]
+    fn measures_from_segment_ends() {
+        // the middle vertex projects past the end of the segment, so its distance is to the end
+        let line = coords(&[(0.0, 0.0), (13.0, 0.0), (10.0, 0.0)]);
+        assert_eq!(douglas_peucker(&line, 2.0), line);
+        assert_eq!(
+            douglas_peucker(&line, 4.0),
+            coords(&[(0.0, 0.0), (10.0, 0.0)])
+        );
+    }
+
+    #[test]
+    fn keeps_closed_rings_apart() {
+        let ring = coords(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (0.0, 0.0),
+        ]);
+        assert_eq!(douglas_peucker(&ring, 1.0), ring);
+    }
+}
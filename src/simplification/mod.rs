@@ -0,0 +1,5 @@
+mod douglas_peucker;
+pub mod simplifier;
+pub mod simplify_algorithm;
+mod topology;
+mod visvalingam_whyatt;
@@ -0,0 +1,162 @@
+use crate::mvt::constants::{DEFAULT_EXTENT, DEFAULT_TILE_SIZE};
+use crate::mvt::mapbox_vector_tile::Feature;
+use crate::simplification::douglas_peucker::douglas_peucker;
+use crate::simplification::simplify_algorithm::SimplifyAlgorithm;
+use crate::simplification::topology::TopologyNodes;
+use crate::simplification::visvalingam_whyatt::visvalingam_whyatt;
+use geo_types::{Coord, Geometry, LineString, MultiLineString, MultiPolygon, Polygon};
+
+pub const DEFAULT_SIMPLIFY_TOLERANCE: f64 = 1.0;
+
+fn polygon_rings(geometry: &Geometry) -> Vec<&LineString> {
+    match geometry {
+        Geometry::Polygon(polygon) => [polygon.exterior()]
+            .into_iter()
+            .chain(polygon.interiors())
+            .collect(),
+        Geometry::MultiPolygon(multi_polygon) => multi_polygon
+            .iter()
+            .flat_map(|polygon| [polygon.exterior()].into_iter().chain(polygon.interiors()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Simplifies lines and polygons projected into tile extent coordinates
+pub struct Simplifier {
+    algorithm: SimplifyAlgorithm,
+    tolerance: f64,
+    preserve_topology: bool,
+}
+
+impl Simplifier {
+    /// Returns `None` when the algorithm is not applied in tile space
+    pub fn new(
+        algorithm: SimplifyAlgorithm,
+        pixel_tolerance: Option<f64>,
+        preserve_topology: bool,
+    ) -> Option<Self> {
+        match algorithm {
+            SimplifyAlgorithm::DouglasPeucker | SimplifyAlgorithm::VisvalingamWhyatt => {
+                let pixel_size = DEFAULT_EXTENT as f64 / DEFAULT_TILE_SIZE as f64;
+                Some(Self {
+                    algorithm,
+                    tolerance: pixel_tolerance.unwrap_or(DEFAULT_SIMPLIFY_TOLERANCE) * pixel_size,
+                    preserve_topology,
+                })
+            }
+            SimplifyAlgorithm::Postgis | SimplifyAlgorithm::None => None,
+        }
+    }
+
+    pub fn simplify_features(&self, features: Vec<Feature>) -> Vec<Feature> {
+        let topology = if self.preserve_topology {
+            Some(TopologyNodes::from_rings(
+                features
+                    .iter()
+                    .flat_map(|feature| polygon_rings(&feature.geometry)),
+            ))
+        } else {
+            None
+        };
+
+        features
+            .into_iter()
+            .filter_map(|mut feature| {
+                feature.geometry = self.simplify_geometry(&feature.geometry, topology.as_ref())?;
+                Some(feature)
+            })
+            .collect()
+    }
+
+    pub fn preserves_topology(&self) -> bool {
+        self.preserve_topology
+    }
+
+    /// Simplifies one feature on its own, without shared edge detection
+    pub fn simplify_feature(&self, mut feature: Feature) -> Option<Feature> {
+        feature.geometry = self.simplify_geometry(&feature.geometry, None)?;
+        Some(feature)
+    }
+
+    fn simplify_coords(&self, coords: &[Coord]) -> Vec<Coord> {
+        match self.algorithm {
+            SimplifyAlgorithm::VisvalingamWhyatt => visvalingam_whyatt(coords, self.tolerance),
+            _ => douglas_peucker(coords, self.tolerance),
+        }
+    }
+
+    fn simplify_line_string(&self, line_string: &LineString) -> Option<LineString> {
+        let coords = self.simplify_coords(&line_string.0);
+        if coords.len() < 2 {
+            return None;
+        }
+        Some(LineString(coords))
+    }
+
+    fn simplify_ring(
+        &self,
+        ring: &LineString,
+        topology: Option<&TopologyNodes>,
+    ) -> Option<LineString> {
+        let coords = match topology {
+            Some(topology) => topology.simplify_ring(ring, &|coords| self.simplify_coords(coords)),
+            None => self.simplify_coords(&ring.0),
+        };
+
+        if coords.len() < 4 {
+            return None;
+        }
+        Some(LineString(coords))
+    }
+
+    fn simplify_polygon(
+        &self,
+        polygon: &Polygon,
+        topology: Option<&TopologyNodes>,
+    ) -> Option<Polygon> {
+        let exterior = self.simplify_ring(polygon.exterior(), topology)?;
+        let interiors = polygon
+            .interiors()
+            .iter()
+            .filter_map(|interior| self.simplify_ring(interior, topology))
+            .collect();
+        Some(Polygon::new(exterior, interiors))
+    }
+
+    fn simplify_geometry(
+        &self,
+        geometry: &Geometry,
+        topology: Option<&TopologyNodes>,
+    ) -> Option<Geometry> {
+        match geometry {
+            Geometry::LineString(line_string) => Some(Geometry::LineString(
+                self.simplify_line_string(line_string)?,
+            )),
+            Geometry::MultiLineString(multi_line_string) => {
+                let line_strings: Vec<LineString> = multi_line_string
+                    .iter()
+                    .filter_map(|line_string| self.simplify_line_string(line_string))
+                    .collect();
+                if line_strings.is_empty() {
+                    return None;
+                }
+                Some(Geometry::MultiLineString(MultiLineString(line_strings)))
+            }
+            Geometry::Polygon(polygon) => {
+                Some(Geometry::Polygon(self.simplify_polygon(polygon, topology)?))
+            }
+            Geometry::MultiPolygon(multi_polygon) => {
+                let polygons: Vec<Polygon> = multi_polygon
+                    .iter()
+                    .filter_map(|polygon| self.simplify_polygon(polygon, topology))
+                    .collect();
+                if polygons.is_empty() {
+                    return None;
+                }
+                Some(Geometry::MultiPolygon(MultiPolygon(polygons)))
+            }
+            _ => Some(geometry.clone()),
+        }
+    }
+}
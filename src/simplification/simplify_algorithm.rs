@@ -0,0 +1,15 @@
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SimplifyAlgorithm {
+    /// Simplifies with `ST_Simplify` inside the tile query
+    #[default]
+    Postgis,
+    /// Douglas-Peucker simplification in tile pixel space
+    DouglasPeucker,
+    /// Visvalingam-Whyatt simplification in tile pixel space
+    VisvalingamWhyatt,
+    /// Disables simplification
+    None,
+}
@@ -0,0 +1,89 @@
+use geo_types::{Coord, LineString};
+use std::collections::{HashMap, HashSet};
+
+type CoordKey = (u64, u64);
+
+fn coord_key(coord: &Coord) -> CoordKey {
+    (coord.x.to_bits(), coord.y.to_bits())
+}
+
+fn open_ring(ring: &LineString) -> &[Coord] {
+    if ring.is_closed() && !ring.0.is_empty() {
+        &ring.0[..ring.0.len() - 1]
+    } else {
+        &ring.0
+    }
+}
+
+/// Vertices where polygon rings meet or part ways. Rings are simplified piecewise between these
+/// nodes so an edge shared by neighbouring polygons is simplified identically in both.
+pub struct TopologyNodes {
+    nodes: HashSet<CoordKey>,
+}
+
+impl TopologyNodes {
+    pub fn from_rings<'a>(rings: impl Iterator<Item = &'a LineString>) -> Self {
+        let mut neighbours: HashMap<CoordKey, Vec<CoordKey>> = HashMap::new();
+
+        for ring in rings {
+            let coords = open_ring(ring);
+            let size = coords.len();
+            if size < 3 {
+                continue;
+            }
+
+            for (index, coord) in coords.iter().enumerate() {
+                let entry = neighbours.entry(coord_key(coord)).or_default();
+                for neighbour in [
+                    coords[(index + size - 1) % size],
+                    coords[(index + 1) % size],
+                ] {
+                    let neighbour = coord_key(&neighbour);
+                    if !entry.contains(&neighbour) {
+                        entry.push(neighbour);
+                    }
+                }
+            }
+        }
+
+        // a vertex inside a shared edge has the same two neighbours in every ring it belongs to
+        let nodes = neighbours
+            .into_iter()
+            .filter(|(_, neighbours)| neighbours.len() > 2)
+            .map(|(key, _)| key)
+            .collect();
+
+        Self { nodes }
+    }
+
+    pub fn simplify_ring(
+        &self,
+        ring: &LineString,
+        simplify: &dyn Fn(&[Coord]) -> Vec<Coord>,
+    ) -> Vec<Coord> {
+        let coords = open_ring(ring);
+        let Some(start) = coords
+            .iter()
+            .position(|coord| self.nodes.contains(&coord_key(coord)))
+        else {
+            return simplify(&ring.0);
+        };
+
+        let mut rotated: Vec<Coord> = Vec::with_capacity(coords.len() + 1);
+        rotated.extend_from_slice(&coords[start..]);
+        rotated.extend_from_slice(&coords[..start]);
+        rotated.push(coords[start]);
+
+        let mut simplified = vec![rotated[0]];
+        let mut section_start = 0;
+        for index in 1..rotated.len() {
+            if index == rotated.len() - 1 || self.nodes.contains(&coord_key(&rotated[index])) {
+                let section = simplify(&rotated[section_start..=index]);
+                simplified.extend_from_slice(&section[1..]);
+                section_start = index;
+            }
+        }
+
+        simplified
+    }
+}
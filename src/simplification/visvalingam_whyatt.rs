@@ -0,0 +1,159 @@
+use geo_types::Coord;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+struct Vertex {
+    area: f64,
+    index: usize,
+    previous: usize,
+    next: usize,
+}
+
+impl PartialEq for Vertex {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Vertex {}
+
+impl PartialOrd for Vertex {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Vertex {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .area
+            .total_cmp(&self.area)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+fn triangle_area(a: Coord, b: Coord, c: Coord) -> f64 {
+    ((a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y)) / 2.0).abs()
+}
+
+/// Removes vertices whose effective area is below `tolerance` squared
+pub fn visvalingam_whyatt(coords: &[Coord], tolerance: f64) -> Vec<Coord> {
+    if coords.len() < 3 {
+        return coords.to_vec();
+    }
+
+    let min_area = tolerance * tolerance;
+    let last = coords.len() - 1;
+    let mut previous: Vec<usize> = (0..coords.len()).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (0..coords.len()).map(|i| (i + 1).min(last)).collect();
+    let mut removed = vec![false; coords.len()];
+
+    let mut heap = BinaryHeap::new();
+    for index in 1..last {
+        heap.push(Vertex {
+            area: triangle_area(coords[index - 1], coords[index], coords[index + 1]),
+            index,
+            previous: index - 1,
+            next: index + 1,
+        });
+    }
+
+    while let Some(vertex) = heap.pop() {
+        if vertex.area >= min_area {
+            break;
+        }
+
+        // skip entries made stale by the removal of a neighbour
+        if removed[vertex.index]
+            || previous[vertex.index] != vertex.previous
+            || next[vertex.index] != vertex.next
+        {
+            continue;
+        }
+
+        removed[vertex.index] = true;
+        next[vertex.previous] = vertex.next;
+        previous[vertex.next] = vertex.previous;
+
+        for neighbour in [vertex.previous, vertex.next] {
+            if neighbour == 0 || neighbour == last {
+                continue;
+            }
+
+            let area = triangle_area(
+                coords[previous[neighbour]],
+                coords[neighbour],
+                coords[next[neighbour]],
+            );
+            heap.push(Vertex {
+                // never let a neighbour drop below the area already removed
+                area: area.max(vertex.area),
+                index: neighbour,
+                previous: previous[neighbour],
+                next: next[neighbour],
+            });
+        }
+    }
+
+    coords
+        .iter()
+        .zip(removed)
+        .filter(|(_, removed)| !*removed)
+        .map(|(coord, _)| *coord)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::coord;
+
+    fn coords(points: &[(f64, f64)]) -> Vec<Coord> {
+        points.iter().map(|&(x, y)| coord! { x: x, y: y }).collect()
+    }
+
+    #[test]
+    fn keeps_short_lines() {
+        let line = coords(&[(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(visvalingam_whyatt(&line, 10.0), line);
+        assert!(visvalingam_whyatt(&[], 10.0).is_empty());
+    }
+
+    #[test]
+    fn removes_the_smallest_triangles_first() {
+        // triangles of areas 1 at (2, 1) and 10 at (6, 5)
+        let line = coords(&[(0.0, 0.0), (2.0, 1.0), (4.0, 0.0), (6.0, 5.0), (8.0, 0.0)]);
+        assert_eq!(visvalingam_whyatt(&line, 0.5), line);
+        assert_eq!(
+            visvalingam_whyatt(&line, 2.0),
+            coords(&[(0.0, 0.0), (4.0, 0.0), (6.0, 5.0), (8.0, 0.0)])
+        );
+        assert_eq!(
+            visvalingam_whyatt(&line, 10.0),
+            coords(&[(0.0, 0.0), (8.0, 0.0)])
+        );
+    }
+
+    #[test]
+    fn drops_collinear_vertices() {
+        let line = coords(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]);
+        assert_eq!(
+            visvalingam_whyatt(&line, 0.001),
+            coords(&[(0.0, 0.0), (3.0, 3.0)])
+        );
+    }
+
+    #[test]
+    fn recomputes_neighbours_after_a_removal() {
+        // removing the collinear (3, 0) grows the triangle at (2, 0) from 0.5 to 4, keeping it
+        let line = coords(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (3.0, 0.0), (10.0, 0.0)]);
+        assert_eq!(
+            visvalingam_whyatt(&line, 1.0),
+            coords(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0), (10.0, 0.0)])
+        );
+        assert_eq!(
+            visvalingam_whyatt(&line, 2.5),
+            coords(&[(0.0, 0.0), (10.0, 0.0)])
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::cache::cache_key::CacheKeyFilter;
+use crate::dep::AppState;
+use crate::routes::mvt_handler::refresh_tile;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::Value;
+use sqlx::postgres::PgListener;
+use std::time::Duration;
+
+/// Highest zoom tiles are expired at
+const MAX_EXPIRY_ZOOM: u32 = 24;
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Hot tiles rendered again at the same time after an expiry
+const REFRESH_CONCURRENCY: usize = 4;
+
+/// Notification payload: the changed layer and the changed area as a
+/// `[min_x, min_y, max_x, max_y]` bbox or a GeoJSON geometry in longitude/latitude
+#[derive(Debug, Deserialize)]
+struct ExpiryNotification {
+    layer: String,
+    bbox: Option<[f64; 4]>,
+    geometry: Option<Value>,
+}
+
+/// Grows the bbox by every position in nested GeoJSON coordinate arrays
+fn extend_bbox(coordinates: &Value, bbox: &mut Option<[f64; 4]>) {
+    let Some(values) = coordinates.as_array() else {
+        return;
+    };
+
+    if let [Some(x), Some(y), ..] = [values.first(), values.get(1)].map(|value| value?.as_f64()) {
+        let [min_x, min_y, max_x, max_y] = bbox.get_or_insert([x, y, x, y]);
+        *min_x = min_x.min(x);
+        *min_y = min_y.min(y);
+        *max_x = max_x.max(x);
+        *max_y = max_y.max(y);
+        return;
+    }
+    for value in values {
+        extend_bbox(value, bbox);
+    }
+}
+
+fn geometry_bbox(geometry: &Value) -> Option<[f64; 4]> {
+    let mut bbox = None;
+    match geometry.get("geometries").and_then(Value::as_array) {
+        Some(geometries) => {
+            for geometry in geometries {
+                if let Some(coordinates) = geometry.get("coordinates") {
+                    extend_bbox(coordinates, &mut bbox);
+                }
+            }
+        }
+        None => extend_bbox(geometry.get("coordinates")?, &mut bbox),
+    }
+    bbox
+}
+
+fn parse_notification(payload: &str) -> Option<CacheKeyFilter> {
+    let notification: ExpiryNotification = serde_json::from_str(payload).ok()?;
+    let bbox = notification
+        .bbox
+        .or_else(|| notification.geometry.as_ref().and_then(geometry_bbox))?;
+    Some(CacheKeyFilter::bbox(
+        Some(&notification.layer),
+        &bbox,
+        0,
+        MAX_EXPIRY_ZOOM,
+    ))
+}
+
+async fn expire(state: &AppState, filter: &CacheKeyFilter) {
+    let purged = state.cache.purge(filter).await;
+    tracing::debug!("expired {} cached tiles", purged);
+
+    let Some(hot_tiles) = &state.hot_tiles else {
+        return;
+    };
+    let tiles = hot_tiles.matching(filter);
+    futures::stream::iter(tiles.iter())
+        .for_each_concurrent(REFRESH_CONCURRENCY, |(key, request)| {
+            refresh_tile(state, key, request)
+        })
+        .await;
+}
+
+async fn listen(state: &AppState, channel: &str) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(&state.pool).await?;
+    listener.listen(channel).await?;
+    tracing::info!("expiring tiles on notifications to {}", channel);
+
+    loop {
+        let notification = listener.recv().await?;
+        match parse_notification(notification.payload()) {
+            Some(filter) => expire(state, &filter).await,
+            None => tracing::warn!(
+                "ignoring notification without a layer and bbox or geometry: {}",
+                notification.payload()
+            ),
+        }
+    }
+}
+
+/// Expires the cached tiles covering changes announced with `NOTIFY` on the channel, and renders
+/// the hot ones again when configured. Reconnects after the connection is lost, changes notified
+/// in the meantime are missed.
+pub async fn expire_on_notify(state: AppState, channel: String) {
+    loop {
+        if let Err(error) = listen(&state, &channel).await {
+            tracing::error!("listening to {} failed: {}", channel, error);
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
@@ -0,0 +1,119 @@
+use crate::clustering::clusterer::{get_clusterer, Clusterer};
+use crate::config::LayerConfig;
+use crate::generalization::small_feature_filter::SmallFeatureFilter;
+use crate::mvt::constants::DEFAULT_EXTENT;
+use crate::mvt::geometry_command_encoder::TileProjection;
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature};
+use crate::simplification::simplifier::{Simplifier, DEFAULT_SIMPLIFY_TOLERANCE};
+use crate::simplification::simplify_algorithm::SimplifyAlgorithm;
+use crate::tiling::tile_budget::BudgetReport;
+
+/// Clusters, projects, simplifies and generalizes the features of a tile layer
+pub struct FeaturePipeline {
+    coordinates: Coordinates,
+    clusterer: Option<Box<dyn Clusterer + Send + Sync>>,
+    projection: TileProjection,
+    simplifier: Option<Simplifier>,
+    small_feature_filter: Option<SmallFeatureFilter>,
+}
+
+impl FeaturePipeline {
+    pub fn new(
+        coordinates: &Coordinates,
+        layer_config: &LayerConfig,
+        report: &BudgetReport,
+    ) -> Self {
+        let clusterer = get_clusterer(
+            layer_config.cluster.unwrap_or_default(),
+            layer_config.cluster_radius,
+            report.cluster_scale,
+            layer_config.cluster_max_zoom,
+        );
+
+        let preserve_topology = layer_config.simplify_preserve_topology.unwrap_or(false);
+        let simplifier = if report.simplify_scale > 1 {
+            let algorithm = match layer_config.simplify.unwrap_or_default() {
+                SimplifyAlgorithm::VisvalingamWhyatt => SimplifyAlgorithm::VisvalingamWhyatt,
+                _ => SimplifyAlgorithm::DouglasPeucker,
+            };
+            let tolerance = layer_config
+                .simplify_tolerance
+                .unwrap_or(DEFAULT_SIMPLIFY_TOLERANCE);
+            Simplifier::new(
+                algorithm,
+                Some(tolerance * report.simplify_scale as f64),
+                preserve_topology,
+            )
+        } else {
+            Simplifier::new(
+                layer_config.simplify.unwrap_or_default(),
+                layer_config.simplify_tolerance,
+                preserve_topology,
+            )
+        };
+
+        let small_feature_filter =
+            if coordinates.z <= layer_config.min_feature_max_zoom.unwrap_or(u32::MAX) {
+                SmallFeatureFilter::new(
+                    layer_config.min_polygon_area,
+                    layer_config.min_line_length,
+                    layer_config.replace_small_features.unwrap_or(false),
+                )
+            } else {
+                None
+            };
+
+        Self {
+            coordinates: *coordinates,
+            clusterer,
+            projection: TileProjection::new(coordinates, DEFAULT_EXTENT),
+            simplifier,
+            small_feature_filter,
+        }
+    }
+
+    /// Whether features can be processed one at a time without seeing the rest of the layer
+    pub fn is_streaming(&self) -> bool {
+        self.clusterer.is_none()
+            && !self
+                .simplifier
+                .as_ref()
+                .is_some_and(Simplifier::preserves_topology)
+    }
+
+    pub fn process_feature(&self, mut feature: Feature) -> Option<Feature> {
+        feature.geometry = self.projection.project_geometry(&feature.geometry);
+
+        if let Some(simplifier) = &self.simplifier {
+            feature = simplifier.simplify_feature(feature)?;
+        }
+
+        if let Some(small_feature_filter) = &self.small_feature_filter {
+            feature = small_feature_filter.filter_feature(feature)?;
+        }
+
+        Some(feature)
+    }
+
+    pub fn process_features(&self, features: Vec<Feature>) -> Vec<Feature> {
+        let mut features = features;
+
+        if let Some(clusterer) = &self.clusterer {
+            features = clusterer.cluster(features, &self.coordinates);
+        }
+
+        for feature in features.iter_mut() {
+            feature.geometry = self.projection.project_geometry(&feature.geometry);
+        }
+
+        if let Some(simplifier) = &self.simplifier {
+            features = simplifier.simplify_features(features);
+        }
+
+        if let Some(small_feature_filter) = &self.small_feature_filter {
+            features = small_feature_filter.filter_features(features);
+        }
+
+        features
+    }
+}
@@ -1,3 +1,9 @@
-mod tile_error;
+mod feature_pipeline;
+pub mod tile_budget;
+mod tile_encoder;
+pub mod tile_error;
+pub mod tile_format;
+pub mod tile_pyramid;
 mod tile_query_constructor;
 pub mod tile_service;
+pub mod tile_source;
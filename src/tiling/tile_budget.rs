@@ -0,0 +1,185 @@
+use crate::config::LayerConfig;
+use crate::mvt::constants::{DEFAULT_EXTENT, DEFAULT_TILE_SIZE};
+use crate::mvt::mapbox_vector_tile::Feature;
+use crate::mvt::property_value::PropertyValue;
+use geo_types::{Coord, Geometry};
+use std::collections::HashMap;
+
+const DENSITY_CELL_SIZE: f64 = 64.0;
+
+/// What was given up to fit a tile into its layer budget
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetReport {
+    pub dropped_features: usize,
+    pub simplify_scale: u32,
+    pub cluster_scale: u32,
+}
+
+impl Default for BudgetReport {
+    fn default() -> Self {
+        Self {
+            dropped_features: 0,
+            simplify_scale: 1,
+            cluster_scale: 1,
+        }
+    }
+}
+
+impl BudgetReport {
+    pub fn is_reduced(&self) -> bool {
+        self.dropped_features > 0 || self.simplify_scale > 1 || self.cluster_scale > 1
+    }
+
+    /// Adds up what the layers of a combined tile gave up, keeping the coarsest scales
+    pub fn merge(&mut self, other: &BudgetReport) {
+        self.dropped_features += other.dropped_features;
+        self.simplify_scale = self.simplify_scale.max(other.simplify_scale);
+        self.cluster_scale = self.cluster_scale.max(other.cluster_scale);
+    }
+}
+
+fn first_coord(geometry: &Geometry) -> Option<Coord> {
+    match geometry {
+        Geometry::Point(point) => Some(point.0),
+        Geometry::LineString(line_string) => line_string.0.first().copied(),
+        Geometry::Polygon(polygon) => polygon.exterior().0.first().copied(),
+        Geometry::MultiPoint(multi_point) => multi_point.0.first().map(|point| point.0),
+        Geometry::MultiLineString(multi_line_string) => multi_line_string
+            .0
+            .first()
+            .and_then(|line_string| line_string.0.first().copied()),
+        Geometry::MultiPolygon(multi_polygon) => multi_polygon
+            .0
+            .first()
+            .and_then(|polygon| polygon.exterior().0.first().copied()),
+        _ => None,
+    }
+}
+
+pub struct TileBudget<'a> {
+    max_size: Option<usize>,
+    max_features: Option<usize>,
+    rank_property: Option<&'a str>,
+}
+
+impl<'a> TileBudget<'a> {
+    /// Returns `None` when the layer has neither a size nor a feature limit
+    pub fn new(layer_config: &'a LayerConfig) -> Option<Self> {
+        if layer_config.max_tile_size.is_none() && layer_config.max_features.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            max_size: layer_config.max_tile_size,
+            max_features: layer_config.max_features,
+            rank_property: layer_config.rank_property.as_deref(),
+        })
+    }
+
+    pub fn fits(&self, size: usize) -> bool {
+        self.max_size.is_none_or(|max_size| size <= max_size)
+    }
+
+    pub fn max_features(&self) -> Option<usize> {
+        self.max_features
+    }
+
+    /// Estimates how many features fit based on the size of the last encoding
+    pub fn features_for_size(&self, feature_count: usize, size: usize) -> usize {
+        let Some(max_size) = self.max_size else {
+            return feature_count;
+        };
+        if size == 0 {
+            return feature_count;
+        }
+
+        let estimate = (feature_count as f64 * max_size as f64 / size as f64 * 0.9) as usize;
+        estimate.min(feature_count.saturating_sub(1))
+    }
+
+    /// Keeps the `keep` most important features in their original order. Importance comes from the
+    /// rank property (higher is more important), otherwise features in crowded areas go first.
+    pub fn drop_features(&self, features: Vec<Feature>, keep: usize) -> Vec<Feature> {
+        if features.len() <= keep {
+            return features;
+        }
+
+        let importance: Vec<f64> = match self.rank_property {
+            Some(rank_property) => features
+                .iter()
+                .map(|feature| {
+                    feature
+                        .properties
+                        .get(rank_property)
+                        .and_then(PropertyValue::as_f64)
+                        .unwrap_or(f64::NEG_INFINITY)
+                })
+                .collect(),
+            None => {
+                let cell_size =
+                    DENSITY_CELL_SIZE * DEFAULT_EXTENT as f64 / DEFAULT_TILE_SIZE as f64;
+                let mut cell_counts: HashMap<(i64, i64), usize> = HashMap::new();
+                features
+                    .iter()
+                    .map(|feature| {
+                        let Some(coord) = first_coord(&feature.geometry) else {
+                            return f64::NEG_INFINITY;
+                        };
+                        let cell = (
+                            (coord.x / cell_size).floor() as i64,
+                            (coord.y / cell_size).floor() as i64,
+                        );
+                        let count = cell_counts.entry(cell).or_default();
+                        *count += 1;
+                        -(*count as f64)
+                    })
+                    .collect()
+            }
+        };
+
+        let mut order: Vec<usize> = (0..features.len()).collect();
+        order.sort_by(|a, b| importance[*b].total_cmp(&importance[*a]));
+
+        let mut kept = vec![false; features.len()];
+        for index in order.into_iter().take(keep) {
+            kept[index] = true;
+        }
+
+        features
+            .into_iter()
+            .zip(kept)
+            .filter(|(_, kept)| *kept)
+            .map(|(feature, _)| feature)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_reports_add_dropped_features_and_keep_the_coarsest_scales() {
+        let mut report = BudgetReport {
+            dropped_features: 3,
+            simplify_scale: 4,
+            cluster_scale: 1,
+        };
+        report.merge(&BudgetReport {
+            dropped_features: 5,
+            simplify_scale: 2,
+            cluster_scale: 8,
+        });
+
+        assert_eq!(
+            report,
+            BudgetReport {
+                dropped_features: 8,
+                simplify_scale: 4,
+                cluster_scale: 8,
+            }
+        );
+        assert!(report.is_reduced());
+        assert!(!BudgetReport::default().is_reduced());
+    }
+}
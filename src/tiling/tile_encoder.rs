@@ -0,0 +1,58 @@
+use crate::geoarrow::geoarrow_layer::GeoArrowLayer;
+use crate::geojson::geojson_layer::GeoJsonLayer;
+use crate::mlt::maplibre_tile::MapLibreLayer;
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature, MapboxLayer, MapboxVectorTile};
+use crate::mvt::property_value::PropertyType;
+use crate::tiling::tile_error::TileError;
+use crate::tiling::tile_format::TileFormat;
+use std::collections::BTreeMap;
+
+/// Encodes processed features into the requested output format one feature at a time
+pub enum TileEncoder {
+    Mvt(MapboxLayer),
+    GeoJson(GeoJsonLayer),
+    Mlt(MapLibreLayer),
+    Arrow(GeoArrowLayer),
+}
+
+impl TileEncoder {
+    pub fn new(format: TileFormat, name: &str, coordinates: &Coordinates) -> Self {
+        match format {
+            TileFormat::Mvt => TileEncoder::Mvt(MapboxLayer::new(name.to_string())),
+            TileFormat::GeoJson => TileEncoder::GeoJson(GeoJsonLayer::new(coordinates)),
+            TileFormat::Mlt => TileEncoder::Mlt(MapLibreLayer::new(name.to_string())),
+            TileFormat::Arrow => TileEncoder::Arrow(GeoArrowLayer::new(coordinates)),
+        }
+    }
+
+    /// Types the property columns of columnar formats after the query's columns
+    pub fn declare_columns(&mut self, columns: &BTreeMap<String, PropertyType>) {
+        match self {
+            TileEncoder::Mlt(layer) => layer.declare_columns(columns),
+            TileEncoder::Arrow(layer) => layer.declare_columns(columns),
+            TileEncoder::Mvt(_) | TileEncoder::GeoJson(_) => {}
+        }
+    }
+
+    pub fn add_feature(&mut self, feature: &Feature) {
+        match self {
+            TileEncoder::Mvt(layer) => layer.push_feature(feature),
+            TileEncoder::GeoJson(layer) => layer.add_feature(feature),
+            TileEncoder::Mlt(layer) => layer.push_feature(feature),
+            TileEncoder::Arrow(layer) => layer.add_feature(feature),
+        }
+    }
+
+    pub fn into_bytes(self) -> Result<Vec<u8>, TileError> {
+        match self {
+            TileEncoder::Mvt(layer) => MapboxVectorTile::from_layers(&[layer])
+                .to_bytes()
+                .map_err(|error| TileError::EncodingError(error.to_string())),
+            TileEncoder::GeoJson(layer) => Ok(layer.into_bytes()),
+            TileEncoder::Mlt(layer) => Ok(layer.into_bytes()),
+            TileEncoder::Arrow(layer) => layer
+                .into_bytes()
+                .map_err(|error| TileError::EncodingError(error.to_string())),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TileFormat {
+    #[default]
+    Mvt,
+    GeoJson,
+    Mlt,
+    Arrow,
+}
+
+impl TileFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "mvt" | "pbf" => Some(TileFormat::Mvt),
+            "geojson" | "json" => Some(TileFormat::GeoJson),
+            "mlt" => Some(TileFormat::Mlt),
+            "arrow" | "arrows" => Some(TileFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    /// Picks the first media type of an `Accept` header that is a supported tile format
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        accept.split(',').find_map(|media_type| {
+            let media_type = media_type.split(';').next()?.trim();
+            match media_type {
+                "application/vnd.mapbox-vector-tile"
+                | "application/x-protobuf"
+                | "application/protobuf" => Some(TileFormat::Mvt),
+                "application/geo+json" => Some(TileFormat::GeoJson),
+                "application/vnd.maplibre-tile" => Some(TileFormat::Mlt),
+                "application/vnd.apache.arrow.stream" => Some(TileFormat::Arrow),
+                _ => None,
+            }
+        })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TileFormat::Mvt => "application/protobuf",
+            TileFormat::GeoJson => "application/geo+json",
+            TileFormat::Mlt => "application/vnd.maplibre-tile",
+            TileFormat::Arrow => "application/vnd.apache.arrow.stream",
+        }
+    }
+}
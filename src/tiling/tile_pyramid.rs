@@ -0,0 +1,288 @@
+use crate::config::LayerConfig;
+use crate::geo::geo_utils::get_tile_range;
+use crate::tiling::tile_error::TileError;
+use crate::tiling::tile_format::TileFormat;
+use crate::tiling::tile_service::{TileLayer, TileService};
+use async_trait::async_trait;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{self, Formatter};
+use std::io::Write;
+use std::sync::Arc;
+
+/// Tiles handed to the archive writer at once, and lost at most when interrupted
+const WRITE_BATCH_SIZE: usize = 256;
+
+/// Highest zoom a pyramid is rendered at
+pub const MAX_PYRAMID_ZOOM: u32 = 24;
+
+#[derive(Debug)]
+pub enum PyramidError {
+    Database(sqlx::Error),
+    Tile(TileError),
+    Io(std::io::Error),
+    ZoomRange(u32, u32),
+}
+
+impl Error for PyramidError {}
+
+impl fmt::Display for PyramidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PyramidError::Database(error) => write!(f, "archive database error: {}", error),
+            PyramidError::Tile(error) => write!(f, "failed to render tile: {:?}", error),
+            PyramidError::Io(error) => write!(f, "failed to write tile: {}", error),
+            PyramidError::ZoomRange(min_zoom, max_zoom) => write!(
+                f,
+                "invalid zoom range {}..={}, zooms go from 0 to {} and the minimum can't exceed the maximum",
+                min_zoom, max_zoom, MAX_PYRAMID_ZOOM
+            ),
+        }
+    }
+}
+
+impl From<sqlx::Error> for PyramidError {
+    fn from(error: sqlx::Error) -> Self {
+        PyramidError::Database(error)
+    }
+}
+
+impl From<std::io::Error> for PyramidError {
+    fn from(error: std::io::Error) -> Self {
+        PyramidError::Io(error)
+    }
+}
+
+/// A rendered tile in XYZ coordinates with gzipped MVT data, `None` for an empty tile
+pub struct PyramidTile {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    pub data: Option<Vec<u8>>,
+}
+
+/// Destination of a rendered tile pyramid
+#[async_trait]
+pub trait TileArchiveWriter {
+    /// XYZ columns and rows of the tiles already in the archive at a zoom, skipped when rendering
+    async fn rendered_tiles(&self, z: u32) -> Result<HashSet<(u32, u32)>, PyramidError>;
+
+    async fn write_tiles(&mut self, tiles: &[PyramidTile]) -> Result<(), PyramidError>;
+
+    async fn finish(self) -> Result<(), PyramidError>;
+}
+
+pub struct TilePyramid {
+    pub layer: String,
+    pub config: LayerConfig,
+    pub query: String,
+    pub geo_col: String,
+    pub srid: String,
+    /// `[min_x, min_y, max_x, max_y]` in longitude/latitude
+    pub bbox: [f64; 4],
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+    pub concurrency: usize,
+}
+
+impl TilePyramid {
+    /// Checks the zoom range before anything is written
+    pub fn validate(&self) -> Result<(), PyramidError> {
+        if self.min_zoom > self.max_zoom || self.max_zoom > MAX_PYRAMID_ZOOM {
+            return Err(PyramidError::ZoomRange(self.min_zoom, self.max_zoom));
+        }
+        Ok(())
+    }
+
+    /// Longitude, latitude and zoom at the middle of the pyramid
+    pub fn center(&self) -> (f64, f64, u32) {
+        let [min_x, min_y, max_x, max_y] = self.bbox;
+        ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0, self.min_zoom)
+    }
+
+    /// TileJSON `vector_layers` describing the pyramid's layer
+    pub fn vector_layers(&self) -> Value {
+        json!([{
+            "id": self.layer,
+            "fields": {},
+            "minzoom": self.min_zoom,
+            "maxzoom": self.max_zoom,
+        }])
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PyramidSummary {
+    pub rendered: usize,
+    pub skipped: usize,
+    pub empty: usize,
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+async fn render_tile(
+    pool: PgPool,
+    pyramid: Arc<TilePyramid>,
+    x: u32,
+    y: u32,
+    z: u32,
+) -> Result<PyramidTile, PyramidError> {
+    let layer = TileLayer {
+        name: &pyramid.layer,
+        config: &pyramid.config,
+        query: &pyramid.query,
+        geo_col: &pyramid.geo_col,
+        srid: &pyramid.srid,
+    };
+    let tile = TileService::new(&pool)
+        .get_tile(x, y, z, &layer, TileFormat::Mvt)
+        .await
+        .map_err(PyramidError::Tile)?;
+
+    let data = if tile.is_empty() {
+        None
+    } else {
+        Some(gzip(&tile.data)?)
+    };
+    Ok(PyramidTile { x, y, z, data })
+}
+
+/// Renders every tile of the layer in the bounding box and zoom range into an archive. Tiles the
+/// archive already holds are skipped, so an interrupted export picks up where it stopped.
+pub async fn render_pyramid<W: TileArchiveWriter + Send>(
+    pool: PgPool,
+    pyramid: TilePyramid,
+    mut writer: W,
+) -> Result<PyramidSummary, PyramidError> {
+    pyramid.validate()?;
+    let pyramid = Arc::new(pyramid);
+    let mut summary = PyramidSummary::default();
+    for z in pyramid.min_zoom..=pyramid.max_zoom {
+        let (min_x, min_y, max_x, max_y) = get_tile_range(&pyramid.bbox, z);
+        // the archive may hold tiles outside the bounding box, which aren't skipped from it
+        let mut rendered = writer.rendered_tiles(z).await?;
+        rendered.retain(|(x, y)| (min_x..=max_x).contains(x) && (min_y..=max_y).contains(y));
+        let tile_count = (max_x - min_x + 1) as usize * (max_y - min_y + 1) as usize;
+        tracing::info!(
+            "zoom {}: {} tiles, {} already rendered",
+            z,
+            tile_count,
+            rendered.len()
+        );
+        summary.skipped += rendered.len();
+
+        let tiles = (min_x..=max_x)
+            .flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter(move |tile| !rendered.contains(tile));
+        let mut results = futures::stream::iter(tiles)
+            .map(|(x, y)| tokio::spawn(render_tile(pool.clone(), pyramid.clone(), x, y, z)))
+            .buffer_unordered(pyramid.concurrency.max(1));
+
+        let mut batch = Vec::with_capacity(WRITE_BATCH_SIZE);
+        while let Some(result) = results.next().await {
+            let tile = match result.map_err(|error| PyramidError::Io(error.into())) {
+                Ok(Ok(tile)) => tile,
+                Ok(Err(error)) | Err(error) => {
+                    // keep the finished tiles so a resumed export does not render them again
+                    writer.write_tiles(&batch).await?;
+                    return Err(error);
+                }
+            };
+            summary.rendered += 1;
+            if tile.data.is_none() {
+                summary.empty += 1;
+            }
+            batch.push(tile);
+
+            if batch.len() >= WRITE_BATCH_SIZE {
+                writer.write_tiles(&batch).await?;
+                batch.clear();
+            }
+        }
+        writer.write_tiles(&batch).await?;
+    }
+
+    writer.finish().await?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pyramid(min_zoom: u32, max_zoom: u32) -> TilePyramid {
+        TilePyramid {
+            layer: "roads".to_string(),
+            config: LayerConfig::default(),
+            query: "SELECT * FROM roads".to_string(),
+            geo_col: "geom".to_string(),
+            srid: "4326".to_string(),
+            bbox: [-180.0, -85.0, 180.0, 85.0],
+            min_zoom,
+            max_zoom,
+            concurrency: 1,
+        }
+    }
+
+    #[test]
+    fn accepts_zoom_ranges_up_to_the_highest_zoom() {
+        assert!(pyramid(0, 0).validate().is_ok());
+        assert!(pyramid(3, MAX_PYRAMID_ZOOM).validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_inverted_and_too_deep_zoom_ranges() {
+        assert!(matches!(
+            pyramid(5, 4).validate(),
+            Err(PyramidError::ZoomRange(5, 4))
+        ));
+        assert!(matches!(
+            pyramid(0, 32).validate(),
+            Err(PyramidError::ZoomRange(0, 32))
+        ));
+    }
+
+    /// An archive holding every tile of zoom 1, so resuming renders nothing
+    struct FullArchive;
+
+    #[async_trait]
+    impl TileArchiveWriter for FullArchive {
+        async fn rendered_tiles(&self, _z: u32) -> Result<HashSet<(u32, u32)>, PyramidError> {
+            Ok((0..2).flat_map(|x| (0..2).map(move |y| (x, y))).collect())
+        }
+
+        async fn write_tiles(&mut self, tiles: &[PyramidTile]) -> Result<(), PyramidError> {
+            assert!(tiles.is_empty());
+            Ok(())
+        }
+
+        async fn finish(self) -> Result<(), PyramidError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn only_counts_skipped_tiles_inside_the_bounding_box() {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let pyramid = TilePyramid {
+            // the north west quarter of the world, a single tile at zoom 1
+            bbox: [-170.0, 10.0, -10.0, 80.0],
+            ..pyramid(1, 1)
+        };
+
+        let summary = render_pyramid(pool, pyramid, FullArchive).await.unwrap();
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.rendered, 0);
+    }
+}
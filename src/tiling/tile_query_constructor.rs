@@ -2,31 +2,71 @@ use crate::geo::geo_utils::{get_bounding_box_from_tile, translate_zoom_to_h3_res
 use h3o::Resolution;
 use indoc::indoc;
 
-pub fn get_tile_query(x: u32, y: u32, z: u32, query: &str, geo_col: &str, srid: &str) -> String {
-    let bbox = get_bounding_box_from_tile(x, y, z);
+pub struct TileQueryOptions {
+    pub h3_clustering: bool,
+    pub simplify: bool,
+    /// Tiles beyond the tile edges points are read from, so clusters on an edge are computed
+    /// from the same points on both sides
+    pub point_buffer: f64,
+}
 
-    let envelope = format!(
+fn envelope(x: u32, y: u32, z: u32, buffer: f64, srid: &str) -> String {
+    let bbox = get_bounding_box_from_tile(x, y, z, buffer);
+    format!(
         "ST_MakeEnvelope({min_x:.8}, {min_y:.8}, {max_x:.8}, {max_y:.8}, {srid})",
         min_x = bbox.min.x(),
         min_y = bbox.min.y(),
         max_x = bbox.max.x(),
         max_y = bbox.max.y(),
         srid = srid
-    );
+    )
+}
+
+pub fn get_tile_query(
+    x: u32,
+    y: u32,
+    z: u32,
+    query: &str,
+    geo_col: &str,
+    srid: &str,
+    options: &TileQueryOptions,
+) -> String {
+    let tile_envelope = envelope(x, y, z, 0.0, srid);
+    let filter = if options.point_buffer > 0.0 {
+        // points around the tile are read for clustering, other geometries only inside it
+        format!(
+            "ST_INTERSECTS({buffered}, {geo_col}) AND \
+             (ST_GeometryType({geo_col}) = 'ST_Point' OR ST_INTERSECTS({tile_envelope}, {geo_col}))",
+            buffered = envelope(x, y, z, options.point_buffer, srid),
+        )
+    } else {
+        format!("ST_INTERSECTS({tile_envelope}, {geo_col})")
+    };
 
     let h3_resolution = translate_zoom_to_h3_resolution(z);
 
+    let (collection_geometry, simplified_geometry) = if options.simplify {
+        (
+            format!("ST_CollectionExtract(ST_Simplify({geo_col}, 0.7 / (2 ^ {z}), true))"),
+            format!("ST_Simplify({geo_col}, t.__internal_geometry_simplify__, true)"),
+        )
+    } else {
+        (
+            format!("ST_CollectionExtract({geo_col})"),
+            geo_col.to_string(),
+        )
+    };
+
     let mut raw_query = format!(
         indoc! {r#"
         WITH geometry_type AS (
             SELECT
-                row_to_json(t) as properties,
-                t.{geo_col},
+                t.*,
                 ST_GeometryType({geo_col}) as __internal_geometry_type__,
                 ROUND(0.7 / (2 ^ {zoom})::numeric, 3) as __internal_geometry_simplify__
             FROM ({query}) t
             WHERE
-                ST_INTERSECTS({bbox}, {geo_col})
+                {filter}
         ),
         setup AS (
             SELECT
@@ -35,31 +75,32 @@ pub fn get_tile_query(x: u32, y: u32, z: u32, query: &str, geo_col: &str, srid:
                     WHEN
                         __internal_geometry_type__ = 'ST_GeometryCollection'
                     THEN
-                        ST_CollectionExtract(ST_Simplify({geo_col}, 0.7 / (2 ^ {zoom}), true))
+                        {collection_geometry}
                     WHEN
                         __internal_geometry_type__ = 'ST_Point'
                     THEN
                         {geo_col}
                     ELSE
-                        ST_Simplify({geo_col}, t.__internal_geometry_simplify__, true)
+                        {simplified_geometry}
                 END as __internal_geometry_mapped__,
                 CAST(1 as int8) as h3ClusterCount
             FROM geometry_type t
 		) SELECT *, ST_AsBinary(__internal_geometry_mapped__) as __internal_geometry_bin__ FROM setup
     "#},
         query = query,
-        bbox = envelope,
+        filter = filter,
         geo_col = geo_col,
-        zoom = z
+        zoom = z,
+        collection_geometry = collection_geometry,
+        simplified_geometry = simplified_geometry
     );
 
-    if h3_resolution < Resolution::Fifteen as u32 {
+    if options.h3_clustering && h3_resolution < Resolution::Fifteen as u32 {
         raw_query = format!(
             indoc! {r#"
         WITH geometry_type AS (
 				SELECT
-					row_to_json(t) as properties,
-					t.{geo_col},
+					t.*,
 					ST_GeometryType({geo_col}) as __internal_geometry_type__,
 					ROUND(0.7 / (2 ^ {zoom})::numeric, 3) as __internal_geometry_simplify__
 				FROM ({query}) t
@@ -72,13 +113,13 @@ pub fn get_tile_query(x: u32, y: u32, z: u32, query: &str, geo_col: &str, srid:
 						WHEN
 							__internal_geometry_type__ = 'ST_GeometryCollection'
 						THEN
-							ST_CollectionExtract(ST_Simplify({geo_col}, 0.7 / (2 ^ {zoom}), true))
+							{collection_geometry}
 						WHEN
 							__internal_geometry_type__ = 'ST_Point'
 						THEN
 							{geo_col}
 						ELSE
-							ST_Simplify({geo_col}, t.__internal_geometry_simplify__, true)
+							{simplified_geometry}
 					END as __internal_geometry_mapped__
 				FROM geometry_type t
 			), shapes AS (
@@ -112,10 +153,12 @@ pub fn get_tile_query(x: u32, y: u32, z: u32, query: &str, geo_col: &str, srid:
             FROM points
         "#},
             query = query,
-            bbox = envelope,
+            bbox = tile_envelope,
             geo_col = geo_col,
             zoom = z,
-            h3_resolution = h3_resolution
+            h3_resolution = h3_resolution,
+            collection_geometry = collection_geometry,
+            simplified_geometry = simplified_geometry
         );
     }
 
@@ -1,69 +1,310 @@
-use crate::db::db_types::TileRow;
-use crate::mvt::mapbox_vector_tile::{Coordinates, Feature, MapboxVectorTile};
+use crate::clustering::cluster_algorithm::ClusterAlgorithm;
+use crate::clustering::clusterer::cluster_buffer;
+use crate::config::LayerConfig;
+use crate::db::db_types::{property_types, TileRow};
+use crate::db::property_format::PropertyFormat;
+use crate::db::property_selection::PropertySelection;
+use crate::generalization::property_quantizer::PropertyQuantizer;
+use crate::mvt::constants::DEFAULT_TILE_SIZE;
+use crate::mvt::mapbox_vector_tile::{Coordinates, Feature};
+use crate::mvt::property_value::{PropertyType, PropertyValue};
+use crate::simplification::simplify_algorithm::SimplifyAlgorithm;
+use crate::tiling::feature_pipeline::FeaturePipeline;
+use crate::tiling::tile_budget::{BudgetReport, TileBudget};
+use crate::tiling::tile_encoder::TileEncoder;
 use crate::tiling::tile_error::TileError;
-use crate::tiling::tile_query_constructor::get_tile_query;
-use serde_json::Value;
+use crate::tiling::tile_format::TileFormat;
+use crate::tiling::tile_query_constructor::{get_tile_query, TileQueryOptions};
+use futures::TryStreamExt;
+use geo_types::Geometry;
 use sqlx::PgPool;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+
+/// Rows read for a tile when the layer does not set `max_rows`
+const DEFAULT_MAX_ROWS: usize = 250_000;
+
+/// Number of times simplification and clustering are each doubled before features are dropped
+const MAX_BUDGET_STEPS: u32 = 3;
+
+pub struct TileLayer<'a> {
+    pub name: &'a str,
+    pub config: &'a LayerConfig,
+    pub query: &'a str,
+    pub geo_col: &'a str,
+    pub srid: &'a str,
+}
+
+pub struct RenderedTile {
+    pub data: Vec<u8>,
+    pub feature_count: usize,
+    pub budget: Option<BudgetReport>,
+}
+
+impl RenderedTile {
+    pub fn is_empty(&self) -> bool {
+        self.feature_count == 0
+    }
+}
 
 pub struct TileService<'a> {
     pool: &'a PgPool,
 }
 
+/// Body of a tile without features in formats that always carry a document, an empty
+/// FeatureCollection for GeoJSON and a stream without rows for Arrow. MVT and MLT tiles without
+/// features have no body.
+pub fn empty_tile(format: TileFormat) -> Option<Vec<u8>> {
+    match format {
+        TileFormat::Mvt | TileFormat::Mlt => None,
+        TileFormat::GeoJson | TileFormat::Arrow => {
+            TileEncoder::new(format, "", &Coordinates { x: 0, y: 0, z: 0 })
+                .into_bytes()
+                .ok()
+        }
+    }
+}
+
+fn to_feature(
+    tile_row: TileRow,
+    h3_clustering: bool,
+    property_quantizer: Option<&PropertyQuantizer>,
+) -> Feature {
+    let mut properties = tile_row.properties;
+    if let Some(property_quantizer) = property_quantizer {
+        property_quantizer.quantize(&mut properties);
+    }
+    if h3_clustering {
+        properties.insert(
+            "h3ClusterCount".to_string(),
+            PropertyValue::Int(tile_row.h3_cluster_count),
+        );
+    }
+
+    Feature {
+        geometry: tile_row.geometry_bin.0,
+        properties,
+    }
+}
+
+fn encode_tile(
+    format: TileFormat,
+    name: &str,
+    coordinates: &Coordinates,
+    columns: &BTreeMap<String, PropertyType>,
+    features: Vec<Feature>,
+) -> Result<Vec<u8>, TileError> {
+    let mut encoder = TileEncoder::new(format, name, coordinates);
+    encoder.declare_columns(columns);
+    for feature in features.iter() {
+        encoder.add_feature(feature);
+    }
+    encoder.into_bytes()
+}
+
 impl<'a> TileService<'a> {
     pub fn new(pool: &'a PgPool) -> Self {
         Self { pool }
     }
 
+    /// Runs the tile query with points fetched from `point_buffer` pixels around the tile, handing
+    /// each row to `on_feature` with the property columns typed from the first row
+    async fn fetch_features(
+        &self,
+        layer: &TileLayer<'_>,
+        coordinates: &Coordinates,
+        point_buffer: u32,
+        mut on_feature: impl FnMut(&BTreeMap<String, PropertyType>, Feature),
+    ) -> Result<BTreeMap<String, PropertyType>, TileError> {
+        let layer_config = layer.config;
+        let Coordinates { x, y, z } = *coordinates;
+        let h3_clustering = layer_config.cluster.unwrap_or_default() == ClusterAlgorithm::H3;
+        let simplify_algorithm = layer_config.simplify.unwrap_or_default();
+        let raw_query = get_tile_query(
+            x,
+            y,
+            z,
+            layer.query,
+            layer.geo_col,
+            layer.srid,
+            &TileQueryOptions {
+                h3_clustering,
+                simplify: simplify_algorithm == SimplifyAlgorithm::Postgis,
+                point_buffer: point_buffer as f64 / DEFAULT_TILE_SIZE as f64,
+            },
+        );
+        let max_rows = layer_config.max_rows.unwrap_or(DEFAULT_MAX_ROWS);
+
+        let property_format = PropertyFormat::from_layer_config(layer_config);
+        let property_selection = PropertySelection::for_zoom(layer_config, z);
+        let property_quantizer = PropertyQuantizer::new(
+            layer_config.property_precision.as_ref(),
+            layer_config.float_encoding,
+            layer_config.integer_floats.unwrap_or(false),
+        );
+        let mut rows = sqlx::query(&raw_query).fetch(self.pool);
+        let mut row_count = 0;
+        let mut columns: Option<BTreeMap<String, PropertyType>> = None;
+
+        while let Some(row) = rows
+            .try_next()
+            .await
+            .map_err(|error| TileError::DatabaseError(error.to_string()))?
+        {
+            row_count += 1;
+            if row_count > max_rows {
+                tracing::warn!(
+                    "layer {} tile {}/{}/{} exceeded {} rows, remaining rows were skipped",
+                    layer.name,
+                    z,
+                    x,
+                    y,
+                    max_rows
+                );
+                break;
+            }
+
+            let columns = columns.get_or_insert_with(|| {
+                let mut types =
+                    property_types(&row, layer.geo_col, &property_format, &property_selection);
+                if let Some(property_quantizer) = &property_quantizer {
+                    for property_type in types.values_mut() {
+                        *property_type = property_quantizer.column_type(*property_type);
+                    }
+                }
+                types
+            });
+
+            let tile_row =
+                TileRow::from_row(&row, layer.geo_col, &property_format, &property_selection)
+                    .map_err(|error| TileError::DatabaseError(error.to_string()))?;
+            on_feature(
+                columns,
+                to_feature(tile_row, h3_clustering, property_quantizer.as_ref()),
+            );
+        }
+        Ok(columns.unwrap_or_default())
+    }
+
     pub async fn get_tile(
         &self,
         x: u32,
         y: u32,
         z: u32,
-        query: &str,
-        geo_col: &str,
-        srid: &str,
-    ) -> Result<Vec<u8>, TileError> {
-        let raw_query = get_tile_query(x, y, z, query, geo_col, srid);
-
-        let query_results = sqlx::query_as::<_, TileRow>(&raw_query)
-            .fetch_all(self.pool)
-            .await;
-        if let Err(error) = query_results {
-            return Err(TileError::DatabaseError(error.to_string()));
-        }
+        layer: &TileLayer<'_>,
+        format: TileFormat,
+    ) -> Result<RenderedTile, TileError> {
+        let layer_config = layer.config;
+        let cluster_algorithm = layer_config.cluster.unwrap_or_default();
+        let budget = TileBudget::new(layer_config);
+        let point_buffer = |cluster_scale| {
+            cluster_buffer(
+                cluster_algorithm,
+                layer_config.cluster_radius,
+                cluster_scale,
+                layer_config.cluster_max_zoom,
+                z,
+            )
+        };
 
-        let rows = query_results.unwrap();
+        let coordinates = Coordinates { x, y, z };
+        let pipeline = FeaturePipeline::new(&coordinates, layer_config, &BudgetReport::default());
+
+        // without a budget or layer wide processing each row is encoded as soon as it arrives
+        let streaming = budget.is_none() && pipeline.is_streaming();
+        let mut encoder = TileEncoder::new(format, layer.name, &coordinates);
+        let mut declared = false;
         let mut features: Vec<Feature> = vec![];
-        for tile_row in rows {
-            if let Some(Value::Object(mut properties)) = tile_row.properties {
-                if properties.contains_key(geo_col) {
-                    properties.remove(geo_col);
+        let mut feature_count = 0;
+
+        let columns = self
+            .fetch_features(layer, &coordinates, point_buffer(1), |columns, feature| {
+                if !streaming {
+                    features.push(feature);
+                    return;
+                }
+                if !declared {
+                    encoder.declare_columns(columns);
+                    declared = true;
                 }
+                if let Some(feature) = pipeline.process_feature(feature) {
+                    encoder.add_feature(&feature);
+                    feature_count += 1;
+                }
+            })
+            .await?;
 
-                let h3_cluster_count = serde_json::Number::from(tile_row.h3_cluster_count);
-                properties.insert(
-                    "h3ClusterCount".to_string(),
-                    Value::Number(h3_cluster_count),
-                );
+        if streaming {
+            return Ok(RenderedTile {
+                data: encoder.into_bytes()?,
+                feature_count,
+                budget: None,
+            });
+        }
 
-                let feature = Feature {
-                    geometry: tile_row.geometry_bin.0,
-                    properties: Value::Object(properties),
-                };
+        let Some(budget) = budget else {
+            let features = pipeline.process_features(features);
+            return Ok(RenderedTile {
+                feature_count: features.len(),
+                data: encode_tile(format, layer.name, &coordinates, &columns, features)?,
+                budget: None,
+            });
+        };
 
-                features.push(feature);
-            }
-        }
+        let has_points = features
+            .iter()
+            .any(|feature| matches!(feature.geometry, Geometry::Point(_)));
+        let has_shapes = features.iter().any(|feature| {
+            !matches!(
+                feature.geometry,
+                Geometry::Point(_) | Geometry::MultiPoint(_)
+            )
+        });
+        let can_coarsen_clusters = has_points
+            && matches!(
+                cluster_algorithm,
+                ClusterAlgorithm::Grid | ClusterAlgorithm::Supercluster
+            );
 
-        let mut layer_map: HashMap<String, Vec<Feature>> = HashMap::new();
-        layer_map.insert("default".to_string(), features);
+        let mut report = BudgetReport::default();
+        let mut keep = budget.max_features();
+        loop {
+            let pipeline = FeaturePipeline::new(&coordinates, layer_config, &report);
+            let processed = pipeline.process_features(features.clone());
+            let processed_count = processed.len();
+            let kept = match keep {
+                Some(keep) => budget.drop_features(processed, keep),
+                None => processed,
+            };
+            let kept_count = kept.len();
+            report.dropped_features = processed_count - kept_count;
 
-        let tile = MapboxVectorTile::new(&Coordinates { x, y, z }, &layer_map).await;
+            let data = encode_tile(format, layer.name, &coordinates, &columns, kept)?;
+            if budget.fits(data.len()) || kept_count == 0 {
+                return Ok(RenderedTile {
+                    data,
+                    feature_count: kept_count,
+                    budget: Some(report),
+                });
+            }
 
-        match tile.to_bytes() {
-            Ok(bytes) => Ok(bytes),
-            Err(error) => Err(TileError::EncodingError(error.to_string())),
+            if has_shapes && report.simplify_scale < 1 << MAX_BUDGET_STEPS {
+                report.simplify_scale *= 2;
+            } else if can_coarsen_clusters && report.cluster_scale < 1 << MAX_BUDGET_STEPS {
+                // points were fetched for the layer's radius, coarser clusters need the points
+                // around the tile the coarsest radius reaches
+                let coarsest_buffer = point_buffer(1 << MAX_BUDGET_STEPS);
+                if report.cluster_scale == 1 && coarsest_buffer > point_buffer(1) {
+                    let mut buffered = vec![];
+                    self.fetch_features(layer, &coordinates, coarsest_buffer, |_, feature| {
+                        buffered.push(feature)
+                    })
+                    .await?;
+                    features = buffered;
+                }
+                report.cluster_scale *= 2;
+            } else {
+                keep = Some(budget.features_for_size(kept_count, data.len()));
+            }
         }
     }
 }
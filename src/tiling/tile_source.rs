@@ -0,0 +1,99 @@
+use crate::config::LayerConfig;
+use crate::mbtiles::mbtiles_reader::MbtilesReader;
+use crate::pmtiles::pmtiles_reader::PmtilesReader;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::{self, Formatter};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum TileSourceError {
+    Io(std::io::Error),
+    Database(sqlx::Error),
+}
+
+impl Error for TileSourceError {}
+
+impl fmt::Display for TileSourceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TileSourceError::Io(error) => write!(f, "failed to read archive: {}", error),
+            TileSourceError::Database(error) => write!(f, "failed to read MBTiles: {}", error),
+        }
+    }
+}
+
+impl From<std::io::Error> for TileSourceError {
+    fn from(error: std::io::Error) -> Self {
+        TileSourceError::Io(error)
+    }
+}
+
+impl From<sqlx::Error> for TileSourceError {
+    fn from(error: sqlx::Error) -> Self {
+        TileSourceError::Database(error)
+    }
+}
+
+/// Prerendered tiles a static layer is served from instead of the database
+pub enum TileSource {
+    Pmtiles(PmtilesReader),
+    Mbtiles(MbtilesReader),
+}
+
+impl TileSource {
+    /// Opens the layer's PMTiles archive or MBTiles file, `None` for a dynamic layer
+    pub async fn open(config: &LayerConfig) -> Option<Result<Self, TileSourceError>> {
+        if let Some(path) = &config.pmtiles {
+            return Some(
+                PmtilesReader::open(Path::new(path))
+                    .await
+                    .map(TileSource::Pmtiles)
+                    .map_err(TileSourceError::from),
+            );
+        }
+        if let Some(path) = &config.mbtiles {
+            return Some(
+                MbtilesReader::open(Path::new(path))
+                    .await
+                    .map(TileSource::Mbtiles)
+                    .map_err(TileSourceError::from),
+            );
+        }
+        None
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            TileSource::Pmtiles(reader) => reader.path(),
+            TileSource::Mbtiles(reader) => reader.path(),
+        }
+    }
+
+    pub fn is_mvt(&self) -> bool {
+        match self {
+            TileSource::Pmtiles(reader) => reader.is_mvt(),
+            TileSource::Mbtiles(reader) => reader.is_mvt(),
+        }
+    }
+
+    pub fn tile_json(&self) -> Value {
+        match self {
+            TileSource::Pmtiles(reader) => reader.tile_json(),
+            TileSource::Mbtiles(reader) => reader.tile_json(),
+        }
+    }
+
+    /// Returns the uncompressed tile, `None` when the source does not hold it
+    pub async fn get_tile(
+        &self,
+        x: u32,
+        y: u32,
+        z: u32,
+    ) -> Result<Option<Vec<u8>>, TileSourceError> {
+        match self {
+            TileSource::Pmtiles(reader) => Ok(reader.get_tile(x, y, z).await?),
+            TileSource::Mbtiles(reader) => Ok(reader.get_tile(x, y, z).await?),
+        }
+    }
+}